@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// What a caller may do. Each role can do everything the ones before it can.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Read servers, config files and the audit log; for CI and dashboards.
+    #[default]
+    ReadOnly,
+    /// Also add, update, restore and roll back servers, and register config files.
+    Operator,
+    /// Also delete servers and config files, and manage API keys.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::ReadOnly => "read_only",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = anyhow::Error;
+
+    /// Accepts `read-only` as well, as typed on the command line.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "read_only" | "read-only" => Ok(Role::ReadOnly),
+            "operator" => Ok(Role::Operator),
+            "admin" => Ok(Role::Admin),
+            other => Err(anyhow::anyhow!(
+                "Unknown role '{}'; expected read_only, operator or admin",
+                other
+            )),
+        }
+    }
+}
+
+/// An API key as the API shows it. The secret part of the token is only
+/// returned once, when the key is created, and only its hash is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub key_id: String,
+    /// What the key is for, e.g. `ci` or `grafana`.
+    pub name: String,
+    #[serde(default)]
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+    /// Whoever created the key, as recorded in the audit log.
+    pub created_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Defaults to `read_only`.
+    #[serde(default)]
+    pub role: Role,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_parse_and_rank() {
+        for role in [Role::ReadOnly, Role::Operator, Role::Admin] {
+            assert_eq!(role.as_str().parse::<Role>().unwrap(), role);
+        }
+        assert_eq!("read-only".parse::<Role>().unwrap(), Role::ReadOnly);
+        assert!("superuser".parse::<Role>().is_err());
+        assert!(Role::ReadOnly < Role::Operator && Role::Operator < Role::Admin);
+    }
+}
@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::api_key::ApiKey;
+use crate::config_file::ConfigFile;
+use crate::server_config::ServerConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    Purge,
+    Rollback,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Purge => "purge",
+            AuditAction::Rollback => "rollback",
+        }
+    }
+}
+
+impl FromStr for AuditAction {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "create" => Ok(AuditAction::Create),
+            "update" => Ok(AuditAction::Update),
+            "delete" => Ok(AuditAction::Delete),
+            "restore" => Ok(AuditAction::Restore),
+            "purge" => Ok(AuditAction::Purge),
+            "rollback" => Ok(AuditAction::Rollback),
+            other => Err(anyhow::anyhow!("Unknown audit action '{}'", other)),
+        }
+    }
+}
+
+/// The kind of record an audit event is about.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditTarget {
+    #[default]
+    Server,
+    ConfigFile,
+    ApiKey,
+}
+
+impl AuditTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditTarget::Server => "server",
+            AuditTarget::ConfigFile => "config_file",
+            AuditTarget::ApiKey => "api_key",
+        }
+    }
+}
+
+impl FromStr for AuditTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "server" => Ok(AuditTarget::Server),
+            "config_file" => Ok(AuditTarget::ConfigFile),
+            "api_key" => Ok(AuditTarget::ApiKey),
+            other => Err(anyhow::anyhow!("Unknown audit target '{}'", other)),
+        }
+    }
+}
+
+/// A server, config file or API key as it was before or after a change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AuditRecord {
+    Server(Box<ServerConfig>),
+    ConfigFile(ConfigFile),
+    ApiKey(ApiKey),
+}
+
+impl AuditRecord {
+    /// The server's name, the config file's path or the API key's name.
+    pub fn name(&self) -> &str {
+        match self {
+            AuditRecord::Server(server) => &server.server_name,
+            AuditRecord::ConfigFile(config_file) => &config_file.path,
+            AuditRecord::ApiKey(api_key) => &api_key.name,
+        }
+    }
+}
+
+/// Who changed which record, how, and what it looked like before and after.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub event_id: String,
+    pub recorded_at: DateTime<Utc>,
+    /// Caller identity taken from the authorizer context or API key, or `anonymous`.
+    pub actor: String,
+    pub action: AuditAction,
+    /// Events recorded before config files and API keys were audited are all about servers.
+    #[serde(default)]
+    pub target: AuditTarget,
+    /// ID of the server, config file or API key that changed.
+    #[serde(alias = "server_id")]
+    pub target_id: String,
+    /// API Gateway request ID, or one the service generated, for correlating with its logs.
+    pub request_id: Option<String>,
+    /// `X-Request-Id` header the client sent, if any. Unlike `request_id` it is not
+    /// checked in any way, so it only helps find the request in the client's own logs.
+    #[serde(default)]
+    pub client_request_id: Option<String>,
+    /// The record before the change; `None` for creates.
+    pub before: Option<AuditRecord>,
+    /// The record after the change; `None` for purges and for deleted config files and keys.
+    pub after: Option<AuditRecord>,
+}
@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Flake URI schemes accepted in a flake reference.
+const SCHEMES: &[&str] = &[
+    "github",
+    "gitlab",
+    "sourcehut",
+    "git+https",
+    "git+ssh",
+    "git+http",
+    "git+file",
+    "git",
+    "hg+https",
+    "hg+http",
+    "hg+ssh",
+    "hg+file",
+    "path",
+    "tarball+https",
+    "tarball+http",
+    "tarball+file",
+    "file+https",
+    "file+http",
+    "file",
+    "https",
+    "http",
+    "flake",
+];
+
+/// A host defined in a flake, e.g. `github:ourorg/infra#nixosConfigurations.web-01`,
+/// optionally pinned to a commit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlakeRef {
+    /// Flake URI, without the `#` fragment.
+    pub uri: String,
+    /// Attribute path within the flake, e.g. `nixosConfigurations.web-01`.
+    pub attribute: String,
+    /// Locked git revision, as a full commit hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+}
+
+impl FlakeRef {
+    /// Describes the first problem with the reference, if any.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_uri(&self.uri)?;
+        validate_attribute(&self.attribute)?;
+        if let Some(rev) = &self.rev {
+            let is_hash =
+                matches!(rev.len(), 40 | 64) && rev.chars().all(|c| c.is_ascii_hexdigit());
+            if !is_hash {
+                return Err(format!(
+                    "Flake rev {:?} must be a full 40 or 64 character commit hash",
+                    rev
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats as `uri#attribute`, the form `nixos-rebuild --flake` takes. This is
+/// also what servers store as their `config_file_path`.
+impl fmt::Display for FlakeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.uri, self.attribute)
+    }
+}
+
+fn validate_uri(uri: &str) -> Result<(), String> {
+    if uri.is_empty() {
+        return Err("Flake URI must not be empty".to_string());
+    }
+    if uri.contains('#') || uri.chars().any(char::is_whitespace) {
+        return Err(format!(
+            "Flake URI {:?} must not contain '#' or whitespace",
+            uri
+        ));
+    }
+    // Local paths and indirect registry names (`nixpkgs`) need no scheme
+    if uri.starts_with('/') || uri.starts_with("./") || uri.starts_with("../") {
+        return Ok(());
+    }
+    let Some((scheme, rest)) = uri.split_once(':') else {
+        if uri
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '/' | '.'))
+        {
+            return Ok(());
+        }
+        return Err(format!(
+            "Flake URI {:?} is not a valid flake reference",
+            uri
+        ));
+    };
+    if !SCHEMES.contains(&scheme) {
+        return Err(format!(
+            "Flake URI {:?} has an unsupported scheme '{}'",
+            uri, scheme
+        ));
+    }
+
+    let location = rest.split('?').next().unwrap_or_default();
+    let valid = match scheme {
+        // `owner/repo`, optionally followed by `/ref-or-rev`
+        "github" | "gitlab" | "sourcehut" => {
+            let segments: Vec<&str> = location.split('/').collect();
+            (2..=3).contains(&segments.len()) && segments.iter().all(|segment| !segment.is_empty())
+        }
+        "flake" | "path" => !location.is_empty(),
+        _ => location
+            .strip_prefix("//")
+            .is_some_and(|location| !location.is_empty()),
+    };
+    if !valid {
+        return Err(format!(
+            "Flake URI {:?} is not a valid {} reference",
+            uri, scheme
+        ));
+    }
+    Ok(())
+}
+
+fn validate_attribute(attribute: &str) -> Result<(), String> {
+    let valid = !attribute.is_empty()
+        && attribute.split('.').all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '\''))
+        });
+    if !valid {
+        return Err(format!(
+            "Flake attribute {:?} must be a dot-separated attribute path, e.g. nixosConfigurations.web-01",
+            attribute
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flake(uri: &str, attribute: &str) -> FlakeRef {
+        FlakeRef {
+            uri: uri.to_string(),
+            attribute: attribute.to_string(),
+            rev: None,
+        }
+    }
+
+    #[test]
+    fn flake_refs_are_validated() {
+        for uri in [
+            "github:ourorg/infra",
+            "github:ourorg/infra/main",
+            "git+https://git.example.com/infra?ref=main",
+            "path:/srv/infra",
+            "/srv/infra",
+            "./infra",
+            "nixpkgs",
+        ] {
+            assert_eq!(flake(uri, "nixosConfigurations.web-01").validate(), Ok(()));
+        }
+        for uri in [
+            "",
+            "github:ourorg",
+            "github:ourorg/infra#web",
+            "ftp://example.com/infra",
+            "git+https:",
+            "our org",
+        ] {
+            assert!(flake(uri, "nixosConfigurations.web-01").validate().is_err());
+        }
+        for attribute in ["", "nixosConfigurations.", "nixos configurations"] {
+            assert!(flake("github:ourorg/infra", attribute).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn revs_must_be_full_commit_hashes() {
+        let mut pinned = flake("github:ourorg/infra", "nixosConfigurations.web-01");
+        pinned.rev = Some("a".repeat(40));
+        assert_eq!(pinned.validate(), Ok(()));
+        pinned.rev = Some("abc123".to_string());
+        assert!(pinned.validate().is_err());
+        pinned.rev = Some("g".repeat(40));
+        assert!(pinned.validate().is_err());
+    }
+
+    #[test]
+    fn flake_refs_display_as_uri_and_attribute() {
+        assert_eq!(
+            flake("github:ourorg/infra", "nixosConfigurations.web-01").to_string(),
+            "github:ourorg/infra#nixosConfigurations.web-01"
+        );
+    }
+}
@@ -0,0 +1,10 @@
+//! Request, response and record types of the homelab HTTP API, shared by the
+//! Lambda that serves it and the CLI that calls it.
+
+pub mod api_key;
+pub mod audit_event;
+pub mod config_file;
+pub mod flake_ref;
+pub mod responses;
+pub mod server_config;
+pub mod server_revision;
@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api_key::{ApiKey, Role};
+use crate::audit_event::AuditEvent;
+use crate::config_file::ConfigFile;
+use crate::server_config::ServerConfig;
+use crate::server_revision::ServerRevision;
+
+/// Returned when a server is created, updated or restored.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerWriteResponse {
+    pub message: String,
+    pub server_id: String,
+    /// The server's version after the change, for a later `If-Match`.
+    pub version: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RollbackResponse {
+    pub message: String,
+    pub server_id: String,
+    pub restored_revision: u64,
+    pub version: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteServerResponse {
+    pub message: String,
+    pub server_id: String,
+    /// Whether the server was removed for good rather than moved to the trash.
+    pub purged: bool,
+}
+
+/// One page of `GET /servers`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerListResponse {
+    pub servers: Vec<ServerConfig>,
+    pub count: usize,
+    /// Passed back as `cursor` to fetch the next page; `None` on the last one.
+    pub next_cursor: Option<String>,
+}
+
+/// Servers matching a name, from `GET /servers/by-name/{name}`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerMatchesResponse {
+    pub servers: Vec<ServerConfig>,
+    pub count: usize,
+}
+
+/// Servers using a configuration, from `GET /configs/{config}/servers`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigServersResponse {
+    pub config_file_path: String,
+    pub servers: Vec<ServerConfig>,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerHistoryResponse {
+    pub server_id: String,
+    pub revisions: Vec<ServerRevision>,
+    pub count: usize,
+}
+
+/// One page of `GET /audit`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditLogResponse {
+    pub events: Vec<AuditEvent>,
+    pub count: usize,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigFileListResponse {
+    pub config_files: Vec<ConfigFile>,
+    pub count: usize,
+}
+
+/// Returned when a config file is added or deleted.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigFileWriteResponse {
+    pub message: String,
+    pub config_id: String,
+}
+
+/// Returned once when an API key is created; `token` cannot be retrieved again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyCreatedResponse {
+    pub message: String,
+    pub key: ApiKey,
+    /// Sent as `Authorization: Bearer <token>`.
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyListResponse {
+    pub keys: Vec<ApiKey>,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyDeleteResponse {
+    pub message: String,
+    pub key_id: String,
+}
+
+/// Returned by `GET /whoami`: how the API sees the caller.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WhoAmIResponse {
+    /// How the caller is recorded in the audit log.
+    pub actor: String,
+    pub role: Role,
+    /// `api_key`, `authorizer`, `jwt` or `anonymous`.
+    pub authenticated_by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_name: Option<String>,
+    /// From the authorizer's claims, for callers it authenticated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+}
+
+/// Stable, machine-readable identifier of an error; the message may change,
+/// the code does not.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The body is missing, not JSON, or not the expected shape.
+    InvalidBody,
+    /// The body is larger than the server accepts (413).
+    BodyTooLarge,
+    /// A query parameter or header has an invalid value.
+    InvalidParameter,
+    /// Fields of the body failed validation (422); `details` lists every one.
+    InvalidField,
+    /// No credentials were sent, or they are not valid (401).
+    Unauthorized,
+    /// The caller's role does not allow this (403).
+    Forbidden,
+    RouteNotFound,
+    ServerNotFound,
+    RevisionNotFound,
+    ConfigFileNotFound,
+    ApiKeyNotFound,
+    NameConflict,
+    /// The server is in the trash and must be restored first.
+    ServerDeleted,
+    /// Only servers in the trash can be restored.
+    ServerNotDeleted,
+    ConfigPathConflict,
+    ConfigFileInUse,
+    VersionMismatch,
+    Internal,
+    /// A code this version does not know, or an error that did not come from the API itself.
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidBody => "invalid_body",
+            ErrorCode::BodyTooLarge => "body_too_large",
+            ErrorCode::InvalidParameter => "invalid_parameter",
+            ErrorCode::InvalidField => "invalid_field",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::RouteNotFound => "route_not_found",
+            ErrorCode::ServerNotFound => "server_not_found",
+            ErrorCode::RevisionNotFound => "revision_not_found",
+            ErrorCode::ConfigFileNotFound => "config_file_not_found",
+            ErrorCode::ApiKeyNotFound => "api_key_not_found",
+            ErrorCode::NameConflict => "name_conflict",
+            ErrorCode::ServerDeleted => "server_deleted",
+            ErrorCode::ServerNotDeleted => "server_not_deleted",
+            ErrorCode::ConfigPathConflict => "config_path_conflict",
+            ErrorCode::ConfigFileInUse => "config_file_in_use",
+            ErrorCode::VersionMismatch => "version_mismatch",
+            ErrorCode::Internal => "internal",
+            ErrorCode::Unknown => "unknown",
+        }
+    }
+}
+
+/// What is wrong with one field of a request, or one query parameter or header.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Body of every error response. The optional fields are set by the errors
+/// they describe, so a client can act on a conflict without parsing `error`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    /// Human-readable message.
+    pub error: String,
+    #[serde(default)]
+    pub code: ErrorCode,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+    /// API Gateway request ID, or one the service generated, for finding the request in the logs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Server already using the requested name (409).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub existing_server_id: Option<String>,
+    /// Config file already registered for the requested path (409).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub existing_config_id: Option<String>,
+    /// The server's version when an `If-Match` did not match (412).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_version: Option<u64>,
+    /// Role the request needs, when the caller's role is not enough (403).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_role: Option<Role>,
+    /// Servers still using a config file that was to be deleted (409).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_ids: Option<Vec<String>>,
+}
+
+impl ErrorResponse {
+    pub fn new(code: ErrorCode, error: impl Into<String>) -> Self {
+        Self {
+            error: error.into(),
+            code,
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn error_codes_serialize_as_their_names() {
+        for code in [
+            ErrorCode::InvalidBody,
+            ErrorCode::InvalidField,
+            ErrorCode::RouteNotFound,
+            ErrorCode::ConfigFileInUse,
+            ErrorCode::VersionMismatch,
+            ErrorCode::Unknown,
+        ] {
+            assert_eq!(serde_json::to_value(code).unwrap(), json!(code.as_str()));
+            assert_eq!(
+                serde_json::from_value::<ErrorCode>(json!(code.as_str())).unwrap(),
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn unrecognised_errors_have_the_unknown_code() {
+        let error: ErrorResponse =
+            serde_json::from_value(json!({ "error": "Slow down", "code": "rate_limited" }))
+                .unwrap();
+        assert_eq!(error.code, ErrorCode::Unknown);
+
+        // Errors from API Gateway itself carry no code at all
+        let error: ErrorResponse = serde_json::from_value(json!({ "error": "Forbidden" })).unwrap();
+        assert_eq!(error.code, ErrorCode::Unknown);
+    }
+
+    #[test]
+    fn error_responses_leave_out_unset_fields() {
+        let error = ErrorResponse::new(ErrorCode::ServerNotFound, "Server not found");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({ "error": "Server not found", "code": "server_not_found" })
+        );
+    }
+}
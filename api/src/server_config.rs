@@ -0,0 +1,234 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::flake_ref::FlakeRef;
+
+/// Free-form `key=value` labels, e.g. `role=db` or `site=basement`.
+pub type Labels = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerConfig {
+    pub server_id: String,
+    pub server_name: String,
+    /// Path of the NixOS configuration; copied from the config file when
+    /// `config_id` is set, and holding `uri#attribute` when `flake` is.
+    pub config_file_path: String,
+    /// Config file entity the server uses, if it was assigned one rather than a bare path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_id: Option<String>,
+    /// Flake output the host is built from, for servers configured through a flake.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flake: Option<FlakeRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Caller that created the server, as recorded in the audit log; unset on
+    /// servers created before callers were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    /// Caller that last changed the server, including moving it to or from the trash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_by: Option<String>,
+    /// Incremented on every update; records written before versioning was added have version 0.
+    #[serde(default)]
+    pub version: u64,
+    /// Set while the server is in the trash; it is purged once the retention period has passed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: Labels,
+    /// Further NixOS modules the host imports alongside its configuration, in import order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<String>,
+}
+
+impl ServerConfig {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// Whether the server carries every `key=value` pair in `selector`.
+    pub fn matches_labels(&self, selector: &[(String, String)]) -> bool {
+        selector
+            .iter()
+            .all(|(key, value)| self.labels.get(key) == Some(value))
+    }
+}
+
+/// Parses a `key=value` label or selector. The key must be non-empty; the value may be.
+pub fn parse_label(label: &str) -> Option<(String, String)> {
+    let (key, value) = label.split_once('=')?;
+    (!key.is_empty()).then(|| (key.to_string(), value.to_string()))
+}
+
+/// Returns the first label key that could not be used in a `key=value` selector.
+pub fn invalid_label_key(labels: &Labels) -> Option<&str> {
+    labels
+        .keys()
+        .map(String::as_str)
+        .find(|key| key.is_empty() || key.contains('='))
+}
+
+/// Describes the first problem with a list of module paths: each must be
+/// non-empty and listed only once.
+pub fn invalid_modules(modules: &[String]) -> Option<String> {
+    for (index, module) in modules.iter().enumerate() {
+        if module.is_empty() {
+            return Some("Module paths must not be empty".to_string());
+        }
+        if modules[..index].contains(module) {
+            return Some(format!("Module {:?} is listed more than once", module));
+        }
+    }
+    None
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateServerRequest {
+    pub server_name: String,
+    /// Either a bare path or a `config_id` (or both, if they agree) is required,
+    /// unless the server is configured through a `flake`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_file_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flake: Option<FlakeRef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: Labels,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<String>,
+}
+
+/// Fields left as `None` (or empty, for the module lists) keep their current value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateServerRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_name: Option<String>,
+    /// Setting a path without a `config_id` or `flake` detaches the server from
+    /// its config file or flake.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_file_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flake: Option<FlakeRef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Replaces every label; an empty map removes them all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Labels>,
+    /// Replaces the whole module list; applied before `remove_modules` and `add_modules`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modules: Option<Vec<String>>,
+    /// Modules to append, unless the server already imports them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add_modules: Vec<String>,
+    /// Modules to drop; ones the server does not import are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_modules: Vec<String>,
+}
+
+impl UpdateServerRequest {
+    /// Whether the request touches the server's modules at all.
+    pub fn changes_modules(&self) -> bool {
+        self.modules.is_some() || !self.add_modules.is_empty() || !self.remove_modules.is_empty()
+    }
+
+    /// Applies the module changes to `modules`, keeping the order of those that stay.
+    pub fn apply_modules(&self, modules: &mut Vec<String>) {
+        if let Some(replacement) = &self.modules {
+            modules.clone_from(replacement);
+        }
+        modules.retain(|module| !self.remove_modules.contains(module));
+        for module in &self.add_modules {
+            if !modules.contains(module) {
+                modules.push(module.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_parse_as_key_value() {
+        assert_eq!(
+            parse_label("role=db"),
+            Some(("role".to_string(), "db".to_string()))
+        );
+        // Only the first `=` separates key and value, and the value may be empty
+        assert_eq!(
+            parse_label("query=a=b"),
+            Some(("query".to_string(), "a=b".to_string()))
+        );
+        assert_eq!(
+            parse_label("role="),
+            Some(("role".to_string(), String::new()))
+        );
+        assert_eq!(parse_label("=db"), None);
+        assert_eq!(parse_label("role"), None);
+    }
+
+    #[test]
+    fn invalid_label_keys_are_found() {
+        let mut labels = Labels::new();
+        labels.insert("role".to_string(), "db".to_string());
+        assert_eq!(invalid_label_key(&labels), None);
+        labels.insert("a=b".to_string(), "c".to_string());
+        assert_eq!(invalid_label_key(&labels), Some("a=b"));
+    }
+
+    fn modules(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn module_changes_keep_the_import_order() {
+        let mut current = modules(&["/a.nix", "/b.nix", "/c.nix"]);
+        let changes = UpdateServerRequest {
+            add_modules: modules(&["/a.nix", "/d.nix"]),
+            remove_modules: modules(&["/b.nix", "/missing.nix"]),
+            ..Default::default()
+        };
+        assert!(changes.changes_modules());
+        changes.apply_modules(&mut current);
+        assert_eq!(current, ["/a.nix", "/c.nix", "/d.nix"]);
+
+        // A replacement list is applied before the additions and removals
+        let changes = UpdateServerRequest {
+            modules: Some(modules(&["/d.nix", "/e.nix"])),
+            add_modules: modules(&["/f.nix"]),
+            remove_modules: modules(&["/e.nix"]),
+            ..Default::default()
+        };
+        changes.apply_modules(&mut current);
+        assert_eq!(current, ["/d.nix", "/f.nix"]);
+
+        assert!(!UpdateServerRequest::default().changes_modules());
+    }
+
+    #[test]
+    fn requests_reject_unknown_fields() {
+        let request = serde_json::json!({
+            "server_name": "web-1",
+            "config_file_path": "/etc/nixos/web.nix",
+        });
+        let request: CreateServerRequest = serde_json::from_value(request).unwrap();
+        assert!(request.modules.is_empty());
+
+        let typo =
+            serde_json::json!({ "server_name": "web-1", "config_path": "/etc/nixos/web.nix" });
+        assert!(serde_json::from_value::<CreateServerRequest>(typo).is_err());
+        let typo = serde_json::json!({ "add_module": ["/etc/nixos/common.nix"] });
+        assert!(serde_json::from_value::<UpdateServerRequest>(typo).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::server_config::{ServerConfig, UpdateServerRequest};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    Purge,
+}
+
+impl RevisionAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RevisionAction::Create => "create",
+            RevisionAction::Update => "update",
+            RevisionAction::Delete => "delete",
+            RevisionAction::Restore => "restore",
+            RevisionAction::Purge => "purge",
+        }
+    }
+}
+
+impl FromStr for RevisionAction {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "create" => Ok(RevisionAction::Create),
+            "update" => Ok(RevisionAction::Update),
+            "delete" => Ok(RevisionAction::Delete),
+            "restore" => Ok(RevisionAction::Restore),
+            "purge" => Ok(RevisionAction::Purge),
+            other => Err(anyhow::anyhow!("Unknown revision action '{}'", other)),
+        }
+    }
+}
+
+/// Immutable snapshot of a server recorded on every create, update, delete,
+/// restore and purge.
+///
+/// `revision` equals the server's new `version` and `server` is the record as
+/// written, except for a purge: that is recorded one past the last version with
+/// `server` holding the record as it was just before it was removed for good.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerRevision {
+    pub server_id: String,
+    pub revision: u64,
+    pub action: RevisionAction,
+    pub recorded_at: DateTime<Utc>,
+    pub server: ServerConfig,
+}
+
+impl ServerRevision {
+    pub fn created(server: &ServerConfig) -> Self {
+        Self::new(
+            RevisionAction::Create,
+            server.version,
+            server.created_at,
+            server,
+        )
+    }
+
+    pub fn updated(server: &ServerConfig) -> Self {
+        Self::new(
+            RevisionAction::Update,
+            server.version,
+            server.updated_at,
+            server,
+        )
+    }
+
+    pub fn deleted(server: &ServerConfig) -> Self {
+        Self::new(
+            RevisionAction::Delete,
+            server.version,
+            server.updated_at,
+            server,
+        )
+    }
+
+    pub fn restored(server: &ServerConfig) -> Self {
+        Self::new(
+            RevisionAction::Restore,
+            server.version,
+            server.updated_at,
+            server,
+        )
+    }
+
+    pub fn purged(server: &ServerConfig, purged_at: DateTime<Utc>) -> Self {
+        Self::new(RevisionAction::Purge, server.version + 1, purged_at, server)
+    }
+
+    /// Changes that bring a server back to the fields recorded in this revision.
+    pub fn restore_changes(&self) -> UpdateServerRequest {
+        UpdateServerRequest {
+            server_name: Some(self.server.server_name.clone()),
+            config_file_path: Some(self.server.config_file_path.clone()),
+            config_id: self.server.config_id.clone(),
+            flake: self.server.flake.clone(),
+            description: Some(self.server.description.clone().unwrap_or_default()),
+            labels: Some(self.server.labels.clone()),
+            modules: Some(self.server.modules.clone()),
+            add_modules: Vec::new(),
+            remove_modules: Vec::new(),
+        }
+    }
+
+    fn new(
+        action: RevisionAction,
+        revision: u64,
+        recorded_at: DateTime<Utc>,
+        server: &ServerConfig,
+    ) -> Self {
+        Self {
+            server_id: server.server_id.clone(),
+            revision,
+            action,
+            recorded_at,
+            server: server.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RollbackRequest {
+    /// Revision whose fields should be restored.
+    pub revision: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flake_ref::FlakeRef;
+
+    #[test]
+    fn restore_changes_bring_back_every_field() {
+        let recorded_at = Utc::now();
+        let old = ServerConfig {
+            server_id: "srv-1".to_string(),
+            server_name: "web-1".to_string(),
+            config_file_path: "github:me/nixos#web".to_string(),
+            config_id: None,
+            flake: Some(FlakeRef {
+                uri: "github:me/nixos".to_string(),
+                attribute: "web".to_string(),
+                rev: None,
+            }),
+            description: None,
+            created_at: recorded_at,
+            updated_at: recorded_at,
+            created_by: None,
+            updated_by: None,
+            version: 1,
+            deleted_at: None,
+            labels: [("role".to_string(), "web".to_string())].into(),
+            modules: vec!["/etc/nixos/common.nix".to_string()],
+        };
+        let revision = ServerRevision::created(&old);
+        assert_eq!(revision.revision, 1);
+
+        let mut current = ServerConfig {
+            server_name: "web-2".to_string(),
+            config_file_path: "/etc/nixos/web.nix".to_string(),
+            flake: None,
+            description: Some("Front end".to_string()),
+            labels: Default::default(),
+            modules: vec!["/etc/nixos/other.nix".to_string()],
+            version: 2,
+            ..old.clone()
+        };
+        let changes = revision.restore_changes();
+        changes.apply_modules(&mut current.modules);
+        assert_eq!(current.modules, old.modules);
+        assert_eq!(changes.server_name.as_deref(), Some("web-1"));
+        assert_eq!(changes.config_file_path, Some(old.config_file_path));
+        assert_eq!(changes.flake, old.flake);
+        assert_eq!(changes.labels, Some(old.labels));
+        // An empty description clears the current one
+        assert_eq!(changes.description.as_deref(), Some(""));
+    }
+}
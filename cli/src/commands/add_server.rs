@@ -1,40 +1,109 @@
 use anyhow::Result;
-use serde_json::json;
-use reqwest::Client;
+use homelab_api::flake_ref::FlakeRef;
+use homelab_api::responses::ErrorCode;
+use homelab_api::server_config::CreateServerRequest;
+use homelab_client::HomelabClient;
+
+use super::config_file::resolve_config_id;
+use crate::report::{explain, failed_with_flags, Flags};
+
+/// Flags setting each field of the request, for reporting invalid ones.
+const FLAGS: Flags = &[
+    ("server_name", "--server"),
+    ("config_file_path", "--config-path"),
+    ("config_id", "--config"),
+    ("flake", "--flake"),
+    ("description", "--description"),
+    ("labels", "--label"),
+    ("modules", "--module"),
+];
+
+/// Where a new server's NixOS configuration comes from.
+pub enum ConfigSource {
+    /// A bare path to the configuration file.
+    Path(String),
+    /// A registered config file, by ID or path.
+    ConfigFile(String),
+    /// A flake output, as `uri#attribute`, optionally locked to a revision.
+    Flake {
+        reference: String,
+        rev: Option<String>,
+    },
+}
 
 pub async fn execute(
-    api_url: &str,
+    client: &HomelabClient,
     server: String,
-    config_path: String,
+    source: ConfigSource,
     description: Option<String>,
+    labels: Vec<(String, String)>,
+    modules: Vec<String>,
 ) -> Result<()> {
-    let client = Client::new();
-    
-    let request_body = json!({
-        "server_name": server,
-        "config_file_path": config_path,
-        "description": description
-    });
-    
-    let url = format!("{}/servers", api_url);
-    
+    let mut request_body = CreateServerRequest {
+        server_name: server.clone(),
+        config_file_path: None,
+        config_id: None,
+        flake: None,
+        description,
+        labels: labels.into_iter().collect(),
+        modules,
+    };
+    match source {
+        ConfigSource::Path(path) => {
+            request_body.config_file_path = Some(path);
+        }
+        ConfigSource::ConfigFile(config) => {
+            request_body.config_id = Some(resolve_config_id(client, &config).await?);
+        }
+        ConfigSource::Flake { reference, rev } => {
+            let (uri, attribute) = parse_flake(&reference)?;
+            request_body.flake = Some(FlakeRef {
+                uri,
+                attribute,
+                rev,
+            });
+        }
+    }
+
     println!("Adding server configuration...");
-    
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await?;
-    
-    if response.status().is_success() {
-        let result: serde_json::Value = response.json().await?;
-        println!("✅ Server added successfully!");
-        println!("Server ID: {}", result["server_id"]);
-    } else {
-        let error: serde_json::Value = response.json().await?;
-        anyhow::bail!("Failed to add server: {}", error["error"]);
+
+    match client.create_server(&request_body).await {
+        Ok(result) => {
+            println!("✅ Server added successfully!");
+            println!("Server ID: {}", result.server_id);
+        }
+        Err(e) if e.code() == Some(ErrorCode::NameConflict) => {
+            let existing = e
+                .api_error()
+                .and_then(|error| error.existing_server_id.clone())
+                .unwrap_or_default();
+            let message = format!(
+                "A server named '{}' already exists (ID: {}). Use `homelab update --server {}` to change it, or pick another name.",
+                server,
+                existing,
+                server
+            );
+            return Err(explain(e, message));
+        }
+        Err(e) => return Err(failed_with_flags("Failed to add server", e, FLAGS)),
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Splits `uri#attribute`. A bare host name as the attribute is taken to mean
+/// `nixosConfigurations.<name>`, as with `nixos-rebuild --flake`.
+fn parse_flake(reference: &str) -> Result<(String, String)> {
+    let Some((uri, attribute)) = reference.split_once('#') else {
+        anyhow::bail!(
+            "Flake reference '{}' must look like URI#attribute, e.g. github:org/infra#web-01",
+            reference
+        );
+    };
+    let attribute = if attribute.contains('.') {
+        attribute.to_string()
+    } else {
+        format!("nixosConfigurations.{}", attribute)
+    };
+    Ok((uri.to_string(), attribute))
+}
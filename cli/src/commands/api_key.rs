@@ -0,0 +1,107 @@
+use anyhow::Result;
+use homelab_api::api_key::{CreateApiKeyRequest, Role};
+use homelab_client::HomelabClient;
+use tabled::{settings::Style, Table, Tabled};
+
+use super::list_servers::format_timestamp;
+use crate::report::{explain, failed, failed_with_flags, Flags};
+
+/// Flags setting each field of `key create`, for reporting invalid ones.
+const CREATE_FLAGS: Flags = &[("name", "--name")];
+
+#[derive(Tabled)]
+struct ApiKeyRow {
+    id: String,
+    name: String,
+    role: String,
+    created_at: String,
+    created_by: String,
+}
+
+/// Creates an API key and prints its token, which the API never shows again.
+pub async fn create(client: &HomelabClient, name: String, role: Role) -> Result<()> {
+    let request = CreateApiKeyRequest { name, role };
+
+    let result = client
+        .create_api_key(&request)
+        .await
+        .map_err(|e| failed_with_flags("Failed to create API key", e, CREATE_FLAGS))?;
+
+    println!("✅ API key created successfully!");
+    println!("Key ID: {}", result.key.key_id);
+    println!("Role:   {}", result.key.role);
+    println!("Token:  {}", result.token);
+    println!("Store the token now; it cannot be shown again. Use it as HOMELAB_TOKEN or `token` in the config file.");
+
+    Ok(())
+}
+
+/// Lists every API key, without their tokens.
+pub async fn list(client: &HomelabClient) -> Result<()> {
+    let keys = client
+        .list_api_keys()
+        .await
+        .map_err(|e| failed("Failed to list API keys", e))?;
+
+    if keys.is_empty() {
+        println!("🔑 No API keys.");
+        return Ok(());
+    }
+
+    let rows: Vec<ApiKeyRow> = keys
+        .iter()
+        .map(|key| ApiKeyRow {
+            id: key.key_id.clone(),
+            name: key.name.clone(),
+            role: key.role.to_string(),
+            created_at: format_timestamp(&key.created_at),
+            created_by: key.created_by.clone(),
+        })
+        .collect();
+
+    println!("🔑 API Keys:");
+    println!("{}", Table::new(&rows).with(Style::modern()));
+    println!("Total API keys: {}", keys.len());
+
+    Ok(())
+}
+
+/// Revokes an API key.
+pub async fn delete(client: &HomelabClient, key_id: String) -> Result<()> {
+    match client.delete_api_key(&key_id).await {
+        Ok(result) => {
+            println!("✅ API key deleted successfully!");
+            println!("Key ID: {}", result.key_id);
+        }
+        Err(e) if e.is_not_found() => {
+            let message = format!("No API key with ID '{}'. See `homelab key list`.", key_id);
+            return Err(explain(e, message));
+        }
+        Err(e) => return Err(failed("Failed to delete API key", e)),
+    }
+
+    Ok(())
+}
+
+/// Shows who the API authenticates the caller as, and what its role allows.
+pub async fn whoami(client: &HomelabClient) -> Result<()> {
+    let me = client
+        .whoami()
+        .await
+        .map_err(|e| failed("Failed to look up the caller", e))?;
+
+    println!("👤 {}", me.actor);
+    println!("Role:              {}", me.role);
+    println!("Authenticated by:  {}", me.authenticated_by);
+    if let (Some(key_id), Some(key_name)) = (&me.key_id, &me.key_name) {
+        println!("API key:           {} ({})", key_name, key_id);
+    }
+    if let Some(email) = &me.email {
+        println!("Email:             {}", email);
+    }
+    if !me.groups.is_empty() {
+        println!("Groups:            {}", me.groups.join(", "));
+    }
+
+    Ok(())
+}
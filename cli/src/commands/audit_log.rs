@@ -0,0 +1,129 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use homelab_api::audit_event::{AuditAction, AuditEvent, AuditTarget};
+use homelab_client::{AuditQuery, HomelabClient};
+use serde_json::Value;
+use tabled::{settings::Style, Table, Tabled};
+use uuid::Uuid;
+
+use super::list_servers::format_timestamp;
+use super::resolve::resolve_any_server;
+use super::server_history::diff;
+use crate::report::failed;
+
+#[derive(Tabled)]
+struct AuditRow {
+    time: String,
+    actor: String,
+    action: String,
+    target: String,
+    changes: String,
+    request_id: String,
+}
+
+/// Parses an RFC 3339 timestamp for clap.
+pub fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|_| {
+            format!(
+                "expected an RFC 3339 timestamp, e.g. 2024-01-31T00:00:00Z, got '{}'",
+                timestamp
+            )
+        })
+}
+
+/// Lists audit events, oldest first. With neither `limit` nor `page` every page
+/// is fetched by following `next_cursor`; otherwise a single page is shown.
+pub async fn execute(
+    client: &HomelabClient,
+    server: Option<String>,
+    since: Option<DateTime<Utc>>,
+    limit: Option<u32>,
+    page: Option<String>,
+) -> Result<()> {
+    // Purged servers can no longer be resolved, so an ID that matches nothing is used as-is
+    let server_id = match server {
+        Some(server) => match resolve_any_server(client, &server).await {
+            Ok(record) => Some(record.server_id),
+            Err(_) if Uuid::parse_str(&server).is_ok() => Some(server),
+            Err(e) => return Err(e),
+        },
+        None => None,
+    };
+
+    let manual_paging = limit.is_some() || page.is_some();
+
+    let mut events: Vec<AuditEvent> = Vec::new();
+    let mut query = AuditQuery {
+        server_id,
+        since,
+        limit,
+        cursor: page,
+    };
+
+    loop {
+        let result = client
+            .list_audit(&query)
+            .await
+            .map_err(|e| failed("Failed to list audit events", e))?;
+        events.extend(result.events);
+
+        query.cursor = result.next_cursor;
+        if manual_paging || query.cursor.is_none() {
+            break;
+        }
+    }
+
+    if events.is_empty() {
+        println!("📋 No audit events recorded.");
+    } else {
+        let rows: Vec<AuditRow> = events.iter().map(row).collect();
+
+        println!("🔎 Audit Log:");
+        println!("{}", Table::new(&rows).with(Style::modern()));
+        println!("Total events: {}", events.len());
+    }
+
+    if manual_paging {
+        if let Some(cursor) = query.cursor {
+            println!("More events available. Next page: --page {}", cursor);
+        }
+    }
+
+    Ok(())
+}
+
+fn row(event: &AuditEvent) -> AuditRow {
+    // Prefer the name the record had after the change, falling back to the one before
+    let name = event
+        .after
+        .as_ref()
+        .or(event.before.as_ref())
+        .map(|record| record.name());
+    let kind = match event.target {
+        AuditTarget::Server => "",
+        AuditTarget::ConfigFile => "config file ",
+        AuditTarget::ApiKey => "API key ",
+    };
+
+    let changes = match event.action {
+        AuditAction::Update | AuditAction::Rollback => {
+            let snapshot = |record| serde_json::to_value(record).unwrap_or(Value::Null);
+            diff(&snapshot(&event.before), &snapshot(&event.after)).join("\n")
+        }
+        _ => String::new(),
+    };
+
+    AuditRow {
+        time: format_timestamp(&event.recorded_at),
+        actor: event.actor.clone(),
+        action: event.action.as_str().to_string(),
+        target: match name {
+            Some(name) => format!("{}{}\n{}", kind, name, event.target_id),
+            None => format!("{}{}", kind, event.target_id),
+        },
+        changes,
+        request_id: event.request_id.clone().unwrap_or_default(),
+    }
+}
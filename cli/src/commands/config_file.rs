@@ -0,0 +1,129 @@
+use anyhow::Result;
+use homelab_api::config_file::CreateConfigFileRequest;
+use homelab_api::responses::ErrorCode;
+use homelab_client::HomelabClient;
+use tabled::{settings::Style, Table, Tabled};
+
+use super::list_servers::format_timestamp;
+use crate::report::{explain, failed, failed_with_flags, not_found, Flags};
+
+/// Flags setting each field of `config-file add`, for reporting invalid ones.
+const ADD_FLAGS: Flags = &[
+    ("path", "--path"),
+    ("description", "--description"),
+    ("owner", "--owner"),
+];
+
+#[derive(Tabled)]
+struct ConfigFileRow {
+    id: String,
+    path: String,
+    description: String,
+    owner: String,
+    created_at: String,
+}
+
+/// Looks up a config file by ID or path and returns its ID.
+pub async fn resolve_config_id(client: &HomelabClient, config: &str) -> Result<String> {
+    match client.get_config_file(config).await {
+        Ok(config_file) => Ok(config_file.config_id),
+        Err(e) if e.is_not_found() => Err(not_found(format!(
+            "No config file with ID or path '{}'. See `homelab config-file list`.",
+            config
+        ))),
+        Err(e) => Err(failed("Failed to look up config file", e)),
+    }
+}
+
+/// Registers a NixOS configuration file that servers can then reference.
+pub async fn add(
+    client: &HomelabClient,
+    path: String,
+    description: Option<String>,
+    owner: Option<String>,
+) -> Result<()> {
+    let request = CreateConfigFileRequest {
+        path: path.clone(),
+        description,
+        owner,
+    };
+
+    match client.create_config_file(&request).await {
+        Ok(result) => {
+            println!("✅ Config file added successfully!");
+            println!("Config ID: {}", result.config_id);
+        }
+        Err(e) if e.code() == Some(ErrorCode::ConfigPathConflict) => {
+            let existing = e
+                .api_error()
+                .and_then(|error| error.existing_config_id.clone())
+                .unwrap_or_default();
+            let message = format!("'{}' is already registered (ID: {}).", path, existing);
+            return Err(explain(e, message));
+        }
+        Err(e) => return Err(failed_with_flags("Failed to add config file", e, ADD_FLAGS)),
+    }
+
+    Ok(())
+}
+
+/// Lists every registered config file.
+pub async fn list(client: &HomelabClient) -> Result<()> {
+    let config_files = client
+        .list_config_files()
+        .await
+        .map_err(|e| failed("Failed to list config files", e))?
+        .config_files;
+
+    if config_files.is_empty() {
+        println!("📋 No config files registered.");
+        return Ok(());
+    }
+
+    let rows: Vec<ConfigFileRow> = config_files
+        .iter()
+        .map(|config_file| ConfigFileRow {
+            id: config_file.config_id.clone(),
+            path: config_file.path.clone(),
+            description: config_file.description.clone().unwrap_or_default(),
+            owner: config_file.owner.clone().unwrap_or_default(),
+            created_at: format_timestamp(&config_file.created_at),
+        })
+        .collect();
+
+    println!("📋 Config Files:");
+    println!("{}", Table::new(&rows).with(Style::modern()));
+    println!("Total config files: {}", config_files.len());
+
+    Ok(())
+}
+
+/// Deletes a config file; the API refuses while servers still use it.
+pub async fn delete(client: &HomelabClient, config: String) -> Result<()> {
+    match client.delete_config_file(&config).await {
+        Ok(result) => {
+            println!("✅ Config file deleted successfully!");
+            println!("Config ID: {}", result.config_id);
+        }
+        Err(e) if e.code() == Some(ErrorCode::ConfigFileInUse) => {
+            let server_ids = e
+                .api_error()
+                .and_then(|error| error.server_ids.clone())
+                .unwrap_or_default();
+            let message = format!(
+                "'{}' is still used by {} server(s): {}. Move them to another config first.",
+                config,
+                server_ids.len(),
+                server_ids.join(", ")
+            );
+            return Err(explain(e, message));
+        }
+        Err(e) if e.is_not_found() => {
+            let message = format!("No config file with ID or path '{}'.", config);
+            return Err(explain(e, message));
+        }
+        Err(e) => return Err(failed("Failed to delete config file", e)),
+    }
+
+    Ok(())
+}
@@ -1,26 +1,56 @@
 use anyhow::Result;
-use reqwest::Client;
+use homelab_api::responses::ErrorCode;
+use homelab_client::HomelabClient;
 
-pub async fn execute(api_url: &str, id: String) -> Result<()> {
-    let client = Client::new();
-    
-    let url = format!("{}/servers/{}", api_url, id);
-    
-    println!("Deleting server configuration for ID: {}", id);
-    
-    let response = client
-        .delete(&url)
-        .send()
-        .await?;
-    
-    if response.status().is_success() {
-        let result: serde_json::Value = response.json().await?;
-        println!("✅ Server deleted successfully!");
-        println!("Message: {}", result["message"]);
+use super::resolve::{resolve_any_server, resolve_server_id};
+use crate::report::{explain, failed};
+
+/// Moves a server into the trash, or removes it for good when `purge` is set.
+pub async fn execute(
+    client: &HomelabClient,
+    server: String,
+    purge: bool,
+    expect_version: Option<u64>,
+) -> Result<()> {
+    // Purging also works on servers that are already in the trash
+    let id = if purge {
+        resolve_any_server(client, &server).await?.server_id
+    } else {
+        resolve_server_id(client, &server).await?
+    };
+
+    if purge {
+        println!(
+            "Permanently deleting server configuration for {} (ID: {})",
+            server, id
+        );
     } else {
-        let error: serde_json::Value = response.json().await?;
-        anyhow::bail!("Failed to delete server: {}", error["error"]);
+        println!("Deleting server configuration for {} (ID: {})", server, id);
     }
-    
+
+    match client.delete_server(&id, purge, expect_version).await {
+        Ok(result) => {
+            println!("✅ Server deleted successfully!");
+            println!("Message: {}", result.message);
+            if !purge {
+                println!("Use `homelab restore {}` to undo.", id);
+            }
+        }
+        Err(e) if e.code() == Some(ErrorCode::VersionMismatch) => {
+            let current = e
+                .api_error()
+                .and_then(|error| error.current_version)
+                .unwrap_or_default();
+            let message = format!(
+                "Server was changed by someone else (expected version {}, now {}). Run `homelab get {}` and retry.",
+                expect_version.unwrap_or_default(),
+                current,
+                server
+            );
+            return Err(explain(e, message));
+        }
+        Err(e) => return Err(failed("Failed to delete server", e)),
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
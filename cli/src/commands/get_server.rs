@@ -0,0 +1,51 @@
+use anyhow::Result;
+use homelab_client::HomelabClient;
+
+use super::label_server::format_labels;
+use super::list_servers::format_timestamp;
+use super::resolve::resolve_server;
+
+pub async fn execute(client: &HomelabClient, server: String) -> Result<()> {
+    let server = resolve_server(client, &server).await?;
+
+    println!("🖥️  {}", server.server_name);
+    println!("  ID:          {}", server.server_id);
+    println!("  Config path: {}", server.config_file_path);
+    if let Some(config_id) = &server.config_id {
+        println!("  Config file: {}", config_id);
+    }
+    if let Some(rev) = server.flake.as_ref().and_then(|flake| flake.rev.as_ref()) {
+        println!("  Flake rev:   {}", rev);
+    }
+    let modules = server.modules.join("\n               ");
+    println!(
+        "  Modules:     {}",
+        if modules.is_empty() { "-" } else { &modules }
+    );
+    println!(
+        "  Description: {}",
+        server.description.as_deref().unwrap_or("-")
+    );
+    let labels = format_labels(&server.labels, ", ");
+    println!(
+        "  Labels:      {}",
+        if labels.is_empty() { "-" } else { &labels }
+    );
+    println!("  Created at:  {}", format_timestamp(&server.created_at));
+    if let Some(created_by) = &server.created_by {
+        println!("  Created by:  {}", created_by);
+    }
+    println!("  Updated at:  {}", format_timestamp(&server.updated_at));
+    if let Some(updated_by) = &server.updated_by {
+        println!("  Updated by:  {}", updated_by);
+    }
+    println!("  Version:     {}", server.version);
+    if let Some(deleted_at) = &server.deleted_at {
+        println!(
+            "  Deleted at:  {} (in the trash; see `homelab restore`)",
+            format_timestamp(deleted_at)
+        );
+    }
+
+    Ok(())
+}
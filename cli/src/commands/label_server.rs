@@ -0,0 +1,101 @@
+use anyhow::Result;
+use homelab_api::responses::ErrorCode;
+use homelab_api::server_config::{Labels, UpdateServerRequest};
+use homelab_client::HomelabClient;
+
+use super::resolve::resolve_server;
+use crate::report::failed;
+
+/// How often a label change is retried when someone else updates the server in between.
+const MAX_ATTEMPTS: usize = 3;
+
+/// A label change given on the command line: `key=value` sets, `key-` removes.
+enum LabelChange {
+    Set(String, String),
+    Remove(String),
+}
+
+/// Parses a `key=value` label for clap.
+pub fn parse_label(label: &str) -> Result<(String, String), String> {
+    match label.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, got '{}'", label)),
+    }
+}
+
+fn parse_change(change: &str) -> Result<LabelChange> {
+    if let Some(key) = change
+        .strip_suffix('-')
+        .filter(|key| !key.is_empty() && !key.contains('='))
+    {
+        return Ok(LabelChange::Remove(key.to_string()));
+    }
+    let (key, value) = parse_label(change).map_err(|e| anyhow::anyhow!("Invalid label: {}", e))?;
+    Ok(LabelChange::Set(key, value))
+}
+
+/// Formats labels as `key=value` pairs separated by `separator`.
+pub fn format_labels(labels: &Labels, separator: &str) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Sets or removes labels on a server, keeping all other labels as they are.
+pub async fn execute(client: &HomelabClient, server: String, changes: Vec<String>) -> Result<()> {
+    let changes = changes
+        .iter()
+        .map(|change| parse_change(change))
+        .collect::<Result<Vec<_>>>()?;
+    let mut target = server.clone();
+
+    for _ in 0..MAX_ATTEMPTS {
+        let record = resolve_server(client, &target).await?;
+        let id = record.server_id;
+        target = id.clone();
+
+        let current = record.labels;
+        let mut labels = current.clone();
+        for change in &changes {
+            match change {
+                LabelChange::Set(key, value) => {
+                    labels.insert(key.clone(), value.clone());
+                }
+                LabelChange::Remove(key) => {
+                    labels.remove(key);
+                }
+            }
+        }
+
+        if labels == current {
+            println!("Labels of {} are already up to date.", server);
+            return Ok(());
+        }
+
+        // Labels are replaced as a whole, so only write them over the version we read
+        let changes = UpdateServerRequest {
+            labels: Some(labels.clone()),
+            ..UpdateServerRequest::default()
+        };
+        match client
+            .update_server(&id, &changes, Some(record.version))
+            .await
+        {
+            Ok(result) => {
+                println!("✅ Labels updated for {} (ID: {})", server, id);
+                println!("Labels:  {}", format_labels(&labels, ", "));
+                println!("Version: {}", result.version);
+                return Ok(());
+            }
+            Err(e) if e.code() == Some(ErrorCode::VersionMismatch) => {}
+            Err(e) => return Err(failed("Failed to update labels", e)),
+        }
+    }
+
+    anyhow::bail!(
+        "Server {} kept changing while its labels were being updated; try again.",
+        server
+    )
+}
@@ -1,7 +1,14 @@
 use anyhow::Result;
-use serde_json::Value;
-use reqwest::Client;
-use tabled::{Table, Tabled, settings::Style};
+use chrono::{DateTime, SecondsFormat, Utc};
+use homelab_api::server_config::ServerConfig;
+use homelab_client::{HomelabClient, ListServersQuery};
+use tabled::{
+    settings::{location::ByColumnName, Disable, Style},
+    Table, Tabled,
+};
+
+use super::label_server::format_labels;
+use crate::report::failed;
 
 #[derive(Tabled)]
 struct ServerRow {
@@ -9,52 +16,86 @@ struct ServerRow {
     name: String,
     config_path: String,
     description: String,
+    labels: String,
+    modules: String,
     created_at: String,
 }
 
-pub async fn execute(api_url: &str) -> Result<()> {
-    let client = Client::new();
-    
-    let url = format!("{}/servers", api_url);
-    
+/// Lists servers. With neither `limit` nor `page` every page is fetched by
+/// following `next_cursor`; otherwise a single page is shown. `wide` adds the
+/// modules column.
+pub async fn execute(
+    client: &HomelabClient,
+    limit: Option<u32>,
+    page: Option<String>,
+    include_deleted: bool,
+    labels: Vec<(String, String)>,
+    wide: bool,
+) -> Result<()> {
+    let manual_paging = limit.is_some() || page.is_some();
+
     println!("Listing all server configurations...");
-    
-    let response = client
-        .get(&url)
-        .send()
-        .await?;
-    
-    if response.status().is_success() {
-        let result: Value = response.json().await?;
-        
-        if let Some(servers) = result["servers"].as_array() {
-            if servers.is_empty() {
-                println!("📋 No servers configured.");
-                return Ok(());
-            }
-            
-            let mut rows = Vec::new();
-            
-            for server in servers {
-                rows.push(ServerRow {
-                    id: server["server_id"].as_str().unwrap_or("N/A").to_string(),
-                    name: server["server_name"].as_str().unwrap_or("N/A").to_string(),
-                    config_path: server["config_file_path"].as_str().unwrap_or("N/A").to_string(),
-                    description: server["description"].as_str().unwrap_or("").to_string(),
-                    created_at: server["created_at"].as_str().unwrap_or("N/A").to_string(),
-                });
-            }
-            
-            println!("📋 Server Configurations:");
-            println!("{}", Table::new(&rows).with(Style::modern()));
-            println!("Total servers: {}", servers.len());
-        } else {
-            println!("📋 No servers configured.");
+
+    let mut servers: Vec<ServerConfig> = Vec::new();
+    let mut query = ListServersQuery {
+        include_deleted,
+        labels,
+        limit,
+        cursor: page,
+    };
+
+    loop {
+        let result = client
+            .list_servers(&query)
+            .await
+            .map_err(|e| failed("Failed to list servers", e))?;
+        servers.extend(result.servers);
+
+        query.cursor = result.next_cursor;
+        if manual_paging || query.cursor.is_none() {
+            break;
         }
+    }
+
+    if servers.is_empty() {
+        println!("📋 No servers configured.");
     } else {
-        let error: Value = response.json().await?;
-        anyhow::bail!("Failed to list servers: {}", error["error"]);
+        let rows: Vec<ServerRow> = servers
+            .iter()
+            .map(|server| ServerRow {
+                id: server.server_id.clone(),
+                name: if server.is_deleted() {
+                    format!("{} (deleted)", server.server_name)
+                } else {
+                    server.server_name.clone()
+                },
+                config_path: server.config_file_path.clone(),
+                description: server.description.clone().unwrap_or_default(),
+                labels: format_labels(&server.labels, "\n"),
+                modules: server.modules.join("\n"),
+                created_at: format_timestamp(&server.created_at),
+            })
+            .collect();
+
+        println!("📋 Server Configurations:");
+        let mut table = Table::new(&rows);
+        table.with(Style::modern());
+        if !wide {
+            table.with(Disable::column(ByColumnName::new("modules")));
+        }
+        println!("{}", table);
+        println!("Total servers: {}", servers.len());
+    }
+
+    if manual_paging {
+        if let Some(cursor) = query.cursor {
+            println!("More servers available. Next page: --page {}", cursor);
+        }
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
+/// Formats a timestamp the way the API sends it, e.g. `2024-01-31T09:30:00.123Z`.
+pub fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
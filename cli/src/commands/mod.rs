@@ -1,4 +1,14 @@
 pub mod add_server;
-pub mod update_config;
+pub mod api_key;
+pub mod audit_log;
+pub mod config_file;
 pub mod delete_config;
-pub mod list_servers;
\ No newline at end of file
+pub mod get_server;
+pub mod label_server;
+pub mod list_servers;
+pub mod resolve;
+pub mod restore_server;
+pub mod rollback_server;
+pub mod server_history;
+pub mod update_config;
+pub mod who_uses;
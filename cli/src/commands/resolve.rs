@@ -0,0 +1,107 @@
+use anyhow::Result;
+use homelab_api::server_config::ServerConfig;
+use homelab_client::HomelabClient;
+use uuid::Uuid;
+
+use crate::report::{failed, not_found};
+
+/// Looks up a server by ID or by name and returns its full record.
+///
+/// Anything that parses as a UUID is tried as an ID first; otherwise (or if no
+/// server has that ID) the name is looked up, and an error is returned when it
+/// matches no server or more than one. Servers in the trash are only found by ID.
+pub async fn resolve_server(client: &HomelabClient, server: &str) -> Result<ServerConfig> {
+    let matches = find_servers(client, server, false).await?;
+    pick_one(server, matches)
+}
+
+/// Like `resolve_server`, but only considers servers in the trash.
+pub async fn resolve_deleted_server(client: &HomelabClient, server: &str) -> Result<ServerConfig> {
+    let mut matches = find_servers(client, server, true).await?;
+    matches.retain(ServerConfig::is_deleted);
+    pick_one(server, matches)
+}
+
+/// Like `resolve_server`, but also finds servers in the trash by name.
+pub async fn resolve_any_server(client: &HomelabClient, server: &str) -> Result<ServerConfig> {
+    let matches = find_servers(client, server, true).await?;
+    pick_one(server, matches)
+}
+
+/// Returns the server with ID `server`, or else every server named `server`.
+async fn find_servers(
+    client: &HomelabClient,
+    server: &str,
+    include_deleted: bool,
+) -> Result<Vec<ServerConfig>> {
+    if Uuid::parse_str(server).is_ok() {
+        match client.get_server(server).await {
+            Ok(record) => return Ok(vec![record]),
+            Err(e) if e.is_not_found() => {}
+            Err(e) => return Err(failed("Failed to look up server", e)),
+        }
+    }
+
+    client
+        .find_servers_by_name(server, include_deleted)
+        .await
+        .map_err(|e| failed("Failed to look up server", e))
+}
+
+fn pick_one(server: &str, mut matches: Vec<ServerConfig>) -> Result<ServerConfig> {
+    match matches.len() {
+        0 => Err(not_found(format!(
+            "No server found with name or ID '{}'",
+            server
+        ))),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            let ids: Vec<&str> = matches.iter().map(|m| m.server_id.as_str()).collect();
+            anyhow::bail!(
+                "Server name '{}' is ambiguous; it matches {} servers. Use one of these IDs instead: {}",
+                server,
+                matches.len(),
+                ids.join(", ")
+            )
+        }
+    }
+}
+
+/// Resolves `server` to its server ID, see `resolve_server`.
+pub async fn resolve_server_id(client: &HomelabClient, server: &str) -> Result<String> {
+    Ok(resolve_server(client, server).await?.server_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn server(server_id: &str) -> ServerConfig {
+        serde_json::from_value(json!({
+            "server_id": server_id,
+            "server_name": "web-1",
+            "config_file_path": "/etc/nixos/web.nix",
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn pick_one_takes_a_single_match() {
+        let picked = pick_one("web-1", vec![server("id-1")]).unwrap();
+        assert_eq!(picked.server_id, "id-1");
+    }
+
+    #[test]
+    fn pick_one_reports_missing_and_ambiguous_names() {
+        let error = pick_one("web-1", Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "No server found with name or ID 'web-1'");
+
+        let error = pick_one("web-1", vec![server("id-1"), server("id-2")]).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("ambiguous"));
+        assert!(message.ends_with("id-1, id-2"));
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+use homelab_api::responses::ErrorCode;
+use homelab_client::HomelabClient;
+
+use super::resolve::resolve_deleted_server;
+use crate::report::{explain, failed};
+
+pub async fn execute(
+    client: &HomelabClient,
+    server: String,
+    expect_version: Option<u64>,
+) -> Result<()> {
+    let id = resolve_deleted_server(client, &server).await?.server_id;
+
+    println!("Restoring server configuration for {} (ID: {})", server, id);
+
+    match client.restore_server(&id, expect_version).await {
+        Ok(result) => {
+            println!("✅ Server restored successfully!");
+            println!("Version: {}", result.version);
+        }
+        Err(e) if e.code() == Some(ErrorCode::VersionMismatch) => {
+            let current = e
+                .api_error()
+                .and_then(|error| error.current_version)
+                .unwrap_or_default();
+            let message = format!(
+                "Server was changed by someone else (expected version {}, now {}). Run `homelab get {}` and retry.",
+                expect_version.unwrap_or_default(),
+                current,
+                id
+            );
+            return Err(explain(e, message));
+        }
+        Err(e) if e.code() == Some(ErrorCode::NameConflict) => {
+            let existing = e
+                .api_error()
+                .and_then(|error| error.existing_server_id.clone())
+                .unwrap_or_default();
+            let message = format!(
+                "Cannot restore: its name is now used by server {}. Rename that server first.",
+                existing
+            );
+            return Err(explain(e, message));
+        }
+        Err(e) => return Err(failed("Failed to restore server", e)),
+    }
+
+    Ok(())
+}
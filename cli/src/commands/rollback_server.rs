@@ -0,0 +1,55 @@
+use anyhow::Result;
+use homelab_api::responses::ErrorCode;
+use homelab_client::HomelabClient;
+
+use super::resolve::resolve_server_id;
+use crate::report::{explain, failed};
+
+pub async fn execute(
+    client: &HomelabClient,
+    server: String,
+    to: u64,
+    expect_version: Option<u64>,
+) -> Result<()> {
+    let id = resolve_server_id(client, &server).await?;
+
+    println!(
+        "Rolling back server {} (ID: {}) to revision {}",
+        server, id, to
+    );
+
+    match client.rollback_server(&id, to, expect_version).await {
+        Ok(result) => {
+            println!("✅ Server rolled back successfully!");
+            println!("Restored revision: {}", result.restored_revision);
+            println!("Version: {}", result.version);
+        }
+        Err(e) if e.code() == Some(ErrorCode::VersionMismatch) => {
+            let current = e
+                .api_error()
+                .and_then(|error| error.current_version)
+                .unwrap_or_default();
+            let message = format!(
+                "Server was changed by someone else (expected version {}, now {}). Run `homelab history {}` and retry.",
+                expect_version.unwrap_or_default(),
+                current,
+                server
+            );
+            return Err(explain(e, message));
+        }
+        Err(e) if e.code() == Some(ErrorCode::NameConflict) => {
+            let existing = e
+                .api_error()
+                .and_then(|error| error.existing_server_id.clone())
+                .unwrap_or_default();
+            let message = format!(
+                "Cannot roll back: the server's old name is now used by server {}.",
+                existing
+            );
+            return Err(explain(e, message));
+        }
+        Err(e) => return Err(failed("Failed to roll back server", e)),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use anyhow::Result;
+use homelab_api::server_revision::RevisionAction;
+use homelab_client::HomelabClient;
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::list_servers::format_timestamp;
+use super::resolve::resolve_server_id;
+use crate::report::failed;
+
+/// Fields compared between consecutive revisions.
+const TRACKED_FIELDS: &[&str] = &[
+    "server_name",
+    "config_file_path",
+    "flake",
+    "modules",
+    "description",
+    "labels",
+];
+
+pub async fn execute(client: &HomelabClient, server: String) -> Result<()> {
+    // IDs are used as-is so the history of deleted servers can still be shown
+    let server_id = if Uuid::parse_str(&server).is_ok() {
+        server
+    } else {
+        resolve_server_id(client, &server).await?
+    };
+
+    let revisions = client
+        .server_history(&server_id)
+        .await
+        .map_err(|e| failed("Failed to get server history", e))?
+        .revisions;
+
+    if revisions.is_empty() {
+        println!("No history recorded for server {}", server_id);
+        return Ok(());
+    }
+
+    println!("📜 History of server {}", server_id);
+    let mut previous: Option<Value> = None;
+    for revision in &revisions {
+        println!();
+        println!(
+            "  r{}  {}  {}",
+            revision.revision,
+            revision.action.as_str(),
+            format_timestamp(&revision.recorded_at)
+        );
+
+        let snapshot = serde_json::to_value(&revision.server)?;
+        match (revision.action, &previous) {
+            (RevisionAction::Delete | RevisionAction::Restore | RevisionAction::Purge, _) => {}
+            (_, Some(before)) => {
+                let changes = diff(before, &snapshot);
+                if changes.is_empty() {
+                    println!("      (no field changes)");
+                }
+                for line in changes {
+                    println!("      {}", line);
+                }
+            }
+            (_, None) => {
+                for field in TRACKED_FIELDS {
+                    if let Some(value) = snapshot.get(*field).filter(|v| !v.is_null()) {
+                        println!("      {}: {}", field, display(value));
+                    }
+                }
+            }
+        }
+        previous = Some(snapshot);
+    }
+
+    Ok(())
+}
+
+/// Describes each tracked field that differs between two snapshots.
+pub fn diff(before: &Value, after: &Value) -> Vec<String> {
+    TRACKED_FIELDS
+        .iter()
+        .filter(|field| before[**field] != after[**field])
+        .map(|field| {
+            format!(
+                "{}: {} → {}",
+                field,
+                display(&before[*field]),
+                display(&after[*field])
+            )
+        })
+        .collect()
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(s) => format!("{:?}", s),
+        other => other.to_string(),
+    }
+}
@@ -1,50 +1,96 @@
 use anyhow::Result;
-use serde_json::json;
-use reqwest::Client;
+use homelab_api::responses::ErrorCode;
+use homelab_api::server_config::UpdateServerRequest;
+use homelab_client::HomelabClient;
+
+use super::resolve::resolve_server_id;
+use crate::report::{explain, failed_with_flags, Flags};
+
+/// Flags setting each field of the request, for reporting invalid ones.
+const FLAGS: Flags = &[
+    ("server_name", "--new-name"),
+    ("config_file_path", "--config-path"),
+    ("description", "--description"),
+    ("add_modules", "--add-module"),
+    ("remove_modules", "--remove-module"),
+];
+
+/// Modules to append to or drop from a server's module list.
+pub struct ModuleChanges {
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+}
 
 pub async fn execute(
-    api_url: &str,
-    id: String,
+    client: &HomelabClient,
+    server: String,
+    new_name: Option<String>,
     config_path: Option<String>,
+    modules: ModuleChanges,
     description: Option<String>,
+    expect_version: Option<u64>,
 ) -> Result<()> {
-    let client = Client::new();
-    
-    // Build update request with only provided fields
-    let mut request_body = json!({});
-    
-    if let Some(path) = config_path {
-        request_body["config_file_path"] = json!(path);
-    }
-    
-    if let Some(desc) = description {
-        request_body["description"] = json!(desc);
-    }
-    
+    // Fields left unset keep their current value
+    let request_body = UpdateServerRequest {
+        server_name: new_name.clone(),
+        config_file_path: config_path,
+        description,
+        add_modules: modules.add,
+        remove_modules: modules.remove,
+        ..UpdateServerRequest::default()
+    };
+
     // Check if any fields were provided
-    if request_body.as_object().unwrap().is_empty() {
-        anyhow::bail!("No updates provided. Use --config-path or --description to update.");
+    if request_body.server_name.is_none()
+        && request_body.config_file_path.is_none()
+        && request_body.description.is_none()
+        && !request_body.changes_modules()
+    {
+        anyhow::bail!(
+            "No updates provided. Use --new-name, --config-path, --add-module, --remove-module or --description to update."
+        );
     }
-    
-    let url = format!("{}/servers/{}", api_url, id);
-    
-    println!("Updating server configuration for ID: {}", id);
-    
-    let response = client
-        .put(&url)
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await?;
-    
-    if response.status().is_success() {
-        let result: serde_json::Value = response.json().await?;
-        println!("✅ Server updated successfully!");
-        println!("Message: {}", result["message"]);
-    } else {
-        let error: serde_json::Value = response.json().await?;
-        anyhow::bail!("Failed to update server: {}", error["error"]);
+
+    let id = resolve_server_id(client, &server).await?;
+
+    println!("Updating server configuration for {} (ID: {})", server, id);
+
+    match client
+        .update_server(&id, &request_body, expect_version)
+        .await
+    {
+        Ok(result) => {
+            println!("✅ Server updated successfully!");
+            println!("Message: {}", result.message);
+            println!("Version: {}", result.version);
+        }
+        Err(e) if e.code() == Some(ErrorCode::VersionMismatch) => {
+            let current = e
+                .api_error()
+                .and_then(|error| error.current_version)
+                .unwrap_or_default();
+            let message = format!(
+                "Server was changed by someone else (expected version {}, now {}). Run `homelab get {}` and retry.",
+                expect_version.unwrap_or_default(),
+                current,
+                server
+            );
+            return Err(explain(e, message));
+        }
+        Err(e) if e.code() == Some(ErrorCode::NameConflict) => {
+            let existing = e
+                .api_error()
+                .and_then(|error| error.existing_server_id.clone())
+                .unwrap_or_default();
+            let message = format!(
+                "Cannot rename to '{}': that name is already used by server {}.",
+                new_name.unwrap_or_default(),
+                existing
+            );
+            return Err(explain(e, message));
+        }
+        Err(e) => return Err(failed_with_flags("Failed to update server", e, FLAGS)),
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use homelab_client::HomelabClient;
+use tabled::{settings::Style, Table, Tabled};
+
+use super::label_server::format_labels;
+use crate::report::failed;
+
+#[derive(Tabled)]
+struct ServerRow {
+    id: String,
+    name: String,
+    description: String,
+    labels: String,
+}
+
+/// Shows which servers use a NixOS configuration file.
+pub async fn execute(
+    client: &HomelabClient,
+    config_path: String,
+    include_deleted: bool,
+) -> Result<()> {
+    let servers = client
+        .servers_using_config(&config_path, include_deleted)
+        .await
+        .map_err(|e| failed("Failed to look up servers", e))?
+        .servers;
+
+    if servers.is_empty() {
+        println!("📋 No servers use {}", config_path);
+        return Ok(());
+    }
+
+    let rows: Vec<ServerRow> = servers
+        .iter()
+        .map(|server| ServerRow {
+            id: server.server_id.clone(),
+            name: if server.is_deleted() {
+                format!("{} (deleted)", server.server_name)
+            } else {
+                server.server_name.clone()
+            },
+            description: server.description.clone().unwrap_or_default(),
+            labels: format_labels(&server.labels, "\n"),
+        })
+        .collect();
+
+    println!("📋 Servers using {}:", config_path);
+    println!("{}", Table::new(&rows).with(Style::modern()));
+    println!("Total servers: {}", servers.len());
+
+    Ok(())
+}
@@ -1,10 +1,12 @@
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use anyhow::Result;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub api_url: Option<String>,
+    /// API key sent as a bearer token; `HOMELAB_TOKEN` overrides it.
+    pub token: Option<String>,
     pub default_timeout_seconds: Option<u64>,
     pub default_region: Option<String>,
 }
@@ -20,6 +22,7 @@ pub struct ServerConfig {
 pub struct HomelabConfigFile {
     pub servers: Vec<ServerConfig>,
     pub api_url: Option<String>,
+    pub token: Option<String>,
     pub timeout_seconds: Option<u64>,
     pub region: Option<String>,
 }
@@ -28,6 +31,7 @@ impl Default for AppConfig {
     fn default() -> Self {
         Self {
             api_url: None,
+            token: None,
             default_timeout_seconds: Some(30),
             default_region: Some("us-east-1".to_string()),
         }
@@ -45,14 +49,14 @@ pub async fn load_config(config_path: Option<&std::path::Path>) -> Result<AppCon
                 home_dir.join(".homelab.yaml"),
                 PathBuf::from("./homelab.yaml"),
             ];
-            
+
             for path in &config_paths {
                 if path.exists() {
                     return load_config_from_file(path).await;
                 }
             }
         }
-        
+
         Ok(AppConfig::default())
     }
 }
@@ -60,10 +64,11 @@ pub async fn load_config(config_path: Option<&std::path::Path>) -> Result<AppCon
 async fn load_config_from_file(path: &std::path::Path) -> Result<AppConfig> {
     let content = tokio::fs::read_to_string(path).await?;
     let config_file: HomelabConfigFile = serde_yaml::from_str(&content)?;
-    
+
     Ok(AppConfig {
         api_url: config_file.api_url,
+        token: config_file.token,
         default_timeout_seconds: config_file.timeout_seconds,
         default_region: config_file.region,
     })
-}
\ No newline at end of file
+}
@@ -1,9 +1,20 @@
-use clap::{Parser, Subcommand};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use homelab_api::api_key::Role;
+use homelab_client::HomelabClient;
+use std::env;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
 
 mod commands;
 mod config;
+mod report;
+
+use commands::add_server::ConfigSource;
+use commands::audit_log::parse_timestamp;
+use commands::label_server::parse_label;
 
 #[derive(Parser)]
 #[command(name = "homelab")]
@@ -12,11 +23,11 @@ mod config;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
-    
+
     /// API endpoint URL for the Homelab Lambda
     #[arg(long)]
     pub api_url: Option<String>,
-    
+
     /// Configuration file path
     #[arg(short, long)]
     pub config: Option<PathBuf>,
@@ -30,60 +41,384 @@ pub enum Commands {
         #[arg(long)]
         server: String,
         /// Path to NixOS configuration file
+        #[arg(long, required_unless_present_any = ["config_file", "flake"], conflicts_with_all = ["config_file", "flake"])]
+        config_path: Option<String>,
+        /// Registered config file to use, by ID or path (see `homelab config-file list`)
+        #[arg(
+            long = "config",
+            id = "config_file",
+            value_name = "CONFIG",
+            conflicts_with = "flake"
+        )]
+        config_file: Option<String>,
+        /// Flake output to build the host from, e.g. github:org/infra#nixosConfigurations.web-01
         #[arg(long)]
-        config_path: String,
+        flake: Option<String>,
+        /// Commit to lock the flake to (full hash)
+        #[arg(long, requires = "flake")]
+        rev: Option<String>,
         /// Server description
         #[arg(long)]
         description: Option<String>,
+        /// Label as key=value (repeatable)
+        #[arg(long = "label", value_parser = parse_label)]
+        labels: Vec<(String, String)>,
+        /// Additional NixOS module to import (repeatable, in import order)
+        #[arg(long = "module")]
+        modules: Vec<String>,
+    },
+    /// Set or remove labels on a server
+    Label {
+        /// Server name or ID
+        server: String,
+        /// Labels to set as key=value, or to remove as key-
+        #[arg(required = true)]
+        labels: Vec<String>,
     },
     /// Update an existing server configuration
     Update {
-        /// Server ID
+        /// Server name or ID
+        #[arg(long, visible_alias = "id")]
+        server: String,
+        /// Rename the server (names must be unique)
         #[arg(long)]
-        id: String,
+        new_name: Option<String>,
         /// New path to NixOS configuration file
         #[arg(long)]
         config_path: Option<String>,
+        /// Append a NixOS module (repeatable)
+        #[arg(long = "add-module")]
+        add_modules: Vec<String>,
+        /// Stop importing a NixOS module (repeatable)
+        #[arg(long = "remove-module")]
+        remove_modules: Vec<String>,
         /// New server description
         #[arg(long)]
         description: Option<String>,
+        /// Only update if the server is still at this version (see `homelab get`)
+        #[arg(long)]
+        expect_version: Option<u64>,
     },
-    /// Delete a server configuration
+    /// Move a server configuration to the trash
     Delete {
-        /// Server ID
+        /// Server name or ID
+        #[arg(long, visible_alias = "id")]
+        server: String,
+        /// Remove the server for good instead of moving it to the trash
+        #[arg(long)]
+        purge: bool,
+        /// Only delete if the server is still at this version (see `homelab get`)
+        #[arg(long)]
+        expect_version: Option<u64>,
+    },
+    /// Take a deleted server configuration back out of the trash
+    Restore {
+        /// Server name or ID
+        server: String,
+        /// Only restore if the server is still at this version
+        #[arg(long)]
+        expect_version: Option<u64>,
+    },
+    /// Show a single server configuration
+    Get {
+        /// Server name or ID
+        server: String,
+    },
+    /// Show the revision history of a server, with the fields changed by each revision
+    History {
+        /// Server name or ID (use the ID for servers that have been deleted)
+        server: String,
+    },
+    /// Restore a server's fields from an earlier revision (see `homelab history`)
+    Rollback {
+        /// Server name or ID
+        server: String,
+        /// Revision to restore
+        #[arg(long)]
+        to: u64,
+        /// Only roll back if the server is still at this version
         #[arg(long)]
-        id: String,
+        expect_version: Option<u64>,
     },
     /// List all server configurations
+    List {
+        /// Maximum number of servers per page (shows a single page)
+        #[arg(long)]
+        limit: Option<u32>,
+        /// Cursor of the page to show, as printed by a previous paged listing
+        #[arg(long)]
+        page: Option<String>,
+        /// Also list servers in the trash
+        #[arg(long)]
+        include_deleted: bool,
+        /// Only list servers with this key=value label (repeatable; all must match)
+        #[arg(short = 'l', long = "label", value_parser = parse_label)]
+        labels: Vec<(String, String)>,
+        /// Also show each server's modules
+        #[arg(short, long)]
+        wide: bool,
+    },
+    /// Show which servers use a NixOS configuration file
+    WhoUses {
+        /// Path to the NixOS configuration file, exactly as recorded on the servers
+        config_path: String,
+        /// Also show servers in the trash
+        #[arg(long)]
+        include_deleted: bool,
+    },
+    /// Manage registered NixOS configuration files
+    #[command(subcommand)]
+    ConfigFile(ConfigFileCommands),
+    /// Show the audit log of changes made through the API
+    Audit {
+        /// Only show changes to this server (name or ID)
+        #[arg(long)]
+        server: Option<String>,
+        /// Only show changes made at or after this time (RFC 3339, e.g. 2024-01-31T00:00:00Z)
+        #[arg(long, value_parser = parse_timestamp)]
+        since: Option<DateTime<Utc>>,
+        /// Maximum number of events per page (shows a single page)
+        #[arg(long)]
+        limit: Option<u32>,
+        /// Cursor of the page to show, as printed by a previous paged listing
+        #[arg(long)]
+        page: Option<String>,
+    },
+    /// Manage API keys (admin role only)
+    #[command(subcommand)]
+    Key(KeyCommands),
+    /// Show who the API sees you as, and your role
+    Whoami,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigFileCommands {
+    /// Register a NixOS configuration file
+    Add {
+        /// Path to the NixOS configuration file
+        #[arg(long)]
+        path: String,
+        /// What the configuration is for
+        #[arg(long)]
+        description: Option<String>,
+        /// Person or team responsible for the file
+        #[arg(long)]
+        owner: Option<String>,
+    },
+    /// List registered configuration files
     List,
+    /// Delete a configuration file that no server uses any more
+    Delete {
+        /// Config file ID or path
+        config: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KeyCommands {
+    /// Create an API key and print its token
+    Create {
+        /// What the key is for, e.g. ci or grafana
+        #[arg(long)]
+        name: String,
+        /// read-only (GET only), operator (also add and update) or admin (also delete and manage keys)
+        #[arg(long, default_value = "read-only")]
+        role: Role,
+    },
+    /// List API keys
+    List,
+    /// Revoke an API key
+    Delete {
+        /// Key ID
+        key_id: String,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => report::report(&e),
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
     // Load configuration
     let app_config = config::load_config(cli.config.as_deref()).await?;
-    
+
     // Use API URL from command line, config file, or default
-    let api_url = cli.api_url
+    let api_url = cli
+        .api_url
         .or(app_config.api_url)
         .unwrap_or_else(|| "https://api.example.com".to_string());
-    
+    let timeout = app_config
+        .default_timeout_seconds
+        .map_or(homelab_client::DEFAULT_TIMEOUT, Duration::from_secs);
+    let mut client = HomelabClient::new(&api_url, timeout)?;
+    // HOMELAB_TOKEN wins over the config file, so CI can use its own key
+    if let Some(token) = env::var("HOMELAB_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .or(app_config.token)
+    {
+        client = client.with_token(token);
+    }
+
     match cli.command {
-        Commands::Add { server, config_path, description } => {
-            commands::add_server::execute(&api_url, server, config_path, description).await?;
+        Commands::Add {
+            server,
+            config_path,
+            config_file,
+            flake,
+            rev,
+            description,
+            labels,
+            modules,
+        } => {
+            let source = match (config_path, config_file, flake) {
+                (Some(path), _, _) => ConfigSource::Path(path),
+                (_, Some(config), _) => ConfigSource::ConfigFile(config),
+                (_, _, Some(reference)) => ConfigSource::Flake { reference, rev },
+                // clap requires exactly one of them
+                (None, None, None) => unreachable!(),
+            };
+            commands::add_server::execute(&client, server, source, description, labels, modules)
+                .await?;
+        }
+        Commands::Label { server, labels } => {
+            commands::label_server::execute(&client, server, labels).await?;
         }
-        Commands::Update { id, config_path, description } => {
-            commands::update_config::execute(&api_url, id, config_path, description).await?;
+        Commands::Update {
+            server,
+            new_name,
+            config_path,
+            add_modules,
+            remove_modules,
+            description,
+            expect_version,
+        } => {
+            let modules = commands::update_config::ModuleChanges {
+                add: add_modules,
+                remove: remove_modules,
+            };
+            commands::update_config::execute(
+                &client,
+                server,
+                new_name,
+                config_path,
+                modules,
+                description,
+                expect_version,
+            )
+            .await?;
         }
-        Commands::Delete { id } => {
-            commands::delete_config::execute(&api_url, id).await?;
+        Commands::Delete {
+            server,
+            purge,
+            expect_version,
+        } => {
+            commands::delete_config::execute(&client, server, purge, expect_version).await?;
         }
-        Commands::List => {
-            commands::list_servers::execute(&api_url).await?;
+        Commands::Restore {
+            server,
+            expect_version,
+        } => {
+            commands::restore_server::execute(&client, server, expect_version).await?;
+        }
+        Commands::Get { server } => {
+            commands::get_server::execute(&client, server).await?;
+        }
+        Commands::History { server } => {
+            commands::server_history::execute(&client, server).await?;
+        }
+        Commands::Rollback {
+            server,
+            to,
+            expect_version,
+        } => {
+            commands::rollback_server::execute(&client, server, to, expect_version).await?;
+        }
+        Commands::List {
+            limit,
+            page,
+            include_deleted,
+            labels,
+            wide,
+        } => {
+            commands::list_servers::execute(&client, limit, page, include_deleted, labels, wide)
+                .await?;
+        }
+        Commands::WhoUses {
+            config_path,
+            include_deleted,
+        } => {
+            commands::who_uses::execute(&client, config_path, include_deleted).await?;
+        }
+        Commands::ConfigFile(ConfigFileCommands::Add {
+            path,
+            description,
+            owner,
+        }) => {
+            commands::config_file::add(&client, path, description, owner).await?;
+        }
+        Commands::ConfigFile(ConfigFileCommands::List) => {
+            commands::config_file::list(&client).await?;
+        }
+        Commands::ConfigFile(ConfigFileCommands::Delete { config }) => {
+            commands::config_file::delete(&client, config).await?;
+        }
+        Commands::Audit {
+            server,
+            since,
+            limit,
+            page,
+        } => {
+            commands::audit_log::execute(&client, server, since, limit, page).await?;
+        }
+        Commands::Key(KeyCommands::Create { name, role }) => {
+            commands::api_key::create(&client, name, role).await?;
+        }
+        Commands::Key(KeyCommands::List) => {
+            commands::api_key::list(&client).await?;
+        }
+        Commands::Key(KeyCommands::Delete { key_id }) => {
+            commands::api_key::delete(&client, key_id).await?;
+        }
+        Commands::Whoami => {
+            commands::api_key::whoami(&client).await?;
         }
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn config_is_both_the_settings_file_and_the_config_file_to_add() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from([
+            "homelab",
+            "--config",
+            "work.yaml",
+            "add",
+            "--server",
+            "web-02",
+            "--config",
+            "/etc/nixos/common-web.nix",
+        ])
+        .unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("work.yaml")));
+        let Commands::Add { config_file, .. } = cli.command else {
+            panic!("expected the add command");
+        };
+        assert_eq!(config_file.as_deref(), Some("/etc/nixos/common-web.nix"));
+
+        let cli = Cli::try_parse_from(["homelab", "-c", "work.yaml", "list"]).unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("work.yaml")));
+    }
+}
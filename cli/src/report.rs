@@ -0,0 +1,138 @@
+use anyhow::Error;
+use homelab_client::{ClientError, StatusCode};
+use std::fmt;
+use std::process::ExitCode;
+
+// Exit codes, so scripts can tell failures apart. clap exits with 2 on usage errors.
+const EXIT_FAILURE: u8 = 1;
+const EXIT_NOT_FOUND: u8 = 3;
+const EXIT_CONFLICT: u8 = 4;
+const EXIT_VERSION_MISMATCH: u8 = 5;
+const EXIT_INVALID_REQUEST: u8 = 6;
+const EXIT_UNAUTHORIZED: u8 = 7;
+const EXIT_SERVER_ERROR: u8 = 8;
+const EXIT_UNREACHABLE: u8 = 9;
+
+/// Maps request body fields to the command-line flags that set them, e.g. `("server_name", "--server")`.
+pub type Flags = &'static [(&'static str, &'static str)];
+
+/// A failed API call, with a message saying what was being done or what to do about it.
+#[derive(Debug)]
+struct Failure {
+    message: String,
+    error: ClientError,
+    flags: Flags,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Something looked up by name or ID on the CLI side that does not exist.
+#[derive(Debug)]
+struct NotFound(String);
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+/// `error` prefixed with what failed, e.g. `failed("Failed to add server", e)`.
+pub fn failed(what: &str, error: ClientError) -> Error {
+    failed_with_flags(what, error, &[])
+}
+
+/// Like `failed`, but invalid fields are reported by the flags that set them.
+pub fn failed_with_flags(what: &str, error: ClientError, flags: Flags) -> Error {
+    Error::new(Failure {
+        message: format!("{}: {}", what, error),
+        error,
+        flags,
+    })
+}
+
+/// `error` explained by `message`, which replaces the API's own message.
+pub fn explain(error: ClientError, message: String) -> Error {
+    Error::new(Failure {
+        message,
+        error,
+        flags: &[],
+    })
+}
+
+/// A "no such thing" error that exits like an API 404.
+pub fn not_found(message: String) -> Error {
+    Error::new(NotFound(message))
+}
+
+/// Prints `error` to stderr, with any field details and the API request ID,
+/// and returns the exit code for it.
+pub fn report(error: &Error) -> ExitCode {
+    if error.is::<NotFound>() {
+        eprintln!("Error: {}", error);
+        return ExitCode::from(EXIT_NOT_FOUND);
+    }
+
+    let (client_error, flags) = match error.downcast_ref::<Failure>() {
+        Some(failure) => (Some(&failure.error), failure.flags),
+        None => (error.downcast_ref::<ClientError>(), &[][..]),
+    };
+    let Some(client_error) = client_error else {
+        eprintln!("Error: {:#}", error);
+        return ExitCode::from(EXIT_FAILURE);
+    };
+
+    eprintln!("Error: {}", error);
+    if let Some(body) = client_error.api_error() {
+        for detail in &body.details {
+            match flag_for(&detail.field, flags) {
+                Some(flag) => eprintln!("  {}: {}", flag, detail.message),
+                // A single invalid field usually is the message itself
+                None if detail.message == body.error => {}
+                None => eprintln!("  {}: {}", detail.field, detail.message),
+            }
+        }
+        if let Some(request_id) = &body.request_id {
+            eprintln!("  Request ID: {}", request_id);
+        }
+    }
+    if client_error.status() == Some(StatusCode::UNAUTHORIZED) {
+        eprintln!("  Set HOMELAB_TOKEN, or `token` in the config file, to an API key.");
+    }
+    if client_error.status() == Some(StatusCode::FORBIDDEN) {
+        eprintln!("  Run `homelab whoami` to see your role.");
+    }
+    ExitCode::from(exit_code(client_error))
+}
+
+/// The flag for `field`, or for the field it is part of (`labels.role`, `modules[2]`).
+fn flag_for(field: &str, flags: Flags) -> Option<&'static str> {
+    flags.iter().find_map(|&(name, flag)| {
+        let rest = field.strip_prefix(name)?;
+        (rest.is_empty() || rest.starts_with('.') || rest.starts_with('[')).then_some(flag)
+    })
+}
+
+fn exit_code(error: &ClientError) -> u8 {
+    match error {
+        ClientError::InvalidUrl(_) => EXIT_FAILURE,
+        ClientError::Transport(_) => EXIT_UNREACHABLE,
+        ClientError::Decode(_) => EXIT_SERVER_ERROR,
+        ClientError::Api { status, .. } => match *status {
+            StatusCode::NOT_FOUND => EXIT_NOT_FOUND,
+            StatusCode::CONFLICT => EXIT_CONFLICT,
+            StatusCode::PRECONDITION_FAILED => EXIT_VERSION_MISMATCH,
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => EXIT_INVALID_REQUEST,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => EXIT_UNAUTHORIZED,
+            status if status.is_server_error() => EXIT_SERVER_ERROR,
+            _ => EXIT_FAILURE,
+        },
+    }
+}
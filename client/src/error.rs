@@ -0,0 +1,78 @@
+use homelab_api::responses::{ErrorCode, ErrorResponse};
+use reqwest::StatusCode;
+use std::fmt;
+
+/// Everything that can go wrong calling the API.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The API URL, or a URL built from it, could not be used.
+    InvalidUrl(String),
+    /// The request could not be sent or its response not read, including timeouts.
+    Transport(reqwest::Error),
+    /// The API answered with an error status.
+    Api {
+        status: StatusCode,
+        error: Box<ErrorResponse>,
+    },
+    /// The API answered successfully, but not with the expected body.
+    Decode(serde_json::Error),
+}
+
+impl ClientError {
+    /// HTTP status of an API error, if the API answered at all.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ClientError::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// The error body of an API error.
+    pub fn api_error(&self) -> Option<&ErrorResponse> {
+        match self {
+            ClientError::Api { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Stable code of an API error; `Unknown` when the API did not send one.
+    pub fn code(&self) -> Option<ErrorCode> {
+        self.api_error().map(|error| error.code)
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(StatusCode::NOT_FOUND)
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, ClientError::Transport(e) if e.is_timeout())
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::InvalidUrl(url) => write!(f, "Invalid API URL: {}", url),
+            ClientError::Transport(e) if e.is_timeout() => write!(f, "Request timed out: {}", e),
+            ClientError::Transport(e) => write!(f, "Request failed: {}", e),
+            ClientError::Api { error, .. } => write!(f, "{}", error.error),
+            ClientError::Decode(e) => write!(f, "Unexpected response from the API: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Transport(e) => Some(e),
+            ClientError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Transport(e)
+    }
+}
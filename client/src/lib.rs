@@ -0,0 +1,488 @@
+//! Typed async client for the homelab HTTP API.
+//!
+//! A `HomelabClient` holds one connection pool; clone it to share the pool
+//! between tasks. Every endpoint has a method taking and returning the types
+//! from `homelab_api`, and every failure is a `ClientError`.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use homelab_api::api_key::{ApiKey, CreateApiKeyRequest};
+use homelab_api::config_file::{ConfigFile, CreateConfigFileRequest};
+use homelab_api::responses::{
+    ApiKeyCreatedResponse, ApiKeyDeleteResponse, ApiKeyListResponse, AuditLogResponse,
+    ConfigFileListResponse, ConfigFileWriteResponse, ConfigServersResponse, DeleteServerResponse,
+    ErrorCode, ErrorResponse, RollbackResponse, ServerHistoryResponse, ServerListResponse,
+    ServerMatchesResponse, ServerWriteResponse, WhoAmIResponse,
+};
+use homelab_api::server_config::{CreateServerRequest, ServerConfig, UpdateServerRequest};
+use homelab_api::server_revision::RollbackRequest;
+use reqwest::{RequestBuilder, Url};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+mod error;
+
+pub use error::ClientError;
+pub use reqwest::StatusCode;
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// Used when the caller has no timeout configured.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Filters and paging for `list_servers`.
+#[derive(Debug, Clone, Default)]
+pub struct ListServersQuery {
+    pub include_deleted: bool,
+    /// Only servers carrying every one of these `key=value` labels.
+    pub labels: Vec<(String, String)>,
+    pub limit: Option<u32>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+/// Filters and paging for `list_audit`.
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub server_id: Option<String>,
+    /// Only events recorded at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HomelabClient {
+    http: reqwest::Client,
+    api_url: Url,
+    token: Option<String>,
+}
+
+impl HomelabClient {
+    /// Creates a client for the API at `api_url`; every request fails once
+    /// `timeout` has passed without a complete response.
+    pub fn new(api_url: &str, timeout: Duration) -> Result<Self> {
+        let api_url =
+            Url::parse(api_url).map_err(|_| ClientError::InvalidUrl(api_url.to_string()))?;
+        if api_url.cannot_be_a_base() {
+            return Err(ClientError::InvalidUrl(api_url.to_string()));
+        }
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(concat!("homelab-client/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            http,
+            api_url,
+            token: None,
+        })
+    }
+
+    /// Sends `token` (an API key) as `Authorization: Bearer <token>` with every request.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn api_url(&self) -> &Url {
+        &self.api_url
+    }
+
+    pub async fn create_server(
+        &self,
+        request: &CreateServerRequest,
+    ) -> Result<ServerWriteResponse> {
+        self.send(self.http.post(self.url(&["servers"])).json(request))
+            .await
+    }
+
+    /// One page of servers, oldest first.
+    pub async fn list_servers(&self, query: &ListServersQuery) -> Result<ServerListResponse> {
+        let mut request = self.http.get(self.url(&["servers"]));
+        if query.include_deleted {
+            request = request.query(&[("include_deleted", "true")]);
+        }
+        for (key, value) in &query.labels {
+            request = request.query(&[("label", format!("{}={}", key, value))]);
+        }
+        if let Some(limit) = query.limit {
+            request = request.query(&[("limit", limit)]);
+        }
+        if let Some(cursor) = &query.cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        self.send(request).await
+    }
+
+    /// Every server named `server_name`; more than one only while names are being fixed up.
+    pub async fn find_servers_by_name(
+        &self,
+        server_name: &str,
+        include_deleted: bool,
+    ) -> Result<Vec<ServerConfig>> {
+        let request = self
+            .http
+            .get(self.url(&["servers", "by-name", server_name]))
+            .query(&[("include_deleted", include_deleted)]);
+        Ok(self.send::<ServerMatchesResponse>(request).await?.servers)
+    }
+
+    /// Servers using a config file, given by ID or path, or a bare configuration path.
+    pub async fn servers_using_config(
+        &self,
+        config: &str,
+        include_deleted: bool,
+    ) -> Result<ConfigServersResponse> {
+        let request = self
+            .http
+            .get(self.url(&["configs", config, "servers"]))
+            .query(&[("include_deleted", include_deleted)]);
+        self.send(request).await
+    }
+
+    /// The server with ID `server_id`, including servers in the trash.
+    pub async fn get_server(&self, server_id: &str) -> Result<ServerConfig> {
+        self.send(self.http.get(self.url(&["servers", server_id])))
+            .await
+    }
+
+    /// Applies `changes`; with `expected_version`, only if the server is still at that version.
+    pub async fn update_server(
+        &self,
+        server_id: &str,
+        changes: &UpdateServerRequest,
+        expected_version: Option<u64>,
+    ) -> Result<ServerWriteResponse> {
+        let request = self
+            .http
+            .put(self.url(&["servers", server_id]))
+            .json(changes);
+        self.send(if_match(request, expected_version)).await
+    }
+
+    /// Moves a server into the trash, or removes it for good when `purge` is set.
+    pub async fn delete_server(
+        &self,
+        server_id: &str,
+        purge: bool,
+        expected_version: Option<u64>,
+    ) -> Result<DeleteServerResponse> {
+        let mut request = self.http.delete(self.url(&["servers", server_id]));
+        if purge {
+            request = request.query(&[("purge", "true")]);
+        }
+        self.send(if_match(request, expected_version)).await
+    }
+
+    /// Takes a server back out of the trash.
+    pub async fn restore_server(
+        &self,
+        server_id: &str,
+        expected_version: Option<u64>,
+    ) -> Result<ServerWriteResponse> {
+        let request = self.http.post(self.url(&["servers", server_id, "restore"]));
+        self.send(if_match(request, expected_version)).await
+    }
+
+    /// Brings a server's fields back to those recorded in `revision`.
+    pub async fn rollback_server(
+        &self,
+        server_id: &str,
+        revision: u64,
+        expected_version: Option<u64>,
+    ) -> Result<RollbackResponse> {
+        let request = self
+            .http
+            .post(self.url(&["servers", server_id, "rollback"]))
+            .json(&RollbackRequest { revision });
+        self.send(if_match(request, expected_version)).await
+    }
+
+    /// Every recorded revision of a server, oldest first.
+    pub async fn server_history(&self, server_id: &str) -> Result<ServerHistoryResponse> {
+        self.send(self.http.get(self.url(&["servers", server_id, "history"])))
+            .await
+    }
+
+    /// One page of audit events, oldest first.
+    pub async fn list_audit(&self, query: &AuditQuery) -> Result<AuditLogResponse> {
+        let mut request = self.http.get(self.url(&["audit"]));
+        if let Some(server_id) = &query.server_id {
+            request = request.query(&[("server_id", server_id)]);
+        }
+        if let Some(since) = &query.since {
+            request =
+                request.query(&[("since", since.to_rfc3339_opts(SecondsFormat::AutoSi, true))]);
+        }
+        if let Some(limit) = query.limit {
+            request = request.query(&[("limit", limit)]);
+        }
+        if let Some(cursor) = &query.cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        self.send(request).await
+    }
+
+    pub async fn create_config_file(
+        &self,
+        request: &CreateConfigFileRequest,
+    ) -> Result<ConfigFileWriteResponse> {
+        self.send(self.http.post(self.url(&["configs"])).json(request))
+            .await
+    }
+
+    pub async fn list_config_files(&self) -> Result<ConfigFileListResponse> {
+        self.send(self.http.get(self.url(&["configs"]))).await
+    }
+
+    /// A config file by ID or path.
+    pub async fn get_config_file(&self, config: &str) -> Result<ConfigFile> {
+        self.send(self.http.get(self.url(&["configs", config])))
+            .await
+    }
+
+    /// Deletes a config file by ID or path; refused while servers still use it.
+    pub async fn delete_config_file(&self, config: &str) -> Result<ConfigFileWriteResponse> {
+        self.send(self.http.delete(self.url(&["configs", config])))
+            .await
+    }
+
+    /// Creates an API key; the response holds its token, which cannot be retrieved later.
+    pub async fn create_api_key(
+        &self,
+        request: &CreateApiKeyRequest,
+    ) -> Result<ApiKeyCreatedResponse> {
+        self.send(self.http.post(self.url(&["keys"])).json(request))
+            .await
+    }
+
+    pub async fn list_api_keys(&self) -> Result<Vec<ApiKey>> {
+        Ok(self
+            .send::<ApiKeyListResponse>(self.http.get(self.url(&["keys"])))
+            .await?
+            .keys)
+    }
+
+    /// Revokes an API key; requests using its token fail from then on.
+    pub async fn delete_api_key(&self, key_id: &str) -> Result<ApiKeyDeleteResponse> {
+        self.send(self.http.delete(self.url(&["keys", key_id])))
+            .await
+    }
+
+    /// Who the API authenticates this client as, and its role.
+    pub async fn whoami(&self) -> Result<WhoAmIResponse> {
+        self.send(self.http.get(self.url(&["whoami"]))).await
+    }
+
+    /// Appends percent-encoded path segments to the API URL, so IDs and
+    /// config paths containing `/` stay within their segment.
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.api_url.clone();
+        // `new` rejected URLs that cannot be a base, so this cannot fail
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        url
+    }
+
+    async fn send<T: DeserializeOwned>(&self, mut request: RequestBuilder) -> Result<T> {
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+
+        if !status.is_success() {
+            // Errors from in front of the API (gateways, proxies) are not always JSON
+            let error = serde_json::from_slice(&body).unwrap_or_else(|_| {
+                let text = String::from_utf8_lossy(&body);
+                match text.trim() {
+                    "" => ErrorResponse::new(ErrorCode::Unknown, status.to_string()),
+                    text => ErrorResponse::new(ErrorCode::Unknown, text),
+                }
+            });
+            return Err(ClientError::Api {
+                status,
+                error: Box::new(error),
+            });
+        }
+        serde_json::from_slice(&body).map_err(ClientError::Decode)
+    }
+}
+
+fn if_match(request: RequestBuilder, expected_version: Option<u64>) -> RequestBuilder {
+    match expected_version {
+        Some(version) => request.header("If-Match", format!("\"{}\"", version)),
+        None => request,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Answers one request on a local port with `response`, returning the
+    /// API URL and a handle yielding the request's head as it was received.
+    fn serve_once(response: String) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api_url = format!("http://{}/prod", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                if line.trim().is_empty() {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            reader
+                .by_ref()
+                .take(content_length)
+                .read_to_end(&mut Vec::new())
+                .unwrap();
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            head
+        });
+        (api_url, handle)
+    }
+
+    fn response(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+        let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", status, body.len());
+        for (name, value) in headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response + "connection: close\r\n\r\n" + body
+    }
+
+    fn client(api_url: &str) -> HomelabClient {
+        HomelabClient::new(api_url, DEFAULT_TIMEOUT).unwrap()
+    }
+
+    /// The value of header `name` in a request head, if sent.
+    fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+        head.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    #[test]
+    fn url_percent_encodes_each_segment() {
+        for api_url in [
+            "https://api.example.com/prod",
+            "https://api.example.com/prod/",
+        ] {
+            let url = client(api_url).url(&["configs", "/etc/nixos/web server.nix", "servers"]);
+            assert_eq!(
+                url.as_str(),
+                "https://api.example.com/prod/configs/%2Fetc%2Fnixos%2Fweb%20server.nix/servers"
+            );
+        }
+
+        let url = client("https://api.example.com").url(&["servers", "a?b#c"]);
+        assert_eq!(url.as_str(), "https://api.example.com/servers/a%3Fb%23c");
+    }
+
+    #[test]
+    fn urls_that_cannot_be_a_base_are_rejected() {
+        assert!(matches!(
+            HomelabClient::new("mailto:ops@example.com", DEFAULT_TIMEOUT),
+            Err(ClientError::InvalidUrl(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn api_errors_are_decoded() {
+        let mut error = ErrorResponse::new(ErrorCode::VersionMismatch, "Server has been modified");
+        error.current_version = Some(4);
+        error.request_id = Some("req-1".to_string());
+        let body = serde_json::to_string(&error).unwrap();
+        let (api_url, _) = serve_once(response("412 Precondition Failed", &[], &body));
+
+        let error = client(&api_url)
+            .restore_server("srv-1", Some(3))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::PRECONDITION_FAILED));
+        assert_eq!(error.code(), Some(ErrorCode::VersionMismatch));
+        let api_error = error.api_error().unwrap();
+        assert_eq!(api_error.current_version, Some(4));
+        assert_eq!(api_error.request_id.as_deref(), Some("req-1"));
+    }
+
+    #[tokio::test]
+    async fn unknown_error_codes_decode_as_unknown() {
+        let body = r#"{"error": "Slow down", "code": "rate_limited"}"#;
+        let (api_url, _) = serve_once(response("429 Too Many Requests", &[], body));
+
+        let error = client(&api_url).whoami().await.unwrap_err();
+        assert_eq!(error.code(), Some(ErrorCode::Unknown));
+        assert_eq!(error.api_error().unwrap().error, "Slow down");
+    }
+
+    #[tokio::test]
+    async fn non_json_errors_fall_back_to_unknown() {
+        let (api_url, _) = serve_once(response("502 Bad Gateway", &[], "upstream timed out\n"));
+        let error = client(&api_url).whoami().await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::BAD_GATEWAY));
+        assert_eq!(error.code(), Some(ErrorCode::Unknown));
+        assert_eq!(error.api_error().unwrap().error, "upstream timed out");
+
+        // Without a body, the status line stands in for the message
+        let (api_url, _) = serve_once(response("503 Service Unavailable", &[], ""));
+        let error = client(&api_url).whoami().await.unwrap_err();
+        assert_eq!(error.code(), Some(ErrorCode::Unknown));
+        assert_eq!(
+            error.api_error().unwrap().error,
+            StatusCode::SERVICE_UNAVAILABLE.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn expected_versions_are_sent_as_if_match() {
+        let written = ServerWriteResponse {
+            message: "Server updated".to_string(),
+            server_id: "srv-1".to_string(),
+            version: 4,
+        };
+        let body = serde_json::to_string(&written).unwrap();
+        let (api_url, request) = serve_once(response("200 OK", &[("ETag", "\"4\"")], &body));
+
+        let written = client(&api_url)
+            .with_token("hlk_key_secret")
+            .update_server("srv-1", &UpdateServerRequest::default(), Some(3))
+            .await
+            .unwrap();
+        assert_eq!(written.version, 4);
+        let head = request.join().unwrap();
+        assert!(head.starts_with("PUT /prod/servers/srv-1 "));
+        // The same quoted form as the ETag the API returns
+        assert_eq!(header(&head, "if-match"), Some("\"3\""));
+        assert_eq!(
+            header(&head, "authorization"),
+            Some("Bearer hlk_key_secret")
+        );
+
+        let (api_url, request) = serve_once(response("200 OK", &[], &body));
+        client(&api_url)
+            .update_server("srv-1", &UpdateServerRequest::default(), None)
+            .await
+            .unwrap();
+        let head = request.join().unwrap();
+        assert_eq!(header(&head, "if-match"), None);
+        assert_eq!(header(&head, "authorization"), None);
+    }
+}
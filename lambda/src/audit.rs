@@ -0,0 +1,154 @@
+//! Audit trail of every mutation made through the API.
+//!
+//! Handlers pass the store an `AuditContext` built by `context`, and the store
+//! writes the audit event in the same atomic write as the change, so a change
+//! is never stored without its event or the other way round.
+
+use chrono::Utc;
+use homelab_api::audit_event::AuditAction;
+use lambda_http::request::RequestContext;
+use lambda_http::{Request, RequestExt};
+use uuid::Uuid;
+
+use crate::auth::{gateway_principal, Identity};
+use crate::store::AuditContext;
+
+/// Actor recorded for servers purged once their time in the trash runs out.
+pub const TRASH_PURGE_ACTOR: &str = "trash-retention";
+
+/// Header a client may send its own request ID in, recorded as `client_request_id`.
+pub const CLIENT_REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Longest `X-Request-Id` kept; anything longer is dropped rather than stored.
+const MAX_CLIENT_REQUEST_ID_LENGTH: usize = 128;
+
+/// A request ID generated by this service, kept in the request's extensions
+/// when API Gateway did not assign one.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Identifies the caller of a request: the identity `function_handler`
+/// authenticated, or else whatever principal API Gateway established.
+pub fn actor(event: &Request) -> String {
+    match event.extensions().get::<Identity>() {
+        Some(identity) => identity.actor(),
+        None => gateway_principal(event).unwrap_or_else(|| Identity::Anonymous.actor()),
+    }
+}
+
+/// The API Gateway request ID, or else the one this service generated. Never
+/// taken from the client, so log entries cannot be attributed to another request.
+pub fn request_id(event: &Request) -> Option<String> {
+    let from_context = match event.request_context_ref() {
+        Some(RequestContext::ApiGatewayV1(context)) => context.request_id.clone(),
+        Some(RequestContext::ApiGatewayV2(context)) => context.request_id.clone(),
+        _ => None,
+    };
+
+    from_context.or_else(|| {
+        event
+            .extensions()
+            .get::<RequestId>()
+            .map(|request_id| request_id.0.clone())
+    })
+}
+
+/// Returns the ID of `event`, generating one first if it has none yet.
+pub fn assign_request_id(event: &mut Request) -> String {
+    match request_id(event) {
+        Some(request_id) => request_id,
+        None => {
+            let request_id = Uuid::new_v4().to_string();
+            event.extensions_mut().insert(RequestId(request_id.clone()));
+            request_id
+        }
+    }
+}
+
+/// The request ID the client sent in `X-Request-Id`, if it is a reasonable length.
+pub fn client_request_id(event: &Request) -> Option<String> {
+    event
+        .headers()
+        .get(CLIENT_REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_CLIENT_REQUEST_ID_LENGTH)
+        .map(str::to_string)
+}
+
+/// Describes the change `event` is about to make, for the store to record
+/// alongside it.
+pub fn context(event: &Request, action: AuditAction) -> AuditContext {
+    AuditContext {
+        action,
+        actor: actor(event),
+        request_id: request_id(event),
+        client_request_id: client_request_id(event),
+        at: Utc::now(),
+    }
+}
+
+/// Describes a change the service makes on its own behalf, such as the
+/// scheduled purge of the trash, recorded as made by `actor`.
+pub fn system(actor: &str, action: AuditAction) -> AuditContext {
+    AuditContext {
+        action,
+        actor: actor.to_string(),
+        request_id: None,
+        client_request_id: None,
+        at: Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lambda_http::Body;
+
+    const REST_COGNITO: &str = include_str!("../tests/data/rest_cognito_authorizer.json");
+
+    fn with_client_request_id(value: &str) -> Request {
+        http::Request::builder()
+            .header(CLIENT_REQUEST_ID_HEADER, value)
+            .body(Body::Empty)
+            .unwrap()
+    }
+
+    #[test]
+    fn api_gateway_request_ids_are_kept() {
+        let mut event = lambda_http::request::from_str(REST_COGNITO).unwrap();
+        assert_eq!(
+            assign_request_id(&mut event),
+            "41b45ea3-70b5-11e6-b7bd-69b5aaebc7d9"
+        );
+        assert!(event.extensions().get::<RequestId>().is_none());
+        assert_eq!(
+            context(&event, AuditAction::Create).actor,
+            "7d8ca528-4931-4254-9273-ea5ee853f271"
+        );
+    }
+
+    #[test]
+    fn request_ids_are_generated_once_and_never_taken_from_the_client() {
+        let mut event = with_client_request_id("req-1");
+        let request_id = assign_request_id(&mut event);
+        assert!(Uuid::parse_str(&request_id).is_ok());
+        assert_eq!(assign_request_id(&mut event), request_id);
+
+        let audit = context(&event, AuditAction::Update);
+        assert_eq!(audit.request_id.as_deref(), Some(request_id.as_str()));
+        assert_eq!(audit.client_request_id.as_deref(), Some("req-1"));
+        assert_eq!(audit.actor, "anonymous");
+    }
+
+    #[test]
+    fn client_request_ids_must_be_a_reasonable_length() {
+        let longest = "a".repeat(MAX_CLIENT_REQUEST_ID_LENGTH);
+        assert_eq!(
+            client_request_id(&with_client_request_id(&longest)).as_deref(),
+            Some(longest.as_str())
+        );
+        let too_long = "a".repeat(MAX_CLIENT_REQUEST_ID_LENGTH + 1);
+        assert_eq!(client_request_id(&with_client_request_id(&too_long)), None);
+        assert_eq!(client_request_id(&with_client_request_id("")), None);
+    }
+}
@@ -0,0 +1,712 @@
+//! Authentication of API callers.
+//!
+//! Callers send an API key as `Authorization: Bearer <token>`. Tokens look
+//! like `hlk_<key_id>_<secret>`; only a SHA-256 hash of the secret is stored,
+//! so a leaked table does not leak working tokens. Callers already
+//! authenticated by an API Gateway authorizer are accepted as they are;
+//! without one, a `JwtVerifier` can check bearer JWTs itself.
+//!
+//! Every caller has a `Role`: API keys carry one, and for authorizer callers
+//! it comes from the caller's groups (or, only where the identity provider
+//! keeps users from setting it, a `role` claim). `function_handler` checks it
+//! against the route.
+
+use chrono::Utc;
+use homelab_api::api_key::{ApiKey, Role};
+use homelab_api::responses::WhoAmIResponse;
+use lambda_http::request::RequestContext;
+use lambda_http::{Error, Request, RequestExt};
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::jwt::JwtVerifier;
+use crate::store::{ServerStore, StoredApiKey};
+
+/// Prefix of every API key token, so they are easy to recognise (and to scan for in leaked code).
+const TOKEN_PREFIX: &str = "hlk_";
+
+/// Random bytes in a token's secret.
+const SECRET_LENGTH: usize = 32;
+
+/// Actor recorded when a request carries no identity at all.
+const ANONYMOUS: &str = "anonymous";
+
+/// Authorizer claims or context values that can carry a caller's role;
+/// `custom:role` is how Cognito names a custom attribute. Only read when
+/// `AUTHORIZER_TRUST_ROLE_CLAIMS` is set, as Cognito users can write their own
+/// custom attributes unless the app client is configured otherwise.
+const ROLE_CLAIMS: &[&str] = &["role", "custom:role"];
+
+/// Claims that can carry a caller's groups: Cognito's, then the usual OIDC one.
+const GROUP_CLAIMS: &[&str] = &["cognito:groups", "groups"];
+
+/// Whether requests without credentials are turned away, and how authorizer
+/// callers get their role.
+#[derive(Debug, Clone)]
+pub struct Auth {
+    require_auth: bool,
+    /// Role of authorizer callers that neither claim one nor belong to a group in `group_roles`.
+    authorizer_role: Role,
+    group_roles: Vec<(String, Role)>,
+    /// Whether a `role` claim counts towards an authorizer caller's role.
+    trust_role_claims: bool,
+    /// Checks bearer JWTs when there is no API Gateway authorizer to do it.
+    jwt: Option<Arc<JwtVerifier>>,
+}
+
+impl Auth {
+    /// Reads `REQUIRE_AUTH`, under which authentication is required unless it
+    /// is `false`, `AUTHORIZER_DEFAULT_ROLE`, which defaults to `read_only`, and
+    /// `AUTHORIZER_GROUP_ROLES`, e.g. `homelab-admins=admin,homelab-ops=operator`,
+    /// and `AUTHORIZER_TRUST_ROLE_CLAIMS`, under which `role` claims are ignored
+    /// unless it is `true`. These apply to verified JWTs too; see `JwtVerifier::from_env`.
+    pub async fn from_env() -> Result<Self, Error> {
+        let require_auth = match env::var("REQUIRE_AUTH") {
+            Ok(value) => value
+                .parse()
+                .map_err(|_| "REQUIRE_AUTH must be true or false")?,
+            Err(_) => true,
+        };
+        if !require_auth {
+            tracing::warn!(
+                "REQUIRE_AUTH is false; requests without credentials are accepted as admin"
+            );
+        }
+        let authorizer_role = match env::var("AUTHORIZER_DEFAULT_ROLE") {
+            Ok(role) => role
+                .parse()
+                .map_err(|e| format!("AUTHORIZER_DEFAULT_ROLE: {}", e))?,
+            Err(_) => Role::ReadOnly,
+        };
+        let group_roles = match env::var("AUTHORIZER_GROUP_ROLES") {
+            Ok(value) => {
+                parse_group_roles(&value).map_err(|e| format!("AUTHORIZER_GROUP_ROLES: {}", e))?
+            }
+            Err(_) => Vec::new(),
+        };
+        let trust_role_claims = match env::var("AUTHORIZER_TRUST_ROLE_CLAIMS") {
+            Ok(value) => value
+                .parse()
+                .map_err(|_| "AUTHORIZER_TRUST_ROLE_CLAIMS must be true or false")?,
+            Err(_) => false,
+        };
+        let jwt = JwtVerifier::from_env().await?.map(Arc::new);
+        Ok(Self {
+            require_auth,
+            authorizer_role,
+            group_roles,
+            trust_role_claims,
+            jwt,
+        })
+    }
+
+    /// An `Auth` configured directly rather than from the environment.
+    #[cfg(test)]
+    pub(crate) fn new(
+        require_auth: bool,
+        authorizer_role: Role,
+        group_roles: Vec<(String, Role)>,
+    ) -> Self {
+        Self {
+            require_auth,
+            authorizer_role,
+            group_roles,
+            trust_role_claims: false,
+            jwt: None,
+        }
+    }
+
+    /// Lets `role` claims count towards authorizer callers' roles.
+    #[cfg(test)]
+    pub(crate) fn trusting_role_claims(self) -> Self {
+        Self {
+            trust_role_claims: true,
+            ..self
+        }
+    }
+
+    /// Identifies the caller of `event`, failing with `401` if it sent an
+    /// invalid API key, or none at all while authentication is required.
+    pub async fn authenticate(
+        &self,
+        store: &dyn ServerStore,
+        event: &Request,
+    ) -> Result<Identity, ApiError> {
+        let authorization = event
+            .headers()
+            .get(http::header::AUTHORIZATION)
+            .map(|value| value.to_str().unwrap_or_default());
+        let token = authorization.and_then(bearer_token);
+
+        if let Some(token) = token.filter(|token| token.starts_with(TOKEN_PREFIX)) {
+            return verify_token(store, token).await.map(Identity::ApiKey);
+        }
+        // JWT and IAM authorizers use the Authorization header themselves
+        if let Some(subject) = gateway_principal(event) {
+            let identity = self.authorizer_identity(subject, &authorizer_claims(event));
+            return Ok(Identity::Authorizer(identity));
+        }
+        if let (Some(token), Some(jwt)) = (token, &self.jwt) {
+            return self.verify_jwt(jwt, token).await.map(Identity::Jwt);
+        }
+        match authorization {
+            Some(_) => Err(ApiError::Unauthorized("Invalid API key")),
+            None if self.require_auth => Err(ApiError::Unauthorized(
+                "Authentication required; send an API key as 'Authorization: Bearer <token>'",
+            )),
+            None => Ok(Identity::Anonymous),
+        }
+    }
+
+    /// The identity of the bearer of a JWT, or `401` if it does not verify.
+    async fn verify_jwt(
+        &self,
+        jwt: &JwtVerifier,
+        token: &str,
+    ) -> Result<AuthorizerIdentity, ApiError> {
+        let claims = match jwt.verify(token).await {
+            Ok(claims) => claims,
+            Err(e) => {
+                tracing::info!("Rejected bearer token: {:#}", e);
+                return Err(ApiError::Unauthorized("Invalid or expired token"));
+            }
+        };
+        let subject = claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or(ApiError::Unauthorized("Token has no subject"))?;
+
+        let mut values = HashMap::new();
+        add_claims(&mut values, &claims);
+        Ok(self.authorizer_identity(subject, &values))
+    }
+
+    /// The identity of a caller an authorizer authenticated as `subject`. Its
+    /// role is the highest of the roles of its groups and, if trusted, any
+    /// `role` claim, or else the default.
+    fn authorizer_identity(
+        &self,
+        subject: String,
+        claims: &HashMap<String, String>,
+    ) -> AuthorizerIdentity {
+        let groups = GROUP_CLAIMS
+            .iter()
+            .find_map(|claim| claims.get(*claim))
+            .map(|value| parse_groups(value))
+            .unwrap_or_default();
+
+        let claimed_role = ROLE_CLAIMS
+            .iter()
+            .filter(|_| self.trust_role_claims)
+            .find_map(|claim| claims.get(*claim))
+            .and_then(|value| match value.parse() {
+                Ok(role) => Some(role),
+                Err(e) => {
+                    tracing::warn!("Ignoring role claim of {}: {}", subject, e);
+                    None
+                }
+            });
+        let group_role = self
+            .group_roles
+            .iter()
+            .filter(|(group, _)| groups.contains(group))
+            .map(|(_, role)| *role)
+            .max();
+
+        AuthorizerIdentity {
+            email: claims.get("email").cloned(),
+            role: claimed_role.max(group_role).unwrap_or(self.authorizer_role),
+            subject,
+            groups,
+        }
+    }
+}
+
+/// A caller authenticated by an API Gateway authorizer or a verified JWT, as
+/// described by the claims.
+#[derive(Debug, Clone)]
+pub struct AuthorizerIdentity {
+    /// The authorizer's principal (see `gateway_principal`), or the JWT's `sub`.
+    pub subject: String,
+    pub email: Option<String>,
+    pub groups: Vec<String>,
+    pub role: Role,
+}
+
+/// Who made a request, as established by `Auth::authenticate`.
+#[derive(Debug, Clone)]
+pub enum Identity {
+    ApiKey(ApiKey),
+    Authorizer(AuthorizerIdentity),
+    /// The bearer of a JWT checked by `JwtVerifier`.
+    Jwt(AuthorizerIdentity),
+    /// Nobody; only while `REQUIRE_AUTH` is `false`, when everything is allowed.
+    Anonymous,
+}
+
+impl Identity {
+    /// How the caller is recorded in the audit log and on the keys it creates.
+    pub fn actor(&self) -> String {
+        match self {
+            Identity::ApiKey(key) => format!("api-key/{}", key.key_id),
+            Identity::Authorizer(identity) | Identity::Jwt(identity) => identity.subject.clone(),
+            Identity::Anonymous => ANONYMOUS.to_string(),
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        match self {
+            Identity::ApiKey(key) => key.role,
+            Identity::Authorizer(identity) | Identity::Jwt(identity) => identity.role,
+            Identity::Anonymous => Role::Admin,
+        }
+    }
+
+    /// Fails with `403` unless the caller has at least `required`.
+    pub fn require(&self, required: Role) -> Result<(), ApiError> {
+        match self.role() {
+            role if role >= required => Ok(()),
+            role => Err(ApiError::Forbidden { required, role }),
+        }
+    }
+
+    pub fn whoami(&self) -> WhoAmIResponse {
+        let mut response = WhoAmIResponse {
+            actor: self.actor(),
+            role: self.role(),
+            authenticated_by: "anonymous".to_string(),
+            key_id: None,
+            key_name: None,
+            email: None,
+            groups: Vec::new(),
+        };
+        match self {
+            Identity::ApiKey(key) => {
+                response.authenticated_by = "api_key".to_string();
+                response.key_id = Some(key.key_id.clone());
+                response.key_name = Some(key.name.clone());
+            }
+            Identity::Authorizer(identity) | Identity::Jwt(identity) => {
+                let authenticated_by = if matches!(self, Identity::Jwt(_)) {
+                    "jwt"
+                } else {
+                    "authorizer"
+                };
+                response.authenticated_by = authenticated_by.to_string();
+                response.email = identity.email.clone();
+                response.groups = identity.groups.clone();
+            }
+            Identity::Anonymous => {}
+        }
+        response
+    }
+}
+
+/// The credentials of a `Bearer` authorization. The scheme is matched
+/// case-insensitively, as RFC 7235 requires.
+fn bearer_token(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.trim().split_once(char::is_whitespace)?;
+    scheme
+        .eq_ignore_ascii_case("Bearer")
+        .then_some(token.trim_start())
+}
+
+/// A new API key and the token for it, which is shown to its creator once and never stored.
+pub fn new_api_key(
+    name: String,
+    role: Role,
+    created_by: String,
+) -> anyhow::Result<(StoredApiKey, String)> {
+    let mut secret = [0u8; SECRET_LENGTH];
+    SystemRandom::new()
+        .fill(&mut secret)
+        .map_err(|_| anyhow::anyhow!("Failed to generate an API key secret"))?;
+    let secret = to_hex(&secret);
+
+    let key_id = Uuid::new_v4().simple().to_string();
+    let token = format!("{}{}_{}", TOKEN_PREFIX, key_id, secret);
+    let api_key = StoredApiKey {
+        key: ApiKey {
+            key_id,
+            name,
+            role,
+            created_at: Utc::now(),
+            created_by,
+        },
+        secret_hash: hash_secret(&secret),
+    };
+    Ok((api_key, token))
+}
+
+/// The key a token belongs to, or `401` if there is none or the secret does not match.
+async fn verify_token(store: &dyn ServerStore, token: &str) -> Result<ApiKey, ApiError> {
+    let (key_id, secret) = token
+        .strip_prefix(TOKEN_PREFIX)
+        .and_then(|rest| rest.split_once('_'))
+        .ok_or(ApiError::Unauthorized("Invalid API key"))?;
+
+    let stored = store
+        .get_api_key(key_id)
+        .await
+        .map_err(ApiError::store("Failed to check API key"))?
+        .ok_or(ApiError::Unauthorized("Invalid API key"))?;
+
+    if bool::from(
+        hash_secret(secret)
+            .as_bytes()
+            .ct_eq(stored.secret_hash.as_bytes()),
+    ) {
+        Ok(stored.key)
+    } else {
+        Err(ApiError::Unauthorized("Invalid API key"))
+    }
+}
+
+fn hash_secret(secret: &str) -> String {
+    to_hex(digest(&SHA256, secret.as_bytes()).as_ref())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The principal an API Gateway authorizer established for `event`, if any.
+///
+/// Prefers the authorizer's principal (a Lambda authorizer's `principalId`, or
+/// the `sub`/`email` claim of a JWT or Cognito authorizer), then the API key
+/// ID, then the IAM caller ARN.
+pub fn gateway_principal(event: &Request) -> Option<String> {
+    match event.request_context_ref() {
+        Some(RequestContext::ApiGatewayV1(context)) => principal(&context.authorizer)
+            .or_else(|| context.identity.api_key_id.clone())
+            .or_else(|| context.identity.user_arn.clone()),
+        Some(RequestContext::ApiGatewayV2(context)) => {
+            context.authorizer.as_ref().and_then(|authorizer| {
+                authorizer
+                    .jwt
+                    .as_ref()
+                    .and_then(|jwt| {
+                        jwt.claims
+                            .get("sub")
+                            .or_else(|| jwt.claims.get("email"))
+                            .cloned()
+                    })
+                    .or_else(|| principal(&authorizer.lambda))
+                    .or_else(|| authorizer.iam.as_ref().and_then(|iam| iam.user_arn.clone()))
+            })
+        }
+        _ => None,
+    }
+}
+
+fn principal(authorizer: &HashMap<String, Value>) -> Option<String> {
+    if let Some(Value::String(principal_id)) = authorizer.get("principalId") {
+        return Some(principal_id.clone());
+    }
+    let claims = authorizer.get("claims")?;
+    ["sub", "email"].iter().find_map(|claim| {
+        claims
+            .get(*claim)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    })
+}
+
+/// The claims an API Gateway authorizer passed along with `event`, or the
+/// context values of a Lambda authorizer, as text.
+fn authorizer_claims(event: &Request) -> HashMap<String, String> {
+    let mut claims = HashMap::new();
+    match event.request_context_ref() {
+        Some(RequestContext::ApiGatewayV1(context)) => {
+            // A Cognito authorizer nests its claims; a Lambda authorizer's context is flat
+            if let Some(Value::Object(nested)) = context.authorizer.get("claims") {
+                add_claims(&mut claims, nested);
+            }
+            add_claims(&mut claims, &context.authorizer);
+        }
+        Some(RequestContext::ApiGatewayV2(context)) => {
+            if let Some(authorizer) = &context.authorizer {
+                if let Some(jwt) = &authorizer.jwt {
+                    claims.extend(jwt.claims.clone());
+                }
+                add_claims(&mut claims, &authorizer.lambda);
+            }
+        }
+        _ => {}
+    }
+    claims
+}
+
+/// Adds the values of `values` that are not objects to `claims`, keeping any
+/// claim already there.
+fn add_claims<'a>(
+    claims: &mut HashMap<String, String>,
+    values: impl IntoIterator<Item = (&'a String, &'a Value)>,
+) {
+    for (name, value) in values {
+        let value = match value {
+            Value::String(value) => value.clone(),
+            Value::Object(_) | Value::Null => continue,
+            other => other.to_string(),
+        };
+        claims.entry(name.clone()).or_insert(value);
+    }
+}
+
+/// Splits a groups claim into group names. Depending on the authorizer, API
+/// Gateway passes a list claim on as a JSON array, as `[a b]` or as `a,b`.
+fn parse_groups(value: &str) -> Vec<String> {
+    if let Ok(groups) = serde_json::from_str(value) {
+        return groups;
+    }
+    value
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|group| !group.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses comma-separated `group=role` pairs.
+fn parse_group_roles(value: &str) -> anyhow::Result<Vec<(String, Role)>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (group, role) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Expected group=role, got '{}'", pair))?;
+            Ok((group.trim().to_string(), role.trim().parse()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::memory::MemoryStore;
+    use crate::store::test_audit;
+    use homelab_api::audit_event::AuditAction;
+
+    const REST_COGNITO: &str = include_str!("../tests/data/rest_cognito_authorizer.json");
+    const REST_LAMBDA: &str = include_str!("../tests/data/rest_lambda_authorizer.json");
+    const HTTP_JWT: &str = include_str!("../tests/data/http_jwt_authorizer.json");
+
+    fn auth(authorizer_role: Role) -> Auth {
+        let group_roles = vec![
+            ("homelab-admins".to_string(), Role::Admin),
+            ("homelab-ops".to_string(), Role::Operator),
+        ];
+        Auth::new(true, authorizer_role, group_roles)
+    }
+
+    /// The request API Gateway would hand over for `fixture`, after `edit`.
+    fn event(fixture: &str, edit: impl FnOnce(&mut Value)) -> Request {
+        let mut event: Value = serde_json::from_str(fixture).unwrap();
+        edit(&mut event);
+        lambda_http::request::from_str(&event.to_string()).unwrap()
+    }
+
+    async fn identify(auth: &Auth, event: &Request) -> AuthorizerIdentity {
+        match auth.authenticate(&MemoryStore::new(), event).await.unwrap() {
+            Identity::Authorizer(identity) => identity,
+            other => panic!("expected an authorizer identity, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn rest_cognito_claims() {
+        let identity = identify(&auth(Role::ReadOnly), &event(REST_COGNITO, |_| {})).await;
+        assert_eq!(identity.subject, "7d8ca528-4931-4254-9273-ea5ee853f271");
+        assert_eq!(identity.email.as_deref(), Some("alice@example.com"));
+        // `a,b` groups; homelab-ops outranks the read_only claim
+        assert_eq!(identity.groups, ["homelab-ops", "homelab-viewers"]);
+        assert_eq!(identity.role, Role::Operator);
+    }
+
+    #[tokio::test]
+    async fn http_jwt_claims() {
+        let identity = identify(&auth(Role::ReadOnly), &event(HTTP_JWT, |_| {})).await;
+        assert_eq!(identity.subject, "2f0c9a4e-1c55-4c1b-9d2e-8f0f6a2b7c11");
+        assert_eq!(identity.email.as_deref(), Some("carol@example.com"));
+        // `[a b]` groups
+        assert_eq!(identity.groups, ["homelab-admins", "homelab-viewers"]);
+        assert_eq!(identity.role, Role::Admin);
+    }
+
+    #[tokio::test]
+    async fn rest_lambda_authorizer_context() {
+        let identity = identify(&auth(Role::ReadOnly), &event(REST_LAMBDA, |_| {})).await;
+        assert_eq!(identity.subject, "user|bob");
+        assert_eq!(identity.email.as_deref(), Some("bob@example.com"));
+        // JSON array groups; the admin role claim is not trusted by default
+        assert_eq!(identity.groups, ["homelab-viewers", "homelab-ops"]);
+        assert_eq!(identity.role, Role::Operator);
+    }
+
+    #[tokio::test]
+    async fn role_claims_are_ignored_unless_trusted() {
+        let claims = "/requestContext/authorizer/claims";
+        let admin_claim = event(REST_COGNITO, |event| {
+            event.pointer_mut(claims).unwrap()["custom:role"] = "admin".into();
+        });
+        assert_eq!(
+            identify(&auth(Role::ReadOnly), &admin_claim).await.role,
+            Role::Operator
+        );
+
+        let role_only = event(HTTP_JWT, |event| {
+            let claims = event
+                .pointer_mut("/requestContext/authorizer/jwt/claims")
+                .unwrap();
+            claims["cognito:groups"] = "[]".into();
+            claims["role"] = "admin".into();
+        });
+        assert_eq!(
+            identify(&auth(Role::ReadOnly), &role_only).await.role,
+            Role::ReadOnly
+        );
+    }
+
+    #[tokio::test]
+    async fn trusted_role_claims_and_group_roles_combine_by_max() {
+        let auth = |role| auth(role).trusting_role_claims();
+        let claims = "/requestContext/authorizer/claims";
+        let admin_claim = event(REST_COGNITO, |event| {
+            event.pointer_mut(claims).unwrap()["custom:role"] = "admin".into();
+        });
+        assert_eq!(
+            identify(&auth(Role::ReadOnly), &admin_claim).await.role,
+            Role::Admin
+        );
+
+        let role_claim = event(HTTP_JWT, |event| {
+            let claims = event
+                .pointer_mut("/requestContext/authorizer/jwt/claims")
+                .unwrap();
+            claims["cognito:groups"] = "[homelab-viewers]".into();
+            claims["role"] = "operator".into();
+        });
+        assert_eq!(
+            identify(&auth(Role::ReadOnly), &role_claim).await.role,
+            Role::Operator
+        );
+
+        let group_only = event(REST_COGNITO, |event| {
+            event
+                .pointer_mut(claims)
+                .unwrap()
+                .as_object_mut()
+                .unwrap()
+                .remove("custom:role");
+        });
+        assert_eq!(
+            identify(&auth(Role::ReadOnly), &group_only).await.role,
+            Role::Operator
+        );
+    }
+
+    #[tokio::test]
+    async fn callers_without_a_role_get_the_default() {
+        let unmapped = event(REST_COGNITO, |event| {
+            let claims = event
+                .pointer_mut("/requestContext/authorizer/claims")
+                .unwrap()
+                .as_object_mut()
+                .unwrap();
+            claims.remove("custom:role");
+            claims["cognito:groups"] = "homelab-viewers".into();
+        });
+        assert_eq!(
+            identify(&auth(Role::ReadOnly), &unmapped).await.role,
+            Role::ReadOnly
+        );
+        assert_eq!(
+            identify(&auth(Role::Operator), &unmapped).await.role,
+            Role::Operator
+        );
+
+        let no_groups = event(HTTP_JWT, |event| {
+            let claims = event
+                .pointer_mut("/requestContext/authorizer/jwt/claims")
+                .unwrap()
+                .as_object_mut()
+                .unwrap();
+            claims.remove("cognito:groups");
+        });
+        let identity = identify(&auth(Role::Operator), &no_groups).await;
+        assert!(identity.groups.is_empty());
+        assert_eq!(identity.role, Role::Operator);
+
+        // A role claim that names no role is ignored
+        let unknown_role = event(REST_LAMBDA, |event| {
+            let authorizer = event.pointer_mut("/requestContext/authorizer").unwrap();
+            authorizer["role"] = "superuser".into();
+            authorizer["groups"] = "[]".into();
+        });
+        assert_eq!(
+            identify(&auth(Role::ReadOnly).trusting_role_claims(), &unknown_role)
+                .await
+                .role,
+            Role::ReadOnly
+        );
+    }
+
+    #[tokio::test]
+    async fn tokens_verify_only_against_their_own_secret() {
+        let store = MemoryStore::new();
+        let (api_key, token) =
+            new_api_key("ci".to_string(), Role::Operator, "admin".to_string()).unwrap();
+        // The secret itself is never stored
+        let secret = token.rsplit('_').next().unwrap();
+        assert_eq!(secret.len(), SECRET_LENGTH * 2);
+        assert_ne!(api_key.secret_hash, secret);
+        let key_id = api_key.key.key_id.clone();
+        store
+            .create_api_key(api_key, &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        let key = verify_token(&store, &token).await.unwrap();
+        assert_eq!(key.key_id, key_id);
+        assert_eq!(key.role, Role::Operator);
+
+        let wrong_secret = format!(
+            "{}{}_{}",
+            TOKEN_PREFIX,
+            key_id,
+            "0".repeat(SECRET_LENGTH * 2)
+        );
+        for token in [
+            wrong_secret.as_str(),
+            "hlk_unknown_secret",
+            "hlk_nosecret",
+            "other",
+        ] {
+            assert!(matches!(
+                verify_token(&store, token).await,
+                Err(ApiError::Unauthorized(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn bearer_schemes_match_in_any_case() {
+        assert_eq!(bearer_token("Bearer hlk_a_b"), Some("hlk_a_b"));
+        assert_eq!(bearer_token("bearer hlk_a_b"), Some("hlk_a_b"));
+        assert_eq!(bearer_token("  BEARER   hlk_a_b \t"), Some("hlk_a_b"));
+        assert_eq!(bearer_token("Basic dXNlcjpwYXNz"), None);
+        assert_eq!(bearer_token("Bearer"), None);
+        assert_eq!(bearer_token("Bearerhlk_a_b"), None);
+    }
+}
@@ -0,0 +1,311 @@
+//! Errors handlers return instead of building error responses themselves.
+//!
+//! Each `ApiError` maps to an HTTP status and a stable `ErrorCode`;
+//! `function_handler` turns it into an `ErrorResponse` body carrying the
+//! request ID, and logs internal errors with their cause.
+
+use homelab_api::api_key::Role;
+use homelab_api::responses::{ErrorCode, ErrorResponse, FieldError};
+use lambda_http::{Body, Response};
+use serde_json::json;
+use std::fmt;
+
+use crate::store::{
+    ConfigFileInUse, ConfigPathConflict, InvalidCursor, NameConflict, ServerDeleted,
+    TooManyModules, UnknownConfigFile, VersionMismatch,
+};
+
+#[derive(Debug)]
+pub enum ApiError {
+    /// The body is missing, not JSON, or does not match the request type.
+    InvalidBody(String),
+    /// The body is over the size `--serve` accepts.
+    BodyTooLarge,
+    /// A query parameter or header has an invalid value.
+    InvalidParameter {
+        name: &'static str,
+        message: String,
+    },
+    /// Fields of the request body with invalid values, all reported at once.
+    InvalidFields(Vec<FieldError>),
+    /// Missing or invalid credentials.
+    Unauthorized(&'static str),
+    /// The caller's role is below the one the route requires.
+    Forbidden {
+        required: Role,
+        role: Role,
+    },
+    RouteNotFound,
+    ServerNotFound,
+    RevisionNotFound(u64),
+    ConfigFileNotFound,
+    ApiKeyNotFound,
+    NameConflict(NameConflict),
+    /// The server is in the trash; the message says what to do instead.
+    ServerDeleted(&'static str),
+    ServerNotDeleted,
+    ConfigPathConflict(ConfigPathConflict),
+    ConfigFileInUse(ConfigFileInUse),
+    VersionMismatch(VersionMismatch),
+    /// Anything the caller cannot fix. Only `message` is sent; `source` is logged.
+    Internal {
+        message: &'static str,
+        source: anyhow::Error,
+    },
+}
+
+impl ApiError {
+    pub fn invalid_parameter(name: &'static str, message: impl Into<String>) -> Self {
+        ApiError::InvalidParameter {
+            name,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_field(field: impl Into<String>, message: impl Into<String>) -> Self {
+        ApiError::InvalidFields(vec![FieldError {
+            field: field.into(),
+            message: message.into(),
+        }])
+    }
+
+    /// Maps a store error to the client error it stands for; anything else is
+    /// an internal error reported to the caller as `message`.
+    ///
+    /// Meant for `map_err`: `store.get_server(id).await.map_err(ApiError::store("Failed to get server"))?`.
+    pub fn store(message: &'static str) -> impl FnOnce(anyhow::Error) -> ApiError {
+        move |e| {
+            let e = match e.downcast::<NameConflict>() {
+                Ok(conflict) => return ApiError::NameConflict(conflict),
+                Err(e) => e,
+            };
+            let e = match e.downcast::<VersionMismatch>() {
+                Ok(mismatch) => return ApiError::VersionMismatch(mismatch),
+                Err(e) => e,
+            };
+            let e = match e.downcast::<ConfigPathConflict>() {
+                Ok(conflict) => return ApiError::ConfigPathConflict(conflict),
+                Err(e) => e,
+            };
+            let e = match e.downcast::<ConfigFileInUse>() {
+                Ok(in_use) => return ApiError::ConfigFileInUse(in_use),
+                Err(e) => e,
+            };
+            if e.is::<ServerDeleted>() {
+                return ApiError::ServerDeleted("Server is deleted; restore it first");
+            }
+            let e = match e.downcast::<UnknownConfigFile>() {
+                Ok(unknown) => return ApiError::invalid_field("config_id", unknown.to_string()),
+                Err(e) => e,
+            };
+            if let Some(too_many) = e.downcast_ref::<TooManyModules>() {
+                return ApiError::invalid_field("modules", too_many.to_string());
+            }
+            if let Some(invalid) = e.downcast_ref::<InvalidCursor>() {
+                return ApiError::invalid_parameter("cursor", invalid.to_string());
+            }
+            ApiError::Internal { message, source: e }
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::InvalidBody(_) | ApiError::InvalidParameter { .. } => 400,
+            ApiError::BodyTooLarge => 413,
+            ApiError::InvalidFields(_) => 422,
+            ApiError::Unauthorized(_) => 401,
+            ApiError::Forbidden { .. } => 403,
+            ApiError::RouteNotFound
+            | ApiError::ServerNotFound
+            | ApiError::RevisionNotFound(_)
+            | ApiError::ConfigFileNotFound
+            | ApiError::ApiKeyNotFound => 404,
+            ApiError::NameConflict(_)
+            | ApiError::ServerDeleted(_)
+            | ApiError::ServerNotDeleted
+            | ApiError::ConfigPathConflict(_)
+            | ApiError::ConfigFileInUse(_) => 409,
+            ApiError::VersionMismatch(_) => 412,
+            ApiError::Internal { .. } => 500,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::InvalidBody(_) => ErrorCode::InvalidBody,
+            ApiError::BodyTooLarge => ErrorCode::BodyTooLarge,
+            ApiError::InvalidParameter { .. } => ErrorCode::InvalidParameter,
+            ApiError::InvalidFields(_) => ErrorCode::InvalidField,
+            ApiError::Unauthorized(_) => ErrorCode::Unauthorized,
+            ApiError::Forbidden { .. } => ErrorCode::Forbidden,
+            ApiError::RouteNotFound => ErrorCode::RouteNotFound,
+            ApiError::ServerNotFound => ErrorCode::ServerNotFound,
+            ApiError::RevisionNotFound(_) => ErrorCode::RevisionNotFound,
+            ApiError::ConfigFileNotFound => ErrorCode::ConfigFileNotFound,
+            ApiError::ApiKeyNotFound => ErrorCode::ApiKeyNotFound,
+            ApiError::NameConflict(_) => ErrorCode::NameConflict,
+            ApiError::ServerDeleted(_) => ErrorCode::ServerDeleted,
+            ApiError::ServerNotDeleted => ErrorCode::ServerNotDeleted,
+            ApiError::ConfigPathConflict(_) => ErrorCode::ConfigPathConflict,
+            ApiError::ConfigFileInUse(_) => ErrorCode::ConfigFileInUse,
+            ApiError::VersionMismatch(_) => ErrorCode::VersionMismatch,
+            ApiError::Internal { .. } => ErrorCode::Internal,
+        }
+    }
+
+    /// The error response for this error, tagged with the request's ID.
+    pub fn into_response(self, request_id: Option<String>) -> Response<Body> {
+        if let ApiError::Internal { message, source } = &self {
+            tracing::error!(
+                request_id = request_id.as_deref(),
+                "{}: {:#}",
+                message,
+                source
+            );
+        }
+
+        let status = self.status();
+        let mut builder = Response::builder()
+            .status(status)
+            .header("Content-Type", "application/json");
+        if let ApiError::Unauthorized(_) = self {
+            builder = builder.header("WWW-Authenticate", "Bearer");
+        }
+
+        let mut body = ErrorResponse::new(self.code(), self.to_string());
+        body.request_id = request_id;
+        match self {
+            ApiError::InvalidParameter { name, message } => {
+                body.details.push(FieldError {
+                    field: name.to_string(),
+                    message,
+                });
+            }
+            ApiError::InvalidFields(violations) => body.details = violations,
+            ApiError::NameConflict(conflict) => {
+                body.existing_server_id = Some(conflict.existing_server_id)
+            }
+            ApiError::ConfigPathConflict(conflict) => {
+                body.existing_config_id = Some(conflict.existing_config_id)
+            }
+            ApiError::ConfigFileInUse(in_use) => body.server_ids = Some(in_use.server_ids),
+            ApiError::Forbidden { required, .. } => body.required_role = Some(required),
+            ApiError::VersionMismatch(mismatch) => body.current_version = mismatch.current_version,
+            _ => {}
+        }
+
+        builder
+            .body(Body::from(json!(body).to_string()))
+            .expect("error response is valid")
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::InvalidBody(message) => write!(f, "{}", message),
+            ApiError::BodyTooLarge => write!(f, "Request body is too large"),
+            ApiError::InvalidParameter { message, .. } => write!(f, "{}", message),
+            ApiError::InvalidFields(violations) => match violations.as_slice() {
+                [violation] => write!(f, "{}", violation.message),
+                _ => {
+                    let fields: Vec<&str> = violations
+                        .iter()
+                        .map(|violation| violation.field.as_str())
+                        .collect();
+                    write!(
+                        f,
+                        "{} fields are invalid: {}",
+                        violations.len(),
+                        fields.join(", ")
+                    )
+                }
+            },
+            ApiError::Unauthorized(message) => write!(f, "{}", message),
+            ApiError::Forbidden { required, role } => {
+                write!(
+                    f,
+                    "This requires the {} role; the caller has the {} role",
+                    required, role
+                )
+            }
+            ApiError::RouteNotFound => write!(f, "Endpoint not found"),
+            ApiError::ServerNotFound => write!(f, "Server not found"),
+            ApiError::RevisionNotFound(revision) => write!(f, "Revision {} not found", revision),
+            ApiError::ConfigFileNotFound => write!(f, "Config file not found"),
+            ApiError::ApiKeyNotFound => write!(f, "API key not found"),
+            ApiError::NameConflict(conflict) => write!(f, "{}", conflict),
+            ApiError::ServerDeleted(message) => write!(f, "{}", message),
+            ApiError::ServerNotDeleted => write!(f, "Server is not deleted"),
+            ApiError::ConfigPathConflict(conflict) => write!(f, "{}", conflict),
+            ApiError::ConfigFileInUse(in_use) => write!(f, "{}", in_use),
+            ApiError::VersionMismatch(mismatch) => write!(f, "{}", mismatch),
+            ApiError::Internal { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<http::Error> for ApiError {
+    fn from(e: http::Error) -> Self {
+        ApiError::Internal {
+            message: "Failed to build response",
+            source: e.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn response_body(resp: &Response<Body>) -> Value {
+        serde_json::from_slice(resp.body().as_ref()).unwrap()
+    }
+
+    #[test]
+    fn store_errors_map_to_the_client_errors_they_stand_for() {
+        let conflict = ApiError::store("Failed to create server")(
+            NameConflict {
+                server_name: "web-1".to_string(),
+                existing_server_id: "server-1".to_string(),
+            }
+            .into(),
+        );
+        assert_eq!(conflict.status(), 409);
+        assert_eq!(conflict.code(), ErrorCode::NameConflict);
+
+        let mismatch = ApiError::store("Failed to update server")(
+            VersionMismatch {
+                current_version: Some(3),
+            }
+            .into(),
+        );
+        assert_eq!(mismatch.status(), 412);
+        let resp = mismatch.into_response(None);
+        assert_eq!(response_body(&resp)["current_version"], 3);
+
+        let internal = ApiError::store("Failed to get server")(anyhow::anyhow!("disk full"));
+        assert_eq!(internal.status(), 500);
+        assert_eq!(internal.code(), ErrorCode::Internal);
+        // The cause is logged, never sent to the caller
+        assert_eq!(internal.to_string(), "Failed to get server");
+    }
+
+    #[test]
+    fn error_responses_carry_the_code_and_request_id() {
+        let resp = ApiError::invalid_field("server_name", "Server name must not be empty")
+            .into_response(Some("request-1".to_string()));
+        assert_eq!(resp.status(), 422);
+        let body = response_body(&resp);
+        assert_eq!(body["code"], "invalid_field");
+        assert_eq!(body["request_id"], "request-1");
+        assert_eq!(body["error"], "Server name must not be empty");
+        assert_eq!(body["details"][0]["field"], "server_name");
+
+        let resp = ApiError::Unauthorized("Missing API key").into_response(None);
+        assert_eq!(resp.status(), 401);
+        assert_eq!(resp.headers()["WWW-Authenticate"], "Bearer");
+        assert!(response_body(&resp).get("request_id").is_none());
+    }
+}
@@ -0,0 +1,50 @@
+use homelab_api::api_key::CreateApiKeyRequest;
+use homelab_api::audit_event::AuditAction;
+use homelab_api::responses::ApiKeyCreatedResponse;
+use lambda_http::{Body, Request, Response};
+use serde_json::json;
+
+use crate::audit;
+use crate::auth::{new_api_key, Identity};
+use crate::error::ApiError;
+use crate::handlers::parse_body;
+use crate::store::ServerStore;
+use crate::validation::validate_create_api_key;
+
+pub async fn handle_add_api_key(
+    store: &dyn ServerStore,
+    identity: &Identity,
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    let request: CreateApiKeyRequest = parse_body(&event)?;
+
+    validate_create_api_key(&request)?;
+
+    let (api_key, token) =
+        new_api_key(request.name, request.role, identity.actor()).map_err(|source| {
+            ApiError::Internal {
+                message: "Failed to create API key",
+                source,
+            }
+        })?;
+    let key = api_key.key.clone();
+
+    store
+        .create_api_key(api_key, &audit::context(&event, AuditAction::Create))
+        .await
+        .map_err(ApiError::store("Failed to create API key"))?;
+
+    tracing::info!("{} created API key {}", key.created_by, key.key_id);
+    Ok(Response::builder()
+        .status(201)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(ApiKeyCreatedResponse {
+                message: "API key created successfully; store the token now, it is not shown again"
+                    .to_string(),
+                key,
+                token,
+            })
+            .to_string(),
+        ))?)
+}
@@ -0,0 +1,48 @@
+use homelab_api::audit_event::AuditAction;
+use homelab_api::config_file::{ConfigFile, CreateConfigFileRequest};
+use homelab_api::responses::ConfigFileWriteResponse;
+use lambda_http::{Body, Request, Response};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::audit;
+use crate::error::ApiError;
+use crate::handlers::parse_body;
+use crate::store::ServerStore;
+use crate::validation::validate_create_config_file;
+
+pub async fn handle_add_config_file(
+    store: &dyn ServerStore,
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    let request: CreateConfigFileRequest = parse_body(&event)?;
+
+    validate_create_config_file(&request)?;
+
+    let audit = audit::context(&event, AuditAction::Create);
+    let config_file = ConfigFile {
+        config_id: Uuid::new_v4().to_string(),
+        path: request.path,
+        description: request.description,
+        owner: request.owner,
+        created_at: audit.at,
+        updated_at: audit.at,
+    };
+
+    store
+        .create_config_file(config_file.clone(), &audit)
+        .await
+        .map_err(ApiError::store("Failed to add config file"))?;
+
+    tracing::info!("Successfully added config file: {}", config_file.config_id);
+    Ok(Response::builder()
+        .status(201)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(ConfigFileWriteResponse {
+                message: "Config file added successfully".to_string(),
+                config_id: config_file.config_id,
+            })
+            .to_string(),
+        ))?)
+}
@@ -1,86 +1,74 @@
-use aws_sdk_dynamodb::types::AttributeValue;
-use lambda_http::{Body, Error, Request, Response};
+use homelab_api::audit_event::AuditAction;
+use homelab_api::responses::ServerWriteResponse;
+use homelab_api::server_config::{CreateServerRequest, ServerConfig};
+use lambda_http::{Body, Request, Response};
 use serde_json::json;
-use std::collections::HashMap;
 use uuid::Uuid;
-use chrono::Utc;
 
-use crate::models::server_config::CreateServerRequest;
+use crate::audit;
+use crate::error::ApiError;
+use crate::handlers::{etag, parse_body, resolve_config_path};
+use crate::store::ServerStore;
+use crate::validation::validate_create_server;
 
 pub async fn handle_add_server(
-    client: &aws_sdk_dynamodb::Client,
-    table_name: &str,
+    store: &dyn ServerStore,
     event: Request,
-) -> Result<Response<Body>, Error> {
-    let body = match event.body() {
-        Body::Empty => {
-            return Ok(Response::builder()
-                .status(400)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({"error": "Request body is required"}).to_string()))
-                .map_err(Box::new)?);
-        }
-        Body::Text(text) => text,
-        _ => {
-            return Ok(Response::builder()
-                .status(400)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({"error": "Invalid request body"}).to_string()))
-                .map_err(Box::new)?);
-        }
-    };
+) -> Result<Response<Body>, ApiError> {
+    let request: CreateServerRequest = parse_body(&event)?;
 
-    let request: CreateServerRequest = match serde_json::from_str(body) {
-        Ok(req) => req,
-        Err(e) => {
-            tracing::error!("Failed to parse request body: {}", e);
-            return Ok(Response::builder()
-                .status(400)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({"error": "Invalid JSON format"}).to_string()))
-                .map_err(Box::new)?);
-        }
-    };
+    validate_create_server(&request)?;
 
-    let server_id = Uuid::new_v4().to_string();
-    let now = Utc::now();
+    let config_file_path = resolve_config_path(
+        store,
+        request.config_file_path,
+        request.config_id.as_deref(),
+        request.flake.as_ref(),
+    )
+    .await?
+    .ok_or_else(|| {
+        ApiError::invalid_field(
+            "config_file_path",
+            "One of config_file_path, config_id or flake is required",
+        )
+    })?;
 
-    let mut item = HashMap::new();
-    item.insert("server_id".to_string(), AttributeValue::S(server_id.clone()));
-    item.insert("server_name".to_string(), AttributeValue::S(request.server_name));
-    item.insert("config_file_path".to_string(), AttributeValue::S(request.config_file_path));
-    item.insert("created_at".to_string(), AttributeValue::S(now.to_rfc3339()));
-    item.insert("updated_at".to_string(), AttributeValue::S(now.to_rfc3339()));
+    let server_id = Uuid::new_v4().to_string();
+    let audit = audit::context(&event, AuditAction::Create);
 
-    if let Some(desc) = request.description {
-        item.insert("description".to_string(), AttributeValue::S(desc));
-    }
+    let server = ServerConfig {
+        server_id: server_id.clone(),
+        server_name: request.server_name,
+        config_file_path,
+        config_id: request.config_id,
+        flake: request.flake,
+        description: request.description,
+        created_at: audit.at,
+        updated_at: audit.at,
+        created_by: Some(audit.actor.clone()),
+        updated_by: Some(audit.actor.clone()),
+        version: 1,
+        deleted_at: None,
+        labels: request.labels,
+        modules: request.modules,
+    };
 
-    match client
-        .put_item()
-        .table_name(table_name)
-        .set_item(Some(item))
-        .send()
+    store
+        .create_server(server, &audit)
         .await
-    {
-        Ok(_) => {
-            tracing::info!("Successfully added server: {}", server_id);
-            Ok(Response::builder()
-                .status(201)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({
-                    "message": "Server added successfully",
-                    "server_id": server_id
-                }).to_string()))
-                .map_err(Box::new)?)
-        }
-        Err(e) => {
-            tracing::error!("Failed to add server to DynamoDB: {}", e);
-            Ok(Response::builder()
-                .status(500)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({"error": "Failed to add server"}).to_string()))
-                .map_err(Box::new)?)
-        }
-    }
-}
\ No newline at end of file
+        .map_err(ApiError::store("Failed to add server"))?;
+
+    tracing::info!("Successfully added server: {}", server_id);
+    Ok(Response::builder()
+        .status(201)
+        .header("Content-Type", "application/json")
+        .header("ETag", etag(1))
+        .body(Body::from(
+            json!(ServerWriteResponse {
+                message: "Server added successfully".to_string(),
+                server_id,
+                version: 1,
+            })
+            .to_string(),
+        ))?)
+}
@@ -0,0 +1,39 @@
+use homelab_api::audit_event::AuditAction;
+use homelab_api::responses::ApiKeyDeleteResponse;
+use lambda_http::{Body, Request, Response};
+use serde_json::json;
+
+use crate::audit;
+use crate::error::ApiError;
+use crate::store::ServerStore;
+
+/// Revokes a key at once; requests already authenticated with it still finish.
+pub async fn handle_delete_api_key(
+    store: &dyn ServerStore,
+    key_id: &str,
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    store
+        .get_api_key(key_id)
+        .await
+        .map_err(ApiError::store("Failed to check API key"))?
+        .ok_or(ApiError::ApiKeyNotFound)?;
+
+    let audit = audit::context(&event, AuditAction::Delete);
+    store
+        .delete_api_key(key_id, &audit)
+        .await
+        .map_err(ApiError::store("Failed to delete API key"))?;
+
+    tracing::info!("{} deleted API key {}", audit.actor, key_id);
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(ApiKeyDeleteResponse {
+                message: "API key deleted successfully".to_string(),
+                key_id: key_id.to_string(),
+            })
+            .to_string(),
+        ))?)
+}
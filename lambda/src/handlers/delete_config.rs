@@ -1,43 +1,68 @@
-use aws_sdk_dynamodb::types::AttributeValue;
-use lambda_http::{Body, Error, Response};
+use homelab_api::audit_event::AuditAction;
+use homelab_api::responses::DeleteServerResponse;
+use lambda_http::{Body, Request, Response};
 use serde_json::json;
-use std::collections::HashMap;
 
+use crate::audit;
+use crate::error::ApiError;
+use crate::handlers::{expected_version, query_flag};
+use crate::store::ServerStore;
+
+/// Moves a server into the trash, or removes it for good with `?purge=true`.
 pub async fn handle_delete_config(
-    client: &aws_sdk_dynamodb::Client,
-    table_name: &str,
+    store: &dyn ServerStore,
     server_id: &str,
-) -> Result<Response<Body>, Error> {
-    let key = HashMap::from([(
-        "server_id".to_string(),
-        AttributeValue::S(server_id.to_string()),
-    )]);
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    let expected_version = expected_version(&event)?;
+    let purge = query_flag(&event, "purge")?;
 
-    match client
-        .delete_item()
-        .table_name(table_name)
-        .set_key(Some(key))
-        .send()
+    match store
+        .get_server(server_id)
         .await
+        .map_err(ApiError::store("Failed to check server"))?
     {
-        Ok(_) => {
-            tracing::info!("Successfully deleted server: {}", server_id);
-            Ok(Response::builder()
-                .status(200)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({
-                    "message": "Server deleted successfully",
-                    "server_id": server_id
-                }).to_string()))
-                .map_err(Box::new)?)
-        }
-        Err(e) => {
-            tracing::error!("Failed to delete server from DynamoDB: {}", e);
-            Ok(Response::builder()
-                .status(500)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({"error": "Failed to delete server"}).to_string()))
-                .map_err(Box::new)?)
+        Some(server) if server.is_deleted() && !purge => {
+            return Err(ApiError::ServerDeleted(
+                "Server is already deleted; purge it to remove it for good",
+            ));
         }
+        Some(_) => {}
+        None => return Err(ApiError::ServerNotFound),
     }
-}
\ No newline at end of file
+
+    if purge {
+        let audit = audit::context(&event, AuditAction::Purge);
+        store
+            .purge_server(server_id, &audit, expected_version)
+            .await
+    } else {
+        let audit = audit::context(&event, AuditAction::Delete);
+        store
+            .delete_server(server_id, &audit, expected_version)
+            .await
+    }
+    .map_err(ApiError::store("Failed to delete server"))?;
+
+    tracing::info!(
+        "Successfully {} server: {}",
+        if purge { "purged" } else { "deleted" },
+        server_id
+    );
+    let message = if purge {
+        "Server purged successfully"
+    } else {
+        "Server moved to trash"
+    };
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(DeleteServerResponse {
+                message: message.to_string(),
+                server_id: server_id.to_string(),
+                purged: purge,
+            })
+            .to_string(),
+        ))?)
+}
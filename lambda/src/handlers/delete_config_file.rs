@@ -0,0 +1,45 @@
+use homelab_api::audit_event::AuditAction;
+use homelab_api::responses::ConfigFileWriteResponse;
+use lambda_http::{Body, Request, Response};
+use serde_json::json;
+
+use crate::audit;
+use crate::error::ApiError;
+use crate::handlers::find_config_file;
+use crate::store::ServerStore;
+
+/// `config` is either the config file's id or its path. Servers in the trash
+/// do not keep a config file in use; restoring them later keeps only the path.
+pub async fn handle_delete_config_file(
+    store: &dyn ServerStore,
+    config: &str,
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    let config_file = find_config_file(store, config)
+        .await
+        .map_err(ApiError::store("Failed to check config file"))?
+        .ok_or(ApiError::ConfigFileNotFound)?;
+
+    store
+        .delete_config_file(
+            &config_file.config_id,
+            &audit::context(&event, AuditAction::Delete),
+        )
+        .await
+        .map_err(ApiError::store("Failed to delete config file"))?;
+
+    tracing::info!(
+        "Successfully deleted config file: {}",
+        config_file.config_id
+    );
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(ConfigFileWriteResponse {
+                message: "Config file deleted successfully".to_string(),
+                config_id: config_file.config_id,
+            })
+            .to_string(),
+        ))?)
+}
@@ -0,0 +1,73 @@
+use homelab_api::responses::{ConfigServersResponse, ServerMatchesResponse};
+use lambda_http::{Body, Request, Response};
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::handlers::query_flag;
+use crate::store::ServerStore;
+
+pub async fn handle_find_servers_by_name(
+    store: &dyn ServerStore,
+    server_name: &str,
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    let include_deleted = query_flag(&event, "include_deleted")?;
+
+    let mut servers = store
+        .find_servers_by_name(server_name)
+        .await
+        .map_err(ApiError::store("Failed to look up servers"))?;
+    if !include_deleted {
+        servers.retain(|server| !server.is_deleted());
+    }
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(ServerMatchesResponse {
+                count: servers.len(),
+                servers,
+            })
+            .to_string(),
+        ))?)
+}
+
+/// Lists the servers that use a NixOS configuration file; an unused path gives an empty list.
+/// `config` is a config file's id or a bare path.
+pub async fn handle_find_servers_by_config_path(
+    store: &dyn ServerStore,
+    config: &str,
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    let include_deleted = query_flag(&event, "include_deleted")?;
+
+    let config_file_path = match store
+        .get_config_file(config)
+        .await
+        .map_err(ApiError::store("Failed to look up config file"))?
+    {
+        Some(config_file) => config_file.path,
+        None => config.to_string(),
+    };
+
+    let mut servers = store
+        .find_servers_by_config_path(&config_file_path)
+        .await
+        .map_err(ApiError::store("Failed to look up servers"))?;
+    if !include_deleted {
+        servers.retain(|server| !server.is_deleted());
+    }
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(ConfigServersResponse {
+                config_file_path,
+                count: servers.len(),
+                servers,
+            })
+            .to_string(),
+        ))?)
+}
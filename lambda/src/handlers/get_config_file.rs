@@ -0,0 +1,22 @@
+use lambda_http::{Body, Response};
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::handlers::find_config_file;
+use crate::store::ServerStore;
+
+/// `config` is either the config file's id or its path.
+pub async fn handle_get_config_file(
+    store: &dyn ServerStore,
+    config: &str,
+) -> Result<Response<Body>, ApiError> {
+    let config_file = find_config_file(store, config)
+        .await
+        .map_err(ApiError::store("Failed to get config file"))?
+        .ok_or(ApiError::ConfigFileNotFound)?;
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!(config_file).to_string()))?)
+}
@@ -0,0 +1,23 @@
+use lambda_http::{Body, Response};
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::handlers::etag;
+use crate::store::ServerStore;
+
+pub async fn handle_get_server(
+    store: &dyn ServerStore,
+    server_id: &str,
+) -> Result<Response<Body>, ApiError> {
+    let server = store
+        .get_server(server_id)
+        .await
+        .map_err(ApiError::store("Failed to get server"))?
+        .ok_or(ApiError::ServerNotFound)?;
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .header("ETag", etag(server.version))
+        .body(Body::from(json!(server).to_string()))?)
+}
@@ -0,0 +1,24 @@
+use homelab_api::responses::ApiKeyListResponse;
+use lambda_http::{Body, Response};
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::store::ServerStore;
+
+pub async fn handle_list_api_keys(store: &dyn ServerStore) -> Result<Response<Body>, ApiError> {
+    let keys = store
+        .list_api_keys()
+        .await
+        .map_err(ApiError::store("Failed to list API keys"))?;
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(ApiKeyListResponse {
+                count: keys.len(),
+                keys,
+            })
+            .to_string(),
+        ))?)
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use homelab_api::responses::AuditLogResponse;
+use lambda_http::{Body, Request, RequestExt, Response};
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::store::{AuditQuery, ServerStore};
+
+const DEFAULT_PAGE_LIMIT: usize = 100;
+const MAX_PAGE_LIMIT: usize = 1000;
+
+pub async fn handle_list_audit(
+    store: &dyn ServerStore,
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    let params = event.query_string_parameters();
+
+    let limit = match params.first("limit").map(str::parse::<usize>) {
+        None => DEFAULT_PAGE_LIMIT,
+        Some(Ok(limit)) if (1..=MAX_PAGE_LIMIT).contains(&limit) => limit,
+        Some(_) => {
+            return Err(ApiError::invalid_parameter(
+                "limit",
+                format!("limit must be between 1 and {}", MAX_PAGE_LIMIT),
+            ));
+        }
+    };
+
+    let since = match params.first("since").map(DateTime::parse_from_rfc3339) {
+        None => None,
+        Some(Ok(since)) => Some(since.with_timezone(&Utc)),
+        Some(Err(_)) => {
+            return Err(ApiError::invalid_parameter(
+                "since",
+                "since must be an RFC 3339 timestamp, e.g. 2024-01-31T00:00:00Z",
+            ));
+        }
+    };
+
+    let query = AuditQuery {
+        server_id: params.first("server_id").map(str::to_string),
+        since,
+        limit,
+        cursor: params.first("cursor").map(str::to_string),
+    };
+
+    let page = store
+        .list_audit_events(query)
+        .await
+        .map_err(ApiError::store("Failed to list audit events"))?;
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(AuditLogResponse {
+                count: page.events.len(),
+                events: page.events,
+                next_cursor: page.next_cursor,
+            })
+            .to_string(),
+        ))?)
+}
@@ -0,0 +1,24 @@
+use homelab_api::responses::ConfigFileListResponse;
+use lambda_http::{Body, Response};
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::store::ServerStore;
+
+pub async fn handle_list_config_files(store: &dyn ServerStore) -> Result<Response<Body>, ApiError> {
+    let config_files = store
+        .list_config_files()
+        .await
+        .map_err(ApiError::store("Failed to list config files"))?;
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(ConfigFileListResponse {
+                count: config_files.len(),
+                config_files,
+            })
+            .to_string(),
+        ))?)
+}
@@ -1,61 +1,66 @@
-use aws_sdk_dynamodb::types::AttributeValue;
-use lambda_http::{Body, Error, Response};
+use homelab_api::responses::ServerListResponse;
+use homelab_api::server_config::parse_label;
+use lambda_http::{Body, Request, RequestExt, Response};
 use serde_json::json;
 
+use crate::error::ApiError;
+use crate::handlers::query_flag;
+use crate::store::{ListQuery, ServerStore};
+
+const DEFAULT_PAGE_LIMIT: usize = 100;
+const MAX_PAGE_LIMIT: usize = 1000;
+
 pub async fn handle_list_servers(
-    client: &aws_sdk_dynamodb::Client,
-    table_name: &str,
-) -> Result<Response<Body>, Error> {
-    match client.scan().table_name(table_name).send().await {
-        Ok(result) => {
-            let servers = match result.items {
-                Some(items) => {
-                    let server_list: Vec<serde_json::Value> = items
-                        .into_iter()
-                        .map(|item| {
-                            let mut server = serde_json::Map::new();
-                            if let Some(AttributeValue::S(id)) = item.get("server_id") {
-                                server.insert("server_id".to_string(), json!(id));
-                            }
-                            if let Some(AttributeValue::S(name)) = item.get("server_name") {
-                                server.insert("server_name".to_string(), json!(name));
-                            }
-                            if let Some(AttributeValue::S(config_path)) = item.get("config_file_path") {
-                                server.insert("config_file_path".to_string(), json!(config_path));
-                            }
-                            if let Some(AttributeValue::S(desc)) = item.get("description") {
-                                server.insert("description".to_string(), json!(desc));
-                            }
-                            if let Some(AttributeValue::S(created)) = item.get("created_at") {
-                                server.insert("created_at".to_string(), json!(created));
-                            }
-                            if let Some(AttributeValue::S(updated)) = item.get("updated_at") {
-                                server.insert("updated_at".to_string(), json!(updated));
-                            }
-                            json!(server)
-                        })
-                        .collect();
-                    server_list
-                }
-                None => Vec::new(),
-            };
-
-            Ok(Response::builder()
-                .status(200)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({
-                    "servers": servers,
-                    "count": servers.len()
-                }).to_string()))
-                .map_err(Box::new)?)
-        }
-        Err(e) => {
-            tracing::error!("Failed to list servers from DynamoDB: {}", e);
-            Ok(Response::builder()
-                .status(500)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({"error": "Failed to list servers"}).to_string()))
-                .map_err(Box::new)?)
+    store: &dyn ServerStore,
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    let params = event.query_string_parameters();
+
+    let limit = match params.first("limit").map(str::parse::<usize>) {
+        None => DEFAULT_PAGE_LIMIT,
+        Some(Ok(limit)) if (1..=MAX_PAGE_LIMIT).contains(&limit) => limit,
+        Some(_) => {
+            return Err(ApiError::invalid_parameter(
+                "limit",
+                format!("limit must be between 1 and {}", MAX_PAGE_LIMIT),
+            ));
         }
-    }
-}
\ No newline at end of file
+    };
+
+    let include_deleted = query_flag(&event, "include_deleted")?;
+
+    // `label=role=db&label=env=prod` selects servers carrying both labels
+    let labels = params
+        .all("label")
+        .unwrap_or_default()
+        .into_iter()
+        .map(parse_label)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            ApiError::invalid_parameter("label", "label selectors must look like key=value")
+        })?;
+
+    let query = ListQuery {
+        limit,
+        cursor: params.first("cursor").map(str::to_string),
+        include_deleted,
+        labels,
+    };
+
+    let page = store
+        .list_servers(query)
+        .await
+        .map_err(ApiError::store("Failed to list servers"))?;
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(ServerListResponse {
+                count: page.servers.len(),
+                servers: page.servers,
+                next_cursor: page.next_cursor,
+            })
+            .to_string(),
+        ))?)
+}
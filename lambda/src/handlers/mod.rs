@@ -1,4 +1,198 @@
+pub mod add_api_key;
+pub mod add_config_file;
 pub mod add_server;
+pub mod delete_api_key;
 pub mod delete_config;
+pub mod delete_config_file;
+pub mod find_servers;
+pub mod get_config_file;
+pub mod get_server;
+pub mod list_api_keys;
+pub mod list_audit;
+pub mod list_config_files;
 pub mod list_servers;
-pub mod update_config;
\ No newline at end of file
+pub mod restore_server;
+pub mod rollback_server;
+pub mod server_history;
+pub mod update_config;
+pub mod whoami;
+
+use homelab_api::config_file::ConfigFile;
+use homelab_api::flake_ref::FlakeRef;
+use lambda_http::{Body, Request, RequestExt};
+use serde::de::DeserializeOwned;
+
+use crate::error::ApiError;
+use crate::store::{ServerStore, UnknownConfigFile};
+
+/// Formats a server version as an `ETag` header value.
+pub fn etag(version: u64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Parses the JSON request body into `T`.
+///
+/// A body that is not JSON at all is a `400`; JSON that does not fit `T`
+/// (unknown, missing or mistyped fields) is a `422` naming the field where serde does.
+pub fn parse_body<T: DeserializeOwned>(event: &Request) -> Result<T, ApiError> {
+    let text = match event.body() {
+        Body::Empty => {
+            return Err(ApiError::InvalidBody(
+                "Request body is required".to_string(),
+            ))
+        }
+        Body::Text(text) => text,
+        _ => return Err(ApiError::InvalidBody("Invalid request body".to_string())),
+    };
+
+    let value: serde_json::Value = serde_json::from_str(text)
+        .map_err(|e| ApiError::InvalidBody(format!("Invalid JSON format: {}", e)))?;
+    if !value.is_object() {
+        return Err(ApiError::InvalidBody(
+            "Request body must be a JSON object".to_string(),
+        ));
+    }
+    serde_json::from_value(value).map_err(|e| {
+        let message = e.to_string();
+        let field = field_of(&message).unwrap_or("body").to_string();
+        ApiError::invalid_field(field, message)
+    })
+}
+
+/// The field a serde error message is about, for `unknown field `x`` and `missing field `x``.
+fn field_of(message: &str) -> Option<&str> {
+    let rest = message
+        .strip_prefix("unknown field `")
+        .or_else(|| message.strip_prefix("missing field `"))?;
+    rest.split('`').next()
+}
+
+/// Reads the version a client expects from the `If-Match` header.
+///
+/// Accepts the ETag returned by the API (`"3"`, optionally weak `W/"3"`);
+/// `*` or a missing header means any version.
+pub fn expected_version(event: &Request) -> Result<Option<u64>, ApiError> {
+    let Some(value) = event.headers().get("If-Match") else {
+        return Ok(None);
+    };
+
+    let invalid = || ApiError::invalid_parameter("If-Match", "Invalid If-Match header");
+    let value = value.to_str().map_err(|_| invalid())?.trim();
+    if value == "*" {
+        return Ok(None);
+    }
+
+    value
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .parse()
+        .map(Some)
+        .map_err(|_| invalid())
+}
+
+/// Reads a `true`/`false` query string flag; a missing flag is `false`.
+pub fn query_flag(event: &Request, name: &'static str) -> Result<bool, ApiError> {
+    match event.query_string_parameters().first(name) {
+        None | Some("false") => Ok(false),
+        Some("true") => Ok(true),
+        Some(_) => Err(ApiError::invalid_parameter(
+            name,
+            format!("{} must be true or false", name),
+        )),
+    }
+}
+
+/// Looks up a config file by id, falling back to its path.
+pub async fn find_config_file(
+    store: &dyn ServerStore,
+    id_or_path: &str,
+) -> anyhow::Result<Option<ConfigFile>> {
+    match store.get_config_file(id_or_path).await? {
+        Some(config_file) => Ok(Some(config_file)),
+        None => store.find_config_file_by_path(id_or_path).await,
+    }
+}
+
+/// Works out the path a create or update request should store, given its
+/// `config_file_path`, `config_id` and `flake`. A `config_id` supplies the path
+/// of its config file, and a path sent alongside it must agree; a flake stands
+/// in for both and supplies its `uri#attribute`.
+pub async fn resolve_config_path(
+    store: &dyn ServerStore,
+    config_file_path: Option<String>,
+    config_id: Option<&str>,
+    flake: Option<&FlakeRef>,
+) -> Result<Option<String>, ApiError> {
+    if let Some(flake) = flake {
+        if config_file_path.is_some() || config_id.is_some() {
+            return Err(ApiError::invalid_field(
+                "flake",
+                "A flake cannot be combined with config_file_path or config_id",
+            ));
+        }
+        return Ok(Some(flake.to_string()));
+    }
+
+    let Some(config_id) = config_id else {
+        return Ok(config_file_path);
+    };
+
+    let config_file = store
+        .get_config_file(config_id)
+        .await
+        .map_err(ApiError::store("Failed to look up config file"))?
+        .ok_or_else(|| {
+            ApiError::invalid_field(
+                "config_id",
+                UnknownConfigFile {
+                    config_id: config_id.to_string(),
+                }
+                .to_string(),
+            )
+        })?;
+    match config_file_path {
+        Some(path) if path != config_file.path => Err(ApiError::invalid_field(
+            "config_file_path",
+            format!(
+                "config_file_path '{}' does not match config file {} ('{}')",
+                path, config_id, config_file.path
+            ),
+        )),
+        _ => Ok(Some(config_file.path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_if_match(value: &str) -> Request {
+        http::Request::builder()
+            .header("If-Match", value)
+            .body(Body::Empty)
+            .unwrap()
+    }
+
+    #[test]
+    fn expected_version_reads_etags() {
+        let request = http::Request::builder().body(Body::Empty).unwrap();
+        assert_eq!(expected_version(&request).unwrap(), None);
+        assert_eq!(expected_version(&with_if_match("*")).unwrap(), None);
+        assert_eq!(expected_version(&with_if_match("\"3\"")).unwrap(), Some(3));
+        assert_eq!(
+            expected_version(&with_if_match("W/\"3\"")).unwrap(),
+            Some(3)
+        );
+        assert_eq!(expected_version(&with_if_match(&etag(7))).unwrap(), Some(7));
+
+        for invalid in ["three", "\"-1\"", ""] {
+            assert!(matches!(
+                expected_version(&with_if_match(invalid)),
+                Err(ApiError::InvalidParameter {
+                    name: "If-Match",
+                    ..
+                })
+            ));
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use homelab_api::audit_event::AuditAction;
+use homelab_api::responses::ServerWriteResponse;
+use lambda_http::{Body, Request, Response};
+use serde_json::json;
+
+use crate::audit;
+use crate::error::ApiError;
+use crate::handlers::{etag, expected_version};
+use crate::store::ServerStore;
+
+pub async fn handle_restore_server(
+    store: &dyn ServerStore,
+    server_id: &str,
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    let expected_version = expected_version(&event)?;
+
+    match store
+        .get_server(server_id)
+        .await
+        .map_err(ApiError::store("Failed to check server"))?
+    {
+        Some(server) if !server.is_deleted() => return Err(ApiError::ServerNotDeleted),
+        Some(_) => {}
+        None => return Err(ApiError::ServerNotFound),
+    }
+
+    let server = store
+        .restore_server(
+            server_id,
+            &audit::context(&event, AuditAction::Restore),
+            expected_version,
+        )
+        .await
+        .map_err(ApiError::store("Failed to restore server"))?;
+
+    tracing::info!("Successfully restored server: {}", server_id);
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .header("ETag", etag(server.version))
+        .body(Body::from(
+            json!(ServerWriteResponse {
+                message: "Server restored successfully".to_string(),
+                server_id: server_id.to_string(),
+                version: server.version,
+            })
+            .to_string(),
+        ))?)
+}
@@ -0,0 +1,83 @@
+use homelab_api::audit_event::AuditAction;
+use homelab_api::responses::RollbackResponse;
+use homelab_api::server_revision::RollbackRequest;
+use lambda_http::{Body, Request, Response};
+use serde_json::json;
+
+use crate::audit;
+use crate::error::ApiError;
+use crate::handlers::{etag, expected_version, parse_body};
+use crate::store::ServerStore;
+
+pub async fn handle_rollback_server(
+    store: &dyn ServerStore,
+    server_id: &str,
+    event: Request,
+) -> Result<Response<Body>, ApiError> {
+    let request: RollbackRequest = parse_body(&event)?;
+    let expected_version = expected_version(&event)?;
+
+    match store
+        .get_server(server_id)
+        .await
+        .map_err(ApiError::store("Failed to check server"))?
+    {
+        Some(server) if server.is_deleted() => {
+            return Err(ApiError::ServerDeleted(
+                "Server is deleted; restore it first",
+            ));
+        }
+        Some(_) => {}
+        None => return Err(ApiError::ServerNotFound),
+    }
+
+    let target = store
+        .list_revisions(server_id)
+        .await
+        .map_err(ApiError::store("Failed to get server history"))?
+        .into_iter()
+        .find(|revision| revision.revision == request.revision)
+        .ok_or(ApiError::RevisionNotFound(request.revision))?;
+
+    // A config file deleted since then leaves the server with just its old path
+    let mut changes = target.restore_changes();
+    if let Some(config_id) = &changes.config_id {
+        let config_file = store
+            .get_config_file(config_id)
+            .await
+            .map_err(ApiError::store("Failed to look up config file"))?;
+        if config_file.is_none() {
+            changes.config_id = None;
+        }
+    }
+
+    // The rollback is recorded as a new revision, so it can itself be rolled back
+    let server = store
+        .update_server(
+            server_id,
+            changes,
+            &audit::context(&event, AuditAction::Rollback),
+            expected_version,
+        )
+        .await
+        .map_err(ApiError::store("Failed to roll back server"))?;
+
+    tracing::info!(
+        "Rolled back server {} to revision {}",
+        server_id,
+        target.revision
+    );
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .header("ETag", etag(server.version))
+        .body(Body::from(
+            json!(RollbackResponse {
+                message: "Server rolled back successfully".to_string(),
+                server_id: server_id.to_string(),
+                restored_revision: target.revision,
+                version: server.version,
+            })
+            .to_string(),
+        ))?)
+}
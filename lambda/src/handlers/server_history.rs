@@ -0,0 +1,37 @@
+use homelab_api::responses::ServerHistoryResponse;
+use lambda_http::{Body, Response};
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::store::ServerStore;
+
+pub async fn handle_server_history(
+    store: &dyn ServerStore,
+    server_id: &str,
+) -> Result<Response<Body>, ApiError> {
+    let revisions = store
+        .list_revisions(server_id)
+        .await
+        .map_err(ApiError::store("Failed to get server history"))?;
+
+    // Servers created before history was recorded still exist but have no revisions yet
+    if revisions.is_empty() {
+        store
+            .get_server(server_id)
+            .await
+            .map_err(ApiError::store("Failed to get server history"))?
+            .ok_or(ApiError::ServerNotFound)?;
+    }
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!(ServerHistoryResponse {
+                server_id: server_id.to_string(),
+                count: revisions.len(),
+                revisions,
+            })
+            .to_string(),
+        ))?)
+}
@@ -0,0 +1,13 @@
+use lambda_http::{Body, Response};
+use serde_json::json;
+
+use crate::auth::Identity;
+use crate::error::ApiError;
+
+/// Shows who the caller is authenticated as and what role that gives them.
+pub fn handle_whoami(identity: &Identity) -> Result<Response<Body>, ApiError> {
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!(identity.whoami()).to_string()))?)
+}
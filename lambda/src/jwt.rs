@@ -0,0 +1,658 @@
+//! Verification of bearer JWTs, for self-hosted deployments where no API
+//! Gateway authorizer checks tokens before they reach `function_handler`.
+//!
+//! A token must be signed with RS256 or ES256 by a key in the configured JWKS,
+//! be issued by `JWT_ISSUER` for `JWT_AUDIENCE`, and not have expired. Keys are
+//! picked by the token's `kid`; a `kid` that is not in the JWKS reloads it, so
+//! keys can be rotated without restarting the server.
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration as StdDuration;
+use tokio::sync::RwLock;
+
+/// Clock skew tolerated when checking `exp` and `nbf`.
+const LEEWAY_SECONDS: i64 = 60;
+
+/// Least time between two reloads for unknown `kid`s, so tokens naming made-up
+/// keys cannot make every request fetch the JWKS.
+const RELOAD_INTERVAL_SECONDS: i64 = 60;
+
+/// Limits on fetching a JWKS from a URL, so a slow identity provider cannot
+/// hold requests up for the whole Lambda timeout.
+const FETCH_CONNECT_TIMEOUT: StdDuration = StdDuration::from_secs(3);
+const FETCH_TIMEOUT: StdDuration = StdDuration::from_secs(5);
+
+/// Length of each P-256 coordinate.
+const P256_COORDINATE_LENGTH: usize = 32;
+
+/// Checks bearer JWTs against a JWKS and returns their claims.
+#[derive(Debug)]
+pub struct JwtVerifier {
+    source: JwksSource,
+    issuer: String,
+    audience: String,
+    keys: RwLock<KeySet>,
+}
+
+impl JwtVerifier {
+    /// Reads `JWKS_PATH` or `JWKS_URL`, and `JWT_ISSUER` and `JWT_AUDIENCE`,
+    /// which are then required, and loads the JWKS. `None` if neither
+    /// `JWKS_PATH` nor `JWKS_URL` is set.
+    pub async fn from_env() -> Result<Option<Self>> {
+        let source = match (env::var("JWKS_PATH"), env::var("JWKS_URL")) {
+            (Ok(_), Ok(_)) => bail!("Set only one of JWKS_PATH and JWKS_URL"),
+            (Ok(path), Err(_)) => JwksSource::File(PathBuf::from(path)),
+            (Err(_), Ok(url)) => {
+                let client = reqwest::Client::builder()
+                    .connect_timeout(FETCH_CONNECT_TIMEOUT)
+                    .timeout(FETCH_TIMEOUT)
+                    .build()?;
+                JwksSource::Url { url, client }
+            }
+            (Err(_), Err(_)) => return Ok(None),
+        };
+        let issuer = env::var("JWT_ISSUER").context("JWT_ISSUER is required with a JWKS")?;
+        let audience = env::var("JWT_AUDIENCE").context("JWT_AUDIENCE is required with a JWKS")?;
+
+        let verifier = Self::new(source, issuer, audience).await?;
+        let key_count = verifier.keys.read().await.keys.len();
+        tracing::info!(
+            "Verifying JWTs from {} with {} key(s) from {}",
+            verifier.issuer,
+            key_count,
+            verifier.source
+        );
+        Ok(Some(verifier))
+    }
+
+    async fn new(source: JwksSource, issuer: String, audience: String) -> Result<Self> {
+        let keys = source.load().await?;
+        Ok(Self {
+            source,
+            issuer,
+            audience,
+            keys: RwLock::new(keys),
+        })
+    }
+
+    /// The claims of `token`, if its signature, issuer, audience and validity period check out.
+    pub async fn verify(&self, token: &str) -> Result<Map<String, Value>> {
+        let (message, signature) = token.rsplit_once('.').ok_or_else(|| anyhow!("Not a JWT"))?;
+        let (header, payload) = message
+            .split_once('.')
+            .ok_or_else(|| anyhow!("Not a JWT"))?;
+        let header: Header =
+            serde_json::from_slice(&decode(header)?).context("Invalid JWT header")?;
+
+        let key = self.key_for(&header).await?;
+        key.verify(message.as_bytes(), &decode(signature)?)?;
+
+        let claims: Map<String, Value> =
+            serde_json::from_slice(&decode(payload)?).context("Invalid JWT claims")?;
+        self.check_claims(&claims, Utc::now())?;
+        Ok(claims)
+    }
+
+    /// The key that should have signed a token with `header`, reloading the
+    /// JWKS once if it is not there.
+    async fn key_for(&self, header: &Header) -> Result<VerifyingKey> {
+        if !matches!(header.alg.as_str(), "RS256" | "ES256") {
+            bail!("Unsupported JWT algorithm '{}'", header.alg);
+        }
+        let kid = header.kid.as_deref();
+        let missing = || anyhow!("No {} key {:?} in the JWKS", header.alg, kid);
+
+        if let Some(key) = self.keys.read().await.find(kid, &header.alg) {
+            return Ok(key.clone());
+        }
+        {
+            // Counting the attempt before fetching keeps others from fetching too,
+            // whether or not the fetch succeeds
+            let mut keys = self.keys.write().await;
+            if let Some(key) = keys.find(kid, &header.alg) {
+                return Ok(key.clone());
+            }
+            if Utc::now() - keys.loaded_at < Duration::seconds(RELOAD_INTERVAL_SECONDS) {
+                return Err(missing());
+            }
+            keys.loaded_at = Utc::now();
+        }
+
+        tracing::info!("Reloading JWKS from {} for key {:?}", self.source, kid);
+        let reloaded = self.source.load().await?;
+        let key = reloaded.find(kid, &header.alg).cloned();
+        *self.keys.write().await = reloaded;
+        key.ok_or_else(missing)
+    }
+
+    fn check_claims(&self, claims: &Map<String, Value>, now: DateTime<Utc>) -> Result<()> {
+        let leeway = LEEWAY_SECONDS;
+        let now = now.timestamp();
+
+        if claims.get("iss").and_then(Value::as_str) != Some(self.issuer.as_str()) {
+            bail!("JWT is not issued by {}", self.issuer);
+        }
+        let audience_matches = match claims.get("aud") {
+            Some(Value::String(audience)) => *audience == self.audience,
+            Some(Value::Array(audiences)) => audiences
+                .iter()
+                .any(|audience| audience.as_str() == Some(&self.audience)),
+            _ => false,
+        };
+        if !audience_matches {
+            bail!("JWT is not meant for {}", self.audience);
+        }
+        match claims.get("exp").and_then(Value::as_i64) {
+            Some(exp) if now > exp + leeway => bail!("JWT has expired"),
+            Some(_) => {}
+            None => bail!("JWT has no expiry"),
+        }
+        if let Some(nbf) = claims.get("nbf").and_then(Value::as_i64) {
+            if now + leeway < nbf {
+                bail!("JWT is not valid yet");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+/// Where the JWKS comes from; it is read again whenever a token names a key it lacks.
+#[derive(Debug)]
+enum JwksSource {
+    File(PathBuf),
+    Url {
+        url: String,
+        client: reqwest::Client,
+    },
+}
+
+impl JwksSource {
+    async fn load(&self) -> Result<KeySet> {
+        let text = match self {
+            JwksSource::File(path) => tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("Failed to read JWKS from {}", path.display()))?,
+            JwksSource::Url { url, client } => async {
+                client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await
+            }
+            .await
+            .with_context(|| format!("Failed to fetch JWKS from {}", url))?,
+        };
+        let jwks: Jwks =
+            serde_json::from_str(&text).with_context(|| format!("Invalid JWKS from {}", self))?;
+
+        let mut keys = Vec::new();
+        for jwk in jwks.keys {
+            match VerifyingKey::from_jwk(&jwk) {
+                Ok(key) => keys.push((jwk.kid, key)),
+                // Key sets often hold encryption keys or other algorithms too
+                Err(e) => tracing::warn!("Skipping JWKS key {:?}: {:#}", jwk.kid, e),
+            }
+        }
+        Ok(KeySet {
+            keys,
+            loaded_at: Utc::now(),
+        })
+    }
+}
+
+impl std::fmt::Display for JwksSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JwksSource::File(path) => write!(f, "{}", path.display()),
+            JwksSource::Url { url, .. } => write!(f, "{}", url),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+/// A JSON Web Key; only the fields of RSA and EC signing keys are read.
+#[derive(Debug, Deserialize)]
+struct Jwk {
+    kty: String,
+    #[serde(default)]
+    kid: Option<String>,
+    #[serde(default)]
+    alg: Option<String>,
+    #[serde(default, rename = "use")]
+    key_use: Option<String>,
+    #[serde(default)]
+    n: Option<String>,
+    #[serde(default)]
+    e: Option<String>,
+    #[serde(default)]
+    crv: Option<String>,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+}
+
+/// The loaded keys with their `kid`s.
+#[derive(Debug)]
+struct KeySet {
+    keys: Vec<(Option<String>, VerifyingKey)>,
+    /// When the keys were loaded, or a reload was last tried.
+    loaded_at: DateTime<Utc>,
+}
+
+impl KeySet {
+    /// The key with `kid` for `alg`; a token without a `kid` can only use the
+    /// key if there is exactly one for `alg`.
+    fn find(&self, kid: Option<&str>, alg: &str) -> Option<&VerifyingKey> {
+        let mut candidates = self
+            .keys
+            .iter()
+            .filter(|(_, key)| key.alg() == alg)
+            .filter(|(key_id, _)| kid.is_none() || key_id.as_deref() == kid)
+            .map(|(_, key)| key);
+        match (candidates.next(), candidates.next()) {
+            (Some(key), None) => Some(key),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum VerifyingKey {
+    Rs256 {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+    /// An uncompressed P-256 point: `0x04`, then x and y.
+    Es256 {
+        point: Vec<u8>,
+    },
+}
+
+impl VerifyingKey {
+    fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        if jwk
+            .key_use
+            .as_deref()
+            .is_some_and(|key_use| key_use != "sig")
+        {
+            bail!("not a signing key");
+        }
+        let component = |value: &Option<String>, name: &str| -> Result<Vec<u8>> {
+            decode(
+                value
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("missing '{}'", name))?,
+            )
+        };
+
+        match (jwk.kty.as_str(), jwk.alg.as_deref()) {
+            ("RSA", None | Some("RS256")) => Ok(VerifyingKey::Rs256 {
+                n: component(&jwk.n, "n")?,
+                e: component(&jwk.e, "e")?,
+            }),
+            ("EC", None | Some("ES256")) if jwk.crv.as_deref() == Some("P-256") => {
+                let x = component(&jwk.x, "x")?;
+                let y = component(&jwk.y, "y")?;
+                if x.len() != P256_COORDINATE_LENGTH || y.len() != P256_COORDINATE_LENGTH {
+                    bail!("invalid P-256 coordinates");
+                }
+                Ok(VerifyingKey::Es256 {
+                    point: [&[0x04][..], &x, &y].concat(),
+                })
+            }
+            (kty, alg) => bail!(
+                "unsupported {} key for {}",
+                kty,
+                alg.unwrap_or("any algorithm")
+            ),
+        }
+    }
+
+    fn alg(&self) -> &'static str {
+        match self {
+            VerifyingKey::Rs256 { .. } => "RS256",
+            VerifyingKey::Es256 { .. } => "ES256",
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<()> {
+        let verified = match self {
+            VerifyingKey::Rs256 { n, e } => RsaPublicKeyComponents { n, e }.verify(
+                &signature::RSA_PKCS1_2048_8192_SHA256,
+                message,
+                signature,
+            ),
+            VerifyingKey::Es256 { point } => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(message, signature)
+            }
+        };
+        verified.map_err(|_| anyhow!("Invalid JWT signature"))
+    }
+}
+
+fn decode(part: &str) -> Result<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(part).context("Invalid base64url")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{
+        EcdsaKeyPair, KeyPair, RsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING, RSA_PKCS1_SHA256,
+    };
+    use serde_json::json;
+    use uuid::Uuid;
+
+    const ISSUER: &str = "https://auth.example.com";
+    const AUDIENCE: &str = "homelab";
+
+    /// Fixed test keys: RSA ones as PKCS#1 DER, the P-256 one as PKCS#8 DER.
+    const RSA_A: &[u8] = include_bytes!("../tests/data/jwt_rsa_a.der");
+    const RSA_B: &[u8] = include_bytes!("../tests/data/jwt_rsa_b.der");
+    const EC: &[u8] = include_bytes!("../tests/data/jwt_ec.pk8");
+
+    enum TestKey {
+        Rsa(RsaKeyPair),
+        Ec(EcdsaKeyPair),
+    }
+
+    impl TestKey {
+        fn rsa(der: &[u8]) -> Self {
+            TestKey::Rsa(RsaKeyPair::from_der(der).unwrap())
+        }
+
+        fn ec(pkcs8: &[u8]) -> Self {
+            let pair = EcdsaKeyPair::from_pkcs8(
+                &ECDSA_P256_SHA256_FIXED_SIGNING,
+                pkcs8,
+                &SystemRandom::new(),
+            );
+            TestKey::Ec(pair.unwrap())
+        }
+
+        fn jwk(&self, kid: &str) -> Value {
+            match self {
+                TestKey::Rsa(pair) => {
+                    let public = RsaPublicKeyComponents::<Vec<u8>>::from(pair.public());
+                    json!({
+                        "kty": "RSA",
+                        "kid": kid,
+                        "alg": "RS256",
+                        "use": "sig",
+                        "n": encode(&public.n),
+                        "e": encode(&public.e),
+                    })
+                }
+                TestKey::Ec(pair) => {
+                    let point = pair.public_key().as_ref();
+                    json!({
+                        "kty": "EC",
+                        "kid": kid,
+                        "crv": "P-256",
+                        "x": encode(&point[1..33]),
+                        "y": encode(&point[33..]),
+                    })
+                }
+            }
+        }
+
+        /// A token with `header` and `claims`, signed with this key.
+        fn sign(&self, header: Value, claims: &Value) -> String {
+            let header = encode(header.to_string().as_bytes());
+            let message = format!("{}.{}", header, encode(claims.to_string().as_bytes()));
+            let signature = match self {
+                TestKey::Rsa(pair) => {
+                    let mut signature = vec![0; pair.public().modulus_len()];
+                    pair.sign(
+                        &RSA_PKCS1_SHA256,
+                        &SystemRandom::new(),
+                        message.as_bytes(),
+                        &mut signature,
+                    )
+                    .unwrap();
+                    signature
+                }
+                TestKey::Ec(pair) => pair
+                    .sign(&SystemRandom::new(), message.as_bytes())
+                    .unwrap()
+                    .as_ref()
+                    .to_vec(),
+            };
+            format!("{}.{}", message, encode(&signature))
+        }
+    }
+
+    fn encode(bytes: &[u8]) -> String {
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    fn claims() -> Value {
+        let now = Utc::now().timestamp();
+        json!({ "sub": "alice", "iss": ISSUER, "aud": AUDIENCE, "iat": now, "exp": now + 300 })
+    }
+
+    /// A JWKS file that tests can rewrite, and a verifier reading it.
+    struct Fixture {
+        path: PathBuf,
+        verifier: JwtVerifier,
+    }
+
+    impl Fixture {
+        async fn new(keys: &[(&TestKey, &str)]) -> Self {
+            let path = std::env::temp_dir().join(format!("homelab-jwks-{}.json", Uuid::new_v4()));
+            write_jwks(&path, keys);
+            let verifier = JwtVerifier::new(
+                JwksSource::File(path.clone()),
+                ISSUER.to_string(),
+                AUDIENCE.to_string(),
+            )
+            .await
+            .unwrap();
+            Self { path, verifier }
+        }
+
+        /// Pretends the keys were loaded `seconds` earlier than they were.
+        async fn age(&self, seconds: i64) {
+            self.verifier.keys.write().await.loaded_at -= Duration::seconds(seconds);
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn write_jwks(path: &PathBuf, keys: &[(&TestKey, &str)]) {
+        let keys: Vec<Value> = keys.iter().map(|(key, kid)| key.jwk(kid)).collect();
+        std::fs::write(path, json!({ "keys": keys }).to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn valid_tokens_are_accepted() {
+        let rsa = TestKey::rsa(RSA_A);
+        let ec = TestKey::ec(EC);
+        let fixture = Fixture::new(&[(&rsa, "rsa"), (&ec, "ec")]).await;
+
+        let token = rsa.sign(json!({ "alg": "RS256", "kid": "rsa" }), &claims());
+        assert_eq!(
+            fixture.verifier.verify(&token).await.unwrap()["sub"],
+            "alice"
+        );
+
+        let token = ec.sign(
+            json!({ "alg": "ES256", "kid": "ec", "typ": "JWT" }),
+            &claims(),
+        );
+        assert_eq!(
+            fixture.verifier.verify(&token).await.unwrap()["sub"],
+            "alice"
+        );
+
+        // Without a kid, the only key for the algorithm is used
+        let token = ec.sign(json!({ "alg": "ES256" }), &claims());
+        assert!(fixture.verifier.verify(&token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn bad_signatures_are_rejected() {
+        let key = TestKey::rsa(RSA_A);
+        let other = TestKey::rsa(RSA_B);
+        let fixture = Fixture::new(&[(&key, "a")]).await;
+
+        let forged = other.sign(json!({ "alg": "RS256", "kid": "a" }), &claims());
+        assert!(fixture.verifier.verify(&forged).await.is_err());
+
+        // Claims swapped after signing
+        let token = key.sign(json!({ "alg": "RS256", "kid": "a" }), &claims());
+        let (header, rest) = token.split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let mut swapped = claims();
+        swapped["sub"] = "mallory".into();
+        let tampered = format!(
+            "{}.{}.{}",
+            header,
+            encode(swapped.to_string().as_bytes()),
+            signature
+        );
+        assert!(fixture.verifier.verify(&tampered).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn unsupported_algorithms_are_rejected() {
+        let pair = RsaKeyPair::from_der(RSA_A).unwrap();
+        let public = RsaPublicKeyComponents::<Vec<u8>>::from(pair.public());
+        let fixture = Fixture::new(&[(&TestKey::Rsa(pair), "a")]).await;
+        let payload = encode(claims().to_string().as_bytes());
+
+        let header = encode(json!({ "alg": "none", "kid": "a" }).to_string().as_bytes());
+        let unsigned = format!("{}.{}.", header, payload);
+        assert!(fixture.verifier.verify(&unsigned).await.is_err());
+
+        // HMAC keyed with the public key, as if it were a shared secret
+        let header = encode(json!({ "alg": "HS256", "kid": "a" }).to_string().as_bytes());
+        let message = format!("{}.{}", header, payload);
+        let secret = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, &public.n);
+        let signature = ring::hmac::sign(&secret, message.as_bytes());
+        let token = format!("{}.{}", message, encode(signature.as_ref()));
+        let error = fixture.verifier.verify(&token).await.unwrap_err();
+        assert!(error.to_string().contains("Unsupported JWT algorithm"));
+    }
+
+    #[tokio::test]
+    async fn unknown_kids_reload_the_jwks_at_most_once_a_minute() {
+        let a = TestKey::rsa(RSA_A);
+        let b = TestKey::rsa(RSA_B);
+        let ec = TestKey::ec(EC);
+        let fixture = Fixture::new(&[(&a, "a")]).await;
+        let token_b = b.sign(json!({ "alg": "RS256", "kid": "b" }), &claims());
+        let token_ec = ec.sign(json!({ "alg": "ES256", "kid": "ec" }), &claims());
+
+        // The key is rotated in, but the keys were loaded too recently to reload
+        write_jwks(&fixture.path, &[(&a, "a"), (&b, "b")]);
+        fixture.age(RELOAD_INTERVAL_SECONDS - 1).await;
+        assert!(fixture.verifier.verify(&token_b).await.is_err());
+
+        fixture.age(1).await;
+        assert!(fixture.verifier.verify(&token_b).await.is_ok());
+
+        // That reload counts towards the next minute
+        write_jwks(&fixture.path, &[(&a, "a"), (&b, "b"), (&ec, "ec")]);
+        assert!(fixture.verifier.verify(&token_ec).await.is_err());
+
+        // So does a reload that fails
+        std::fs::remove_file(&fixture.path).unwrap();
+        fixture.age(RELOAD_INTERVAL_SECONDS).await;
+        assert!(fixture.verifier.verify(&token_ec).await.is_err());
+        write_jwks(&fixture.path, &[(&a, "a"), (&b, "b"), (&ec, "ec")]);
+        assert!(fixture.verifier.verify(&token_ec).await.is_err());
+        assert!(fixture.verifier.verify(&token_b).await.is_ok());
+
+        fixture.age(RELOAD_INTERVAL_SECONDS).await;
+        assert!(fixture.verifier.verify(&token_ec).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn issuer_and_audience_must_match() {
+        let key = TestKey::rsa(RSA_A);
+        let fixture = Fixture::new(&[(&key, "a")]).await;
+        let header = json!({ "alg": "RS256", "kid": "a" });
+
+        let mut other_issuer = claims();
+        other_issuer["iss"] = "https://evil.example.com".into();
+        assert!(fixture
+            .verifier
+            .verify(&key.sign(header.clone(), &other_issuer))
+            .await
+            .is_err());
+
+        let mut other_audience = claims();
+        other_audience["aud"] = "someone-else".into();
+        assert!(fixture
+            .verifier
+            .verify(&key.sign(header.clone(), &other_audience))
+            .await
+            .is_err());
+
+        let mut audiences = claims();
+        audiences["aud"] = json!(["someone-else", AUDIENCE]);
+        assert!(fixture
+            .verifier
+            .verify(&key.sign(header.clone(), &audiences))
+            .await
+            .is_ok());
+
+        let mut no_audience = claims();
+        no_audience.as_object_mut().unwrap().remove("aud");
+        assert!(fixture
+            .verifier
+            .verify(&key.sign(header, &no_audience))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn expiry_and_not_before_allow_a_minute_of_leeway() {
+        let key = TestKey::rsa(RSA_A);
+        let fixture = Fixture::new(&[(&key, "a")]).await;
+        let now = Utc::now();
+        let at = |name: &str, offset: i64| {
+            let mut claims = claims();
+            claims[name] = (now.timestamp() + offset).into();
+            claims.as_object().unwrap().clone()
+        };
+        let check = |claims: &Map<String, Value>| fixture.verifier.check_claims(claims, now);
+
+        assert!(check(&at("exp", -LEEWAY_SECONDS)).is_ok());
+        assert!(check(&at("exp", -LEEWAY_SECONDS - 1)).is_err());
+        assert!(check(&at("nbf", LEEWAY_SECONDS)).is_ok());
+        assert!(check(&at("nbf", LEEWAY_SECONDS + 1)).is_err());
+
+        let mut no_expiry = at("nbf", 0);
+        no_expiry.remove("exp");
+        assert!(check(&no_expiry).is_err());
+    }
+}
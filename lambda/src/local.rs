@@ -0,0 +1,217 @@
+use chrono::Duration;
+use homelab_api::audit_event::AuditAction;
+use hyper::body::HttpBody;
+use hyper::header::{HeaderMap, HeaderValue, CONTENT_LENGTH};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::Server;
+use lambda_http::{Body, Error, RequestExt};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::audit::{self, RequestId, TRASH_PURGE_ACTOR};
+use crate::auth::Auth;
+use crate::error::ApiError;
+use crate::function_handler;
+use crate::store::ServerStore;
+
+/// Response header carrying the ID this server assigned to the request.
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Largest request body read, far more than any request of the API needs.
+/// Bigger ones are answered with `413` before they reach `function_handler`.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Serves the API over plain HTTP on `addr`, outside of the Lambda runtime.
+///
+/// Every request is translated into a `lambda_http::Request` and routed through
+/// the same `function_handler` the Lambda uses, so both modes behave identically.
+/// Servers that have been in the trash for longer than `trash_retention` are
+/// purged in the background.
+pub async fn serve(
+    addr: SocketAddr,
+    store: Arc<dyn ServerStore>,
+    auth: Auth,
+    trash_retention: Duration,
+) -> Result<(), Error> {
+    tokio::spawn(purge_trash(store.clone(), trash_retention));
+
+    let auth = Arc::new(auth);
+    let make_service = make_service_fn(move |_conn| {
+        let store = store.clone();
+        let auth = auth.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let store = store.clone();
+                let auth = auth.clone();
+                async move { Ok::<_, Infallible>(handle(store.as_ref(), &auth, req).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    tracing::info!("Listening on http://{}", addr);
+
+    server
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Purges expired servers from the trash once at startup and then every hour.
+async fn purge_trash(store: Arc<dyn ServerStore>, trash_retention: Duration) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+        let audit = audit::system(TRASH_PURGE_ACTOR, AuditAction::Purge);
+        match store
+            .purge_expired(audit.at - trash_retention, &audit)
+            .await
+        {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!("Purged {} expired servers from the trash", purged),
+            Err(e) => tracing::error!("Failed to purge the trash: {}", e),
+        }
+    }
+}
+
+async fn handle(
+    store: &dyn ServerStore,
+    auth: &Auth,
+    mut req: hyper::Request<hyper::Body>,
+) -> hyper::Response<hyper::Body> {
+    // Outside of API Gateway nothing assigns request IDs, so make one up; any
+    // `X-Request-Id` the client sent is only recorded as its own ID
+    let request_id = Uuid::new_v4().to_string();
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let mut resp = respond(store, auth, req, Some(request_id.clone())).await;
+    resp.headers_mut().insert(
+        REQUEST_ID_HEADER,
+        HeaderValue::from_str(&request_id).expect("UUID is a valid header value"),
+    );
+    resp
+}
+
+async fn respond(
+    store: &dyn ServerStore,
+    auth: &Auth,
+    req: hyper::Request<hyper::Body>,
+    request_id: Option<String>,
+) -> hyper::Response<hyper::Body> {
+    let resp = match into_lambda_request(req).await {
+        Ok(event) => match function_handler(store, auth, event).await {
+            Ok(resp) => resp,
+            Err(e) => ApiError::Internal {
+                message: "Internal server error",
+                source: anyhow::anyhow!(e),
+            }
+            .into_response(request_id),
+        },
+        Err(e) => e.into_response(request_id),
+    };
+
+    let (parts, body) = resp.into_parts();
+    let body = match body {
+        Body::Empty => hyper::Body::empty(),
+        Body::Text(text) => hyper::Body::from(text),
+        Body::Binary(bytes) => hyper::Body::from(bytes),
+    };
+    hyper::Response::from_parts(parts, body)
+}
+
+async fn into_lambda_request(
+    req: hyper::Request<hyper::Body>,
+) -> Result<lambda_http::Request, ApiError> {
+    let (parts, body) = req.into_parts();
+    let bytes = read_body(&parts.headers, body).await?;
+
+    let body = if bytes.is_empty() {
+        Body::Empty
+    } else {
+        match String::from_utf8(bytes.to_vec()) {
+            Ok(text) => Body::Text(text),
+            Err(e) => Body::Binary(e.into_bytes()),
+        }
+    };
+
+    let mut query: HashMap<String, Vec<String>> = HashMap::new();
+    if let Some(raw) = parts.uri.query() {
+        for (key, value) in form_urlencoded::parse(raw.as_bytes()) {
+            query
+                .entry(key.into_owned())
+                .or_default()
+                .push(value.into_owned());
+        }
+    }
+
+    Ok(lambda_http::Request::from_parts(parts, body).with_query_string_parameters(query))
+}
+
+/// Reads the whole body, giving up as soon as it is known to be over `MAX_BODY_BYTES`.
+async fn read_body(headers: &HeaderMap, mut body: hyper::Body) -> Result<Vec<u8>, ApiError> {
+    let content_length = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if content_length.is_some_and(|length| length > MAX_BODY_BYTES as u64) {
+        return Err(ApiError::BodyTooLarge);
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| {
+            tracing::error!("Failed to read request body: {}", e);
+            ApiError::InvalidBody("Invalid request body".to_string())
+        })?;
+        if bytes.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(ApiError::BodyTooLarge);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::memory::MemoryStore;
+    use homelab_api::api_key::Role;
+
+    async fn post(
+        body: hyper::Body,
+        content_length: Option<usize>,
+    ) -> hyper::Response<hyper::Body> {
+        let mut request = hyper::Request::builder().method("POST").uri("/servers");
+        if let Some(length) = content_length {
+            request = request.header(CONTENT_LENGTH, length);
+        }
+        let auth = Auth::new(true, Role::ReadOnly, Vec::new());
+        respond(
+            &MemoryStore::new(),
+            &auth,
+            request.body(body).unwrap(),
+            None,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_are_rejected_before_the_handler() {
+        let resp = post(hyper::Body::from(vec![b'a'; MAX_BODY_BYTES + 1]), None).await;
+        assert_eq!(resp.status(), 413);
+
+        // A declared length over the limit is rejected without reading the body
+        let resp = post(hyper::Body::empty(), Some(MAX_BODY_BYTES + 1)).await;
+        assert_eq!(resp.status(), 413);
+
+        // Anything within the limit reaches the handler, which wants a key
+        let resp = post(hyper::Body::from(vec![b'a'; MAX_BODY_BYTES]), None).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
@@ -1,12 +1,41 @@
+use chrono::Duration;
 use lambda_http::{run, service_fn, Body, Error, Request, Response};
-use serde_json::json;
+use lambda_runtime::LambdaEvent;
+use percent_encoding::percent_decode_str;
+use serde_json::{json, Value};
 use std::env;
+use std::sync::Arc;
 
+mod audit;
+mod auth;
+mod error;
 mod handlers;
-mod models;
+mod jwt;
+mod local;
+mod store;
+#[cfg(test)]
+mod tests;
+mod validation;
 
-use handlers::{add_server, delete_config, list_servers, update_config};
+use auth::Auth;
+use error::ApiError;
+use handlers::{
+    add_api_key, add_config_file, add_server, delete_api_key, delete_config, delete_config_file,
+    find_servers, get_config_file, get_server, list_api_keys, list_audit, list_config_files,
+    list_servers, restore_server, rollback_server, server_history, update_config, whoami,
+};
+use homelab_api::api_key::Role;
+use homelab_api::audit_event::AuditAction;
+use store::{
+    dynamodb::{DynamoDbStore, DynamoDbTables},
+    json_file::JsonFileStore,
+    memory::MemoryStore,
+    sqlite::SqliteStore,
+    ServerStore, DEFAULT_TRASH_RETENTION_DAYS,
+};
 
+/// Lambda handler name under which `bootstrap` purges the trash instead of serving the API.
+const PURGE_TRASH_HANDLER: &str = "purge-trash";
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
@@ -14,61 +43,270 @@ async fn main() -> Result<(), Error> {
         .json()
         .with_max_level(tracing::Level::INFO)
         .with_target(false)
+        .with_writer(std::io::stderr)
         .without_time()
         .init();
 
-    let config = aws_config::load_from_env().await;
-    let dynamodb_client = aws_sdk_dynamodb::Client::new(&config);
+    // `bootstrap --create-key <name>` creates an admin API key, for calling the API before any key exists
+    if let Some(name) = arg_value("--create-key", "a key name, e.g. admin")? {
+        let store = build_store("sqlite").await?;
+        return create_admin_key(store.as_ref(), name).await;
+    }
+
+    // `bootstrap --backfill-listing` adds servers written by older versions to the DynamoDB listing index
+    if env::args().any(|arg| arg == "--backfill-listing") {
+        let store = dynamodb_store().await?;
+        let updated = store.backfill_listing().await?;
+        tracing::info!("Added {} servers to the listing index", updated);
+        return Ok(());
+    }
+
+    // The same binary, deployed with handler `purge-trash`, purges the trash on a schedule
+    if env::var("_HANDLER").as_deref() == Ok(PURGE_TRASH_HANDLER) {
+        let store = build_store("dynamodb").await?;
+        let trash_retention = trash_retention()?;
+        return lambda_runtime::run(service_fn(|_: LambdaEvent<Value>| {
+            purge_trash(store.as_ref(), trash_retention)
+        }))
+        .await;
+    }
+
+    let auth = Auth::from_env().await?;
+
+    // `bootstrap --serve <addr>` runs a standalone HTTP server instead of the Lambda runtime
+    if let Some(addr) = arg_value("--serve", "an address, e.g. 0.0.0.0:8080")? {
+        let store = build_store("sqlite").await?;
+        return local::serve(addr.parse()?, store, auth, trash_retention()?).await;
+    }
+
+    let store = build_store("dynamodb").await?;
 
     run(service_fn(|event: Request| {
-        function_handler(&dynamodb_client, event)
+        function_handler(store.as_ref(), &auth, event)
     }))
     .await
 }
 
+/// Removes servers that have been in the trash for longer than `trash_retention`.
+async fn purge_trash(store: &dyn ServerStore, trash_retention: Duration) -> Result<Value, Error> {
+    let audit = audit::system(audit::TRASH_PURGE_ACTOR, AuditAction::Purge);
+    let purged = store
+        .purge_expired(audit.at - trash_retention, &audit)
+        .await?;
+    tracing::info!("Purged {} expired servers from the trash", purged);
+    Ok(json!({ "purged": purged }))
+}
+
+/// The value following `flag` on the command line, if the flag is given.
+fn arg_value(flag: &str, expected: &str) -> Result<Option<String>, Error> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} requires {}", flag, expected))?;
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+async fn create_admin_key(store: &dyn ServerStore, name: String) -> Result<(), Error> {
+    let audit = audit::system("bootstrap", AuditAction::Create);
+    let (api_key, token) = auth::new_api_key(name, Role::Admin, audit.actor.clone())?;
+    let key_id = api_key.key.key_id.clone();
+    store.create_api_key(api_key, &audit).await?;
+
+    tracing::info!("Created admin API key {}", key_id);
+    println!("{}", token);
+    Ok(())
+}
+
+/// How long deleted servers stay in the trash, from `TRASH_RETENTION_DAYS`.
+fn trash_retention() -> Result<Duration, Error> {
+    match env::var("TRASH_RETENTION_DAYS") {
+        Ok(days) => {
+            let days: i64 = days
+                .parse()
+                .map_err(|_| "TRASH_RETENTION_DAYS must be a whole number of days")?;
+            Ok(Duration::days(days))
+        }
+        Err(_) => Ok(Duration::days(DEFAULT_TRASH_RETENTION_DAYS)),
+    }
+}
+
+/// Connects to the DynamoDB tables named by the `*TABLE_NAME` variables.
+async fn dynamodb_store() -> Result<DynamoDbStore, Error> {
+    let table_name =
+        |variable: &str, default: &str| env::var(variable).unwrap_or_else(|_| default.to_string());
+    let tables = DynamoDbTables {
+        servers: table_name("TABLE_NAME", "homelab-servers"),
+        names: table_name("NAMES_TABLE_NAME", "homelab-server-names"),
+        revisions: table_name("REVISIONS_TABLE_NAME", "homelab-server-revisions"),
+        audit: table_name("AUDIT_TABLE_NAME", "homelab-audit-events"),
+        config_files: table_name("CONFIG_FILES_TABLE_NAME", "homelab-config-files"),
+        config_paths: table_name("CONFIG_PATHS_TABLE_NAME", "homelab-config-paths"),
+        modules: table_name("MODULES_TABLE_NAME", "homelab-server-modules"),
+        api_keys: table_name("API_KEYS_TABLE_NAME", "homelab-api-keys"),
+    };
+    let config = aws_config::load_from_env().await;
+    let dynamodb_client = aws_sdk_dynamodb::Client::new(&config);
+    Ok(DynamoDbStore::new(dynamodb_client, tables))
+}
+
+/// Selects the storage backend from `STORAGE_BACKEND` (`dynamodb`, `sqlite`, `json` or `memory`),
+/// falling back to `default_backend` when unset.
+async fn build_store(default_backend: &str) -> Result<Arc<dyn ServerStore>, Error> {
+    let backend = env::var("STORAGE_BACKEND").unwrap_or_else(|_| default_backend.to_string());
+
+    match backend.as_str() {
+        "dynamodb" => Ok(Arc::new(dynamodb_store().await?)),
+        "sqlite" => {
+            let path =
+                env::var("STORAGE_PATH").unwrap_or_else(|_| "homelab-servers.db".to_string());
+            tracing::info!("Using SQLite storage backend at {}", path);
+            Ok(Arc::new(SqliteStore::open(path)?))
+        }
+        "json" => {
+            let path =
+                env::var("STORAGE_PATH").unwrap_or_else(|_| "homelab-servers.json".to_string());
+            tracing::info!("Using JSON file storage backend at {}", path);
+            Ok(Arc::new(JsonFileStore::open(path).await?))
+        }
+        "memory" => {
+            tracing::info!("Using in-memory storage backend");
+            Ok(Arc::new(MemoryStore::new()))
+        }
+        other => Err(format!("Unknown STORAGE_BACKEND '{}'", other).into()),
+    }
+}
+
 pub async fn function_handler(
-    dynamodb_client: &aws_sdk_dynamodb::Client,
-    event: Request,
+    store: &dyn ServerStore,
+    auth: &Auth,
+    mut event: Request,
 ) -> Result<Response<Body>, Error> {
-    let table_name = env::var("TABLE_NAME").unwrap_or_else(|_| "homelab-servers".to_string());
-    
-    tracing::info!("Received request: {} {}", event.method(), event.uri().path());
+    tracing::info!(
+        "Received request: {} {}",
+        event.method(),
+        event.uri().path()
+    );
+
+    // Browsers send CORS preflights without credentials, whatever the route
+    if event.method() == http::Method::OPTIONS {
+        return Ok(with_cors(
+            Response::builder().status(204).body(Body::Empty)?,
+        ));
+    }
+
+    let request_id = Some(audit::assign_request_id(&mut event));
+    let identity = match auth.authenticate(store, &event).await {
+        Ok(identity) => identity,
+        Err(e) => return Ok(with_cors(e.into_response(request_id))),
+    };
+    event.extensions_mut().insert(identity.clone());
 
     let method = event.method().clone();
-    let path = event.uri().path().to_string();
-    let path_parts: Vec<&str> = path.trim_start_matches('/').split('/').collect();
-    
+    // Segments are decoded after splitting, so an encoded `/` (as in a config path) stays within its segment
+    let path_parts: Vec<String> = event
+        .uri()
+        .path()
+        .trim_start_matches('/')
+        .split('/')
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        .collect();
+    let path_parts: Vec<&str> = path_parts.iter().map(String::as_str).collect();
+
+    if let Err(e) = identity.require(required_role(&method, &path_parts)) {
+        return Ok(with_cors(e.into_response(request_id)));
+    }
+
     let response = match (method, path_parts.as_slice()) {
-        (http::Method::POST, ["servers"]) => {
-            add_server::handle_add_server(dynamodb_client, &table_name, event).await
+        (http::Method::POST, ["servers"]) => add_server::handle_add_server(store, event).await,
+        (http::Method::GET, ["servers"]) => list_servers::handle_list_servers(store, event).await,
+        (http::Method::GET, ["servers", "by-name", server_name]) => {
+            find_servers::handle_find_servers_by_name(store, server_name, event).await
+        }
+        (http::Method::GET, ["servers", server_id, "history"]) => {
+            server_history::handle_server_history(store, server_id).await
         }
-        (http::Method::GET, ["servers"]) => {
-            list_servers::handle_list_servers(dynamodb_client, &table_name).await
+        (http::Method::POST, ["servers", server_id, "restore"]) => {
+            restore_server::handle_restore_server(store, server_id, event).await
+        }
+        (http::Method::POST, ["servers", server_id, "rollback"]) => {
+            rollback_server::handle_rollback_server(store, server_id, event).await
+        }
+        (http::Method::GET, ["servers", server_id]) => {
+            get_server::handle_get_server(store, server_id).await
         }
         (http::Method::PUT, ["servers", server_id]) => {
-            update_config::handle_update_config(dynamodb_client, &table_name, server_id, event).await
+            update_config::handle_update_config(store, server_id, event).await
         }
         (http::Method::DELETE, ["servers", server_id]) => {
-            delete_config::handle_delete_config(dynamodb_client, &table_name, server_id).await
+            delete_config::handle_delete_config(store, server_id, event).await
+        }
+        (http::Method::POST, ["configs"]) => {
+            add_config_file::handle_add_config_file(store, event).await
+        }
+        (http::Method::GET, ["configs"]) => {
+            list_config_files::handle_list_config_files(store).await
+        }
+        (http::Method::GET, ["configs", config, "servers"]) => {
+            find_servers::handle_find_servers_by_config_path(store, config, event).await
+        }
+        (http::Method::GET, ["configs", config]) => {
+            get_config_file::handle_get_config_file(store, config).await
+        }
+        (http::Method::DELETE, ["configs", config]) => {
+            delete_config_file::handle_delete_config_file(store, config, event).await
         }
-        _ => {
-            Ok(Response::builder()
-                .status(404)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({"error": "Endpoint not found"}).to_string()))
-                .map_err(Box::new)?)
+        (http::Method::GET, ["audit"]) => list_audit::handle_list_audit(store, event).await,
+        (http::Method::POST, ["keys"]) => {
+            add_api_key::handle_add_api_key(store, &identity, event).await
         }
+        (http::Method::GET, ["keys"]) => list_api_keys::handle_list_api_keys(store).await,
+        (http::Method::DELETE, ["keys", key_id]) => {
+            delete_api_key::handle_delete_api_key(store, key_id, event).await
+        }
+        (http::Method::GET, ["whoami"]) => whoami::handle_whoami(&identity),
+        _ => Err(ApiError::RouteNotFound),
     };
 
-    // Add CORS headers
-    match response {
-        Ok(mut resp) => {
-            let headers = resp.headers_mut();
-            headers.insert("Access-Control-Allow-Origin", hyper::header::HeaderValue::from_static("*"));
-            headers.insert("Access-Control-Allow-Methods", hyper::header::HeaderValue::from_static("GET, POST, PUT, DELETE, OPTIONS"));
-            headers.insert("Access-Control-Allow-Headers", hyper::header::HeaderValue::from_static("Content-Type, Authorization"));
-            Ok(resp)
-        }
-        Err(e) => Err(e),
+    Ok(with_cors(
+        response.unwrap_or_else(|e| e.into_response(request_id)),
+    ))
+}
+
+/// The least role allowed to call a route: reading needs `read_only`,
+/// deleting and managing API keys need `admin`, and any other change `operator`.
+fn required_role(method: &http::Method, path_parts: &[&str]) -> Role {
+    match (method, path_parts) {
+        (_, ["keys", ..]) | (&http::Method::DELETE, _) => Role::Admin,
+        (&http::Method::GET, _) => Role::ReadOnly,
+        _ => Role::Operator,
     }
-}
\ No newline at end of file
+}
+
+/// Adds CORS headers.
+fn with_cors(mut resp: Response<Body>) -> Response<Body> {
+    let headers = resp.headers_mut();
+    headers.insert(
+        "Access-Control-Allow-Origin",
+        hyper::header::HeaderValue::from_static("*"),
+    );
+    headers.insert(
+        "Access-Control-Allow-Methods",
+        hyper::header::HeaderValue::from_static("GET, POST, PUT, DELETE, OPTIONS"),
+    );
+    headers.insert(
+        "Access-Control-Allow-Headers",
+        hyper::header::HeaderValue::from_static(
+            "Content-Type, Authorization, If-Match, X-Request-Id",
+        ),
+    );
+    headers.insert(
+        "Access-Control-Expose-Headers",
+        hyper::header::HeaderValue::from_static("ETag, X-Request-Id"),
+    );
+    resp
+}
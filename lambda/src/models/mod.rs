@@ -1 +0,0 @@
-pub mod server_config;
\ No newline at end of file
@@ -0,0 +1,1932 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use aws_sdk_dynamodb::error::SdkError;
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
+use aws_sdk_dynamodb::types::{AttributeValue, Delete, Put, TransactWriteItem, Update};
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::future::try_join_all;
+use homelab_api::api_key::{ApiKey, Role};
+use homelab_api::audit_event::{AuditEvent, AuditRecord, AuditTarget};
+use homelab_api::config_file::ConfigFile;
+use homelab_api::flake_ref::FlakeRef;
+use homelab_api::server_config::{Labels, ServerConfig, UpdateServerRequest};
+use homelab_api::server_revision::ServerRevision;
+use std::collections::{BTreeSet, HashMap};
+
+use super::{
+    apply_changes, check_version, decode_cursor, encode_cursor, mark_deleted, mark_restored,
+    AuditContext, AuditPage, AuditQuery, ConfigFileInUse, ConfigPathConflict,
+    CreationOrderPosition, ListQuery, NameConflict, ServerDeleted, ServerPage, ServerStore,
+    StoredApiKey, UnknownConfigFile, VersionMismatch,
+};
+
+/// Global secondary index on `server_name`, see `terraform/main.tf`.
+const SERVER_NAME_INDEX: &str = "server_name-index";
+
+/// Global secondary index on `config_file_path`, see `terraform/main.tf`.
+const CONFIG_FILE_PATH_INDEX: &str = "config_file_path-index";
+
+/// Global secondary index on `config_id`, see `terraform/main.tf`.
+const CONFIG_ID_INDEX: &str = "config_id-index";
+
+/// Global secondary index on a config file's `path`, see `terraform/main.tf`.
+const CONFIG_PATH_INDEX: &str = "path-index";
+
+/// Global secondary index over every server by `created_key`, see
+/// `terraform/main.tf`. A hash of its ID puts each server in one of
+/// `SERVER_LISTING_SHARDS` partitions, `servers#0` to `servers#7`, so listings
+/// and writes are spread over several partitions instead of one hot one; a
+/// listing queries every shard and merges them in creation order.
+const SERVER_LISTING_INDEX: &str = "listing-index";
+const SERVER_LISTING: &str = "servers";
+const SERVER_LISTING_SHARDS: u32 = 8;
+
+/// Global secondary index over the whole audit log by `event_key`, see
+/// `terraform/main.tf`. Every event has `AUDIT_LOG` as its `log`, so a single
+/// query reads events of all servers in time order.
+const AUDIT_LOG_INDEX: &str = "log-index";
+const AUDIT_LOG: &str = "audit";
+
+/// DynamoDB's limit on the number of items one transaction may write.
+const MAX_TRANSACTION_ITEMS: usize = 100;
+
+/// Servers live in `table_name`, and `SERVER_LISTING_INDEX` orders them by a
+/// `created_key` of their creation time and ID. Each server also claims its name with an item
+/// in `names_table_name` (keyed by `server_name`), written in the same
+/// transaction, so two servers can never end up with the same name.
+///
+/// Every write also puts an immutable item into `revisions_table_name`
+/// (keyed by `server_id` and `revision`) within that transaction.
+///
+/// Audit events go to `audit_table_name`, keyed by `server_id` (which holds
+/// the ID of whichever server, config file or API key changed) and an
+/// `event_key` that sorts chronologically; `AUDIT_LOG_INDEX` orders all of
+/// them by `event_key` alone. Each is put in the same transaction as the
+/// change it describes.
+///
+/// Config files live in `config_files_table_name`, keyed by `config_id`. Each
+/// one keeps a `server_count` of the live servers referencing it, adjusted in
+/// the same transaction as the server write, so a config file can only be
+/// deleted once nothing uses it.
+/// Like server names, each config file claims its `path` with an item in
+/// `config_paths_table_name` (keyed by `path`), written in the same transaction.
+///
+/// Every module a server imports has an item in `modules_table_name` (keyed by
+/// `module` and `server_id`), written in the same transaction as the server, so
+/// servers can be found by the paths they import. Servers in the trash keep
+/// theirs until they are purged.
+///
+/// API keys live in `api_keys_table_name`, keyed by `key_id`, and are
+/// written in a transaction only so their audit event goes with them.
+///
+/// Servers in the trash are only ever removed by the scheduled `purge_expired`,
+/// which releases their name, path and module claims in the same transaction.
+/// The table deliberately has no TTL: expiring server items on their own
+/// would leave those claims behind.
+pub struct DynamoDbStore {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+    names_table_name: String,
+    revisions_table_name: String,
+    audit_table_name: String,
+    config_files_table_name: String,
+    config_paths_table_name: String,
+    modules_table_name: String,
+    api_keys_table_name: String,
+}
+
+/// Names of the tables a `DynamoDbStore` uses, see `terraform/main.tf`.
+pub struct DynamoDbTables {
+    pub servers: String,
+    pub names: String,
+    pub revisions: String,
+    pub audit: String,
+    pub config_files: String,
+    pub config_paths: String,
+    pub modules: String,
+    pub api_keys: String,
+}
+
+impl DynamoDbStore {
+    pub fn new(client: aws_sdk_dynamodb::Client, tables: DynamoDbTables) -> Self {
+        Self {
+            client,
+            table_name: tables.servers,
+            names_table_name: tables.names,
+            revisions_table_name: tables.revisions,
+            audit_table_name: tables.audit,
+            config_files_table_name: tables.config_files,
+            config_paths_table_name: tables.config_paths,
+            modules_table_name: tables.modules,
+            api_keys_table_name: tables.api_keys,
+        }
+    }
+
+    /// Gives servers written before `SERVER_LISTING_INDEX` existed, or before it
+    /// was sharded, the attributes it is keyed by, so that listings include
+    /// them. Returns how many were updated.
+    pub async fn backfill_listing(&self) -> Result<usize> {
+        let mut updated = 0;
+        let mut start_key = None;
+
+        loop {
+            let result = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .filter_expression("attribute_not_exists(#listing) OR #listing = :unsharded")
+                .expression_attribute_names("#listing", "listing")
+                .expression_attribute_values(
+                    ":unsharded",
+                    AttributeValue::S(SERVER_LISTING.to_string()),
+                )
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items.unwrap_or_default() {
+                let server_id = string_attr(&item, "server_id")?;
+                let listing = listing_key(&timestamp_attr(&item, "created_at")?, &server_id);
+                self.client
+                    .update_item()
+                    .table_name(&self.table_name)
+                    .set_key(Some(Self::key(&server_id)))
+                    .update_expression("SET listing = :listing, created_key = :created_key")
+                    .condition_expression("attribute_exists(server_id)")
+                    .expression_attribute_values(":listing", listing["listing"].clone())
+                    .expression_attribute_values(":created_key", listing["created_key"].clone())
+                    .send()
+                    .await?;
+                updated += 1;
+            }
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        Ok(updated)
+    }
+
+    /// Up to `query.limit + 1` servers of one shard of `SERVER_LISTING_INDEX`
+    /// matching `query`, in creation order from just after `after`.
+    async fn list_shard(
+        &self,
+        shard: u32,
+        query: &ListQuery,
+        after: Option<&CreationOrderPosition>,
+    ) -> Result<Vec<ServerConfig>> {
+        let mut key_condition = "#listing = :listing".to_string();
+        let mut names = HashMap::from([("#listing".to_string(), "listing".to_string())]);
+        let mut values = HashMap::from([(":listing".to_string(), listing_partition(shard))]);
+        if let Some(after) = after {
+            key_condition.push_str(" AND #created_key > :after");
+            names.insert("#created_key".to_string(), "created_key".to_string());
+            values.insert(
+                ":after".to_string(),
+                created_key(&after.created_at, &after.server_id),
+            );
+        }
+
+        let mut filters = Vec::new();
+        if !query.include_deleted {
+            filters.push("attribute_not_exists(deleted_at)".to_string());
+        }
+        // Label keys are arbitrary strings, so each one goes through an expression attribute name
+        if !query.labels.is_empty() {
+            names.insert("#labels".to_string(), "labels".to_string());
+        }
+        for (index, (key, value)) in query.labels.iter().enumerate() {
+            filters.push(format!("#labels.#label{index} = :label{index}"));
+            names.insert(format!("#label{index}"), key.clone());
+            values.insert(format!(":label{index}"), AttributeValue::S(value.clone()));
+        }
+        let filter = (!filters.is_empty()).then(|| filters.join(" AND "));
+
+        // Limit applies before the filter, so keep reading until one server past the page turns up
+        let mut servers = Vec::new();
+        let mut start_key = None;
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(SERVER_LISTING_INDEX)
+                .key_condition_expression(&key_condition)
+                .set_filter_expression(filter.clone())
+                .set_expression_attribute_names(Some(names.clone()))
+                .set_expression_attribute_values(Some(values.clone()))
+                .limit((query.limit + 1 - servers.len()) as i32)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items.unwrap_or_default() {
+                servers.push(server_from_item(&item)?);
+            }
+
+            start_key = result.last_evaluated_key;
+            if servers.len() > query.limit || start_key.is_none() {
+                return Ok(servers);
+            }
+        }
+    }
+
+    fn key(server_id: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([(
+            "server_id".to_string(),
+            AttributeValue::S(server_id.to_string()),
+        )])
+    }
+
+    fn config_file_key(config_id: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([(
+            "config_id".to_string(),
+            AttributeValue::S(config_id.to_string()),
+        )])
+    }
+
+    fn api_key_key(key_id: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([("key_id".to_string(), AttributeValue::S(key_id.to_string()))])
+    }
+
+    fn name_key(server_name: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([(
+            "server_name".to_string(),
+            AttributeValue::S(server_name.to_string()),
+        )])
+    }
+
+    fn path_key(path: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([("path".to_string(), AttributeValue::S(path.to_string()))])
+    }
+
+    fn module_key(module: &str, server_id: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            ("module".to_string(), AttributeValue::S(module.to_string())),
+            (
+                "server_id".to_string(),
+                AttributeValue::S(server_id.to_string()),
+            ),
+        ])
+    }
+
+    /// Transaction step claiming `server_name` for `server_id`; fails if it is already claimed.
+    fn claim_name(&self, server_name: &str, server_id: &str) -> Result<TransactWriteItem> {
+        let mut item = Self::name_key(server_name);
+        item.insert(
+            "server_id".to_string(),
+            AttributeValue::S(server_id.to_string()),
+        );
+
+        let put = Put::builder()
+            .table_name(&self.names_table_name)
+            .set_item(Some(item))
+            .condition_expression("attribute_not_exists(server_name)")
+            .build()?;
+        Ok(TransactWriteItem::builder().put(put).build())
+    }
+
+    /// Transaction step overwriting a whole server record, provided it is still
+    /// as `current` was read: at the same version, and in or out of the trash.
+    fn replace_server(
+        &self,
+        server: &ServerConfig,
+        current: &ServerConfig,
+    ) -> Result<TransactWriteItem> {
+        let mut names = HashMap::new();
+        let mut values = HashMap::new();
+        let deleted_condition = if current.is_deleted() {
+            "attribute_exists(deleted_at)"
+        } else {
+            "attribute_not_exists(deleted_at)"
+        };
+        let condition = format!(
+            "attribute_exists(server_id) AND {} AND {}",
+            version_condition(current.version, &mut names, &mut values),
+            deleted_condition
+        );
+
+        let put = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(server_to_item(server.clone())))
+            .condition_expression(condition)
+            .set_expression_attribute_names(Some(names))
+            .set_expression_attribute_values(Some(values))
+            .build()?;
+        Ok(TransactWriteItem::builder().put(put).build())
+    }
+
+    /// Transaction step recording `revision`; revisions are never overwritten.
+    fn put_revision(&self, revision: &ServerRevision) -> Result<TransactWriteItem> {
+        let put = Put::builder()
+            .table_name(&self.revisions_table_name)
+            .set_item(Some(revision_to_item(revision)?))
+            .condition_expression("attribute_not_exists(server_id)")
+            .build()?;
+        Ok(TransactWriteItem::builder().put(put).build())
+    }
+
+    /// Transaction step appending `event` to the audit log. It never fails, so
+    /// it goes last.
+    fn put_audit_event(&self, event: &AuditEvent) -> Result<TransactWriteItem> {
+        let put = Put::builder()
+            .table_name(&self.audit_table_name)
+            .set_item(Some(audit_event_to_item(event)?))
+            .build()?;
+        Ok(TransactWriteItem::builder().put(put).build())
+    }
+
+    /// Transaction step adding `delta` to a config file's `server_count`; fails
+    /// if the config file does not exist.
+    fn count_reference(&self, config_id: &str, delta: i64) -> Result<TransactWriteItem> {
+        let update = Update::builder()
+            .table_name(&self.config_files_table_name)
+            .set_key(Some(Self::config_file_key(config_id)))
+            .update_expression("ADD server_count :delta")
+            .condition_expression("attribute_exists(config_id)")
+            .expression_attribute_values(":delta", AttributeValue::N(delta.to_string()))
+            .build()?;
+        Ok(TransactWriteItem::builder().update(update).build())
+    }
+
+    /// Transaction steps moving a live server's reference from config file
+    /// `from` to `to`. The step taking the new reference, if any, comes first.
+    fn move_reference(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Vec<TransactWriteItem>> {
+        if from == to {
+            return Ok(Vec::new());
+        }
+        let mut steps = Vec::new();
+        if let Some(to) = to {
+            steps.push(self.count_reference(to, 1)?);
+        }
+        if let Some(from) = from {
+            steps.push(self.count_reference(from, -1)?);
+        }
+        Ok(steps)
+    }
+
+    /// Transaction steps moving `server_id`'s module items from the modules in
+    /// `from` to those in `to`. They never fail, so they go after every step
+    /// whose failure the caller maps to an error.
+    fn move_modules(
+        &self,
+        server_id: &str,
+        from: &[String],
+        to: &[String],
+    ) -> Result<Vec<TransactWriteItem>> {
+        let from: BTreeSet<&String> = from.iter().collect();
+        let to: BTreeSet<&String> = to.iter().collect();
+        let mut steps = Vec::new();
+        for module in to.difference(&from) {
+            let put = Put::builder()
+                .table_name(&self.modules_table_name)
+                .set_item(Some(Self::module_key(module, server_id)))
+                .build()?;
+            steps.push(TransactWriteItem::builder().put(put).build());
+        }
+        for module in from.difference(&to) {
+            let delete = Delete::builder()
+                .table_name(&self.modules_table_name)
+                .set_key(Some(Self::module_key(module, server_id)))
+                .build()?;
+            steps.push(TransactWriteItem::builder().delete(delete).build());
+        }
+        Ok(steps)
+    }
+
+    /// Servers, including any in the trash, that import `module`.
+    async fn find_servers_by_module(&self, module: &str) -> Result<Vec<ServerConfig>> {
+        let mut servers = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.modules_table_name)
+                .key_condition_expression("#module = :module")
+                .expression_attribute_names("#module", "module")
+                .expression_attribute_values(":module", AttributeValue::S(module.to_string()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items.unwrap_or_default() {
+                // The server may have been purged since the query
+                if let Some(server) = self.get_server(&string_attr(&item, "server_id")?).await? {
+                    servers.push(server);
+                }
+            }
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        Ok(servers)
+    }
+
+    /// Builds the `ConfigFileInUse` for a config file at `path` that live servers
+    /// still reference, use the path of, or import as a module.
+    async fn config_file_in_use(&self, config_id: &str, path: &str) -> anyhow::Error {
+        match self.config_file_users(config_id, path).await {
+            Ok(users) => ConfigFileInUse {
+                config_id: config_id.to_string(),
+                server_ids: users.into_iter().map(|server| server.server_id).collect(),
+            }
+            .into(),
+            Err(e) => e,
+        }
+    }
+
+    /// Live servers referencing `config_id`, configured by `path`, or importing `path` as a module.
+    async fn config_file_users(&self, config_id: &str, path: &str) -> Result<Vec<ServerConfig>> {
+        let mut users = self.find_servers_by_config_path(path).await?;
+        let mut start_key = None;
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(CONFIG_ID_INDEX)
+                .key_condition_expression("config_id = :config_id")
+                .filter_expression("attribute_not_exists(deleted_at)")
+                .expression_attribute_values(":config_id", AttributeValue::S(config_id.to_string()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items.unwrap_or_default() {
+                users.push(server_from_item(&item)?);
+            }
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        users.retain(|server| !server.is_deleted());
+        users.sort_by(|a, b| (a.created_at, &a.server_id).cmp(&(b.created_at, &b.server_id)));
+        users.dedup_by(|a, b| a.server_id == b.server_id);
+        Ok(users)
+    }
+
+    /// Fails with `UnknownConfigFile` if `config_id` is set but names no config file.
+    async fn ensure_config_file_exists(&self, config_id: Option<&str>) -> Result<()> {
+        match config_id {
+            Some(config_id) if self.get_config_file(config_id).await?.is_none() => {
+                Err(UnknownConfigFile {
+                    config_id: config_id.to_string(),
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Transaction step releasing `server_name`, if `server_id` currently holds it.
+    ///
+    /// Servers created before names were unique may not hold a claim at all, in
+    /// which case there is nothing to release.
+    async fn release_name(
+        &self,
+        server_name: &str,
+        server_id: &str,
+    ) -> Result<Option<TransactWriteItem>> {
+        if self.name_owner(server_name).await?.as_deref() != Some(server_id) {
+            return Ok(None);
+        }
+
+        let delete = Delete::builder()
+            .table_name(&self.names_table_name)
+            .set_key(Some(Self::name_key(server_name)))
+            .condition_expression("server_id = :server_id")
+            .expression_attribute_values(":server_id", AttributeValue::S(server_id.to_string()))
+            .build()?;
+        Ok(Some(TransactWriteItem::builder().delete(delete).build()))
+    }
+
+    async fn name_owner(&self, server_name: &str) -> Result<Option<String>> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.names_table_name)
+            .set_key(Some(Self::name_key(server_name)))
+            .consistent_read(true)
+            .send()
+            .await?;
+
+        result
+            .item
+            .map(|item| string_attr(&item, "server_id"))
+            .transpose()
+    }
+
+    /// Transaction step claiming `path` for `config_id`; fails if it is already claimed.
+    fn claim_path(&self, path: &str, config_id: &str) -> Result<TransactWriteItem> {
+        let mut item = Self::path_key(path);
+        item.insert(
+            "config_id".to_string(),
+            AttributeValue::S(config_id.to_string()),
+        );
+
+        let put = Put::builder()
+            .table_name(&self.config_paths_table_name)
+            .set_item(Some(item))
+            .condition_expression("attribute_not_exists(#path)")
+            .expression_attribute_names("#path", "path")
+            .build()?;
+        Ok(TransactWriteItem::builder().put(put).build())
+    }
+
+    /// Transaction step releasing `config_id`'s claim on `path`, if it holds one;
+    /// config files created before paths were claimed have none.
+    async fn release_path(&self, path: &str, config_id: &str) -> Result<Option<TransactWriteItem>> {
+        if self.path_owner(path).await?.as_deref() != Some(config_id) {
+            return Ok(None);
+        }
+
+        let delete = Delete::builder()
+            .table_name(&self.config_paths_table_name)
+            .set_key(Some(Self::path_key(path)))
+            .condition_expression("config_id = :config_id")
+            .expression_attribute_values(":config_id", AttributeValue::S(config_id.to_string()))
+            .build()?;
+        Ok(Some(TransactWriteItem::builder().delete(delete).build()))
+    }
+
+    async fn path_owner(&self, path: &str) -> Result<Option<String>> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.config_paths_table_name)
+            .set_key(Some(Self::path_key(path)))
+            .consistent_read(true)
+            .send()
+            .await?;
+
+        result
+            .item
+            .map(|item| string_attr(&item, "config_id"))
+            .transpose()
+    }
+
+    /// Fails with `NameConflict` if a server other than `server_id` already uses `server_name`.
+    ///
+    /// This catches servers that predate name claims; the conditional claim in
+    /// the write transaction covers concurrent requests.
+    async fn ensure_name_available(&self, server_name: &str, server_id: &str) -> Result<()> {
+        let existing = self
+            .find_servers_by_name(server_name)
+            .await?
+            .into_iter()
+            .find(|server| server.server_id != server_id && !server.is_deleted());
+
+        match existing {
+            Some(existing) => Err(NameConflict {
+                server_name: server_name.to_string(),
+                existing_server_id: existing.server_id,
+            }
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Converts a cancelled transaction whose name claim (always the first
+    /// step) failed into `NameConflict`.
+    async fn map_claim_error(
+        &self,
+        err: SdkError<TransactWriteItemsError>,
+        server_name: &str,
+    ) -> anyhow::Error {
+        if !step_failed(&err, 0) {
+            return err.into();
+        }
+
+        match self.name_owner(server_name).await {
+            Ok(Some(existing_server_id)) => NameConflict {
+                server_name: server_name.to_string(),
+                existing_server_id,
+            }
+            .into(),
+            Ok(None) => anyhow!(
+                "Name claim for '{}' failed but no owner was found",
+                server_name
+            ),
+            Err(e) => e,
+        }
+    }
+
+    /// Builds the `VersionMismatch` for a write whose version condition failed.
+    async fn version_mismatch(&self, server_id: &str) -> anyhow::Error {
+        match self.get_server(server_id).await {
+            Ok(current) => VersionMismatch {
+                current_version: current.map(|server| server.version),
+            }
+            .into(),
+            Err(e) => e,
+        }
+    }
+}
+
+/// Whether a transaction was cancelled because step `index` failed its condition.
+fn step_failed(err: &SdkError<TransactWriteItemsError>, index: usize) -> bool {
+    match err.as_service_error() {
+        Some(TransactWriteItemsError::TransactionCanceledException(e)) => {
+            e.cancellation_reasons()
+                .get(index)
+                .and_then(|reason| reason.code())
+                == Some("ConditionalCheckFailed")
+        }
+        _ => false,
+    }
+}
+
+/// Condition requiring the stored `version` to equal `:expected_version`.
+/// Items written before versioning have no `version` attribute and count as version 0.
+fn version_condition(
+    expected_version: u64,
+    names: &mut HashMap<String, String>,
+    values: &mut HashMap<String, AttributeValue>,
+) -> &'static str {
+    names.insert("#version".to_string(), "version".to_string());
+    values.insert(
+        ":expected_version".to_string(),
+        AttributeValue::N(expected_version.to_string()),
+    );
+    if expected_version == 0 {
+        "(attribute_not_exists(#version) OR #version = :expected_version)"
+    } else {
+        "#version = :expected_version"
+    }
+}
+
+/// The shard of `SERVER_LISTING_INDEX` a server belongs to, from a hash of its
+/// ID that must never change, or servers would be listed in the wrong shard.
+fn listing_shard(server_id: &str) -> u32 {
+    server_id.bytes().fold(0u32, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(byte as u32)
+    }) % SERVER_LISTING_SHARDS
+}
+
+fn listing_partition(shard: u32) -> AttributeValue {
+    AttributeValue::S(format!("{}#{}", SERVER_LISTING, shard))
+}
+
+/// Sort key of `SERVER_LISTING_INDEX`, ordering servers by creation time and then ID.
+fn created_key(created_at: &DateTime<Utc>, server_id: &str) -> AttributeValue {
+    AttributeValue::S(format!("{}#{}", sortable_timestamp(created_at), server_id))
+}
+
+/// Key of `SERVER_LISTING_INDEX` at the server created at `created_at` with `server_id`.
+fn listing_key(created_at: &DateTime<Utc>, server_id: &str) -> HashMap<String, AttributeValue> {
+    HashMap::from([
+        (
+            "listing".to_string(),
+            listing_partition(listing_shard(server_id)),
+        ),
+        (
+            "created_key".to_string(),
+            created_key(created_at, server_id),
+        ),
+        (
+            "server_id".to_string(),
+            AttributeValue::S(server_id.to_string()),
+        ),
+    ])
+}
+
+fn server_to_item(server: ServerConfig) -> HashMap<String, AttributeValue> {
+    let mut item = listing_key(&server.created_at, &server.server_id);
+    item.insert("server_id".to_string(), AttributeValue::S(server.server_id));
+    item.insert(
+        "server_name".to_string(),
+        AttributeValue::S(server.server_name),
+    );
+    item.insert(
+        "config_file_path".to_string(),
+        AttributeValue::S(server.config_file_path),
+    );
+    item.insert(
+        "created_at".to_string(),
+        AttributeValue::S(server.created_at.to_rfc3339()),
+    );
+    item.insert(
+        "updated_at".to_string(),
+        AttributeValue::S(server.updated_at.to_rfc3339()),
+    );
+    item.insert(
+        "version".to_string(),
+        AttributeValue::N(server.version.to_string()),
+    );
+
+    if let Some(desc) = server.description {
+        item.insert("description".to_string(), AttributeValue::S(desc));
+    }
+
+    if let Some(deleted_at) = server.deleted_at {
+        item.insert(
+            "deleted_at".to_string(),
+            AttributeValue::S(deleted_at.to_rfc3339()),
+        );
+    }
+
+    if let Some(config_id) = server.config_id {
+        item.insert("config_id".to_string(), AttributeValue::S(config_id));
+    }
+
+    if let Some(created_by) = server.created_by {
+        item.insert("created_by".to_string(), AttributeValue::S(created_by));
+    }
+
+    if let Some(updated_by) = server.updated_by {
+        item.insert("updated_by".to_string(), AttributeValue::S(updated_by));
+    }
+
+    if let Some(flake) = server.flake {
+        item.insert("flake".to_string(), flake_to_attr(flake));
+    }
+
+    if !server.labels.is_empty() {
+        let labels = server
+            .labels
+            .into_iter()
+            .map(|(key, value)| (key, AttributeValue::S(value)))
+            .collect();
+        item.insert("labels".to_string(), AttributeValue::M(labels));
+    }
+
+    // A list rather than a string set, since import order matters
+    if !server.modules.is_empty() {
+        let modules = server.modules.into_iter().map(AttributeValue::S).collect();
+        item.insert("modules".to_string(), AttributeValue::L(modules));
+    }
+
+    item
+}
+
+fn flake_to_attr(flake: FlakeRef) -> AttributeValue {
+    let mut item = HashMap::new();
+    item.insert("uri".to_string(), AttributeValue::S(flake.uri));
+    item.insert("attribute".to_string(), AttributeValue::S(flake.attribute));
+    if let Some(rev) = flake.rev {
+        item.insert("rev".to_string(), AttributeValue::S(rev));
+    }
+    AttributeValue::M(item)
+}
+
+fn config_file_to_item(config_file: ConfigFile) -> HashMap<String, AttributeValue> {
+    let mut item = HashMap::new();
+    item.insert(
+        "config_id".to_string(),
+        AttributeValue::S(config_file.config_id),
+    );
+    item.insert("path".to_string(), AttributeValue::S(config_file.path));
+    item.insert(
+        "created_at".to_string(),
+        AttributeValue::S(config_file.created_at.to_rfc3339()),
+    );
+    item.insert(
+        "updated_at".to_string(),
+        AttributeValue::S(config_file.updated_at.to_rfc3339()),
+    );
+    item.insert(
+        "server_count".to_string(),
+        AttributeValue::N("0".to_string()),
+    );
+
+    if let Some(description) = config_file.description {
+        item.insert("description".to_string(), AttributeValue::S(description));
+    }
+
+    if let Some(owner) = config_file.owner {
+        item.insert("owner".to_string(), AttributeValue::S(owner));
+    }
+
+    item
+}
+
+fn config_file_from_item(item: &HashMap<String, AttributeValue>) -> Result<ConfigFile> {
+    Ok(ConfigFile {
+        config_id: string_attr(item, "config_id")?,
+        path: string_attr(item, "path")?,
+        description: string_attr(item, "description").ok(),
+        owner: string_attr(item, "owner").ok(),
+        created_at: timestamp_attr(item, "created_at")?,
+        updated_at: timestamp_attr(item, "updated_at")?,
+    })
+}
+
+fn api_key_to_item(api_key: StoredApiKey) -> HashMap<String, AttributeValue> {
+    HashMap::from([
+        ("key_id".to_string(), AttributeValue::S(api_key.key.key_id)),
+        ("name".to_string(), AttributeValue::S(api_key.key.name)),
+        (
+            "role".to_string(),
+            AttributeValue::S(api_key.key.role.as_str().to_string()),
+        ),
+        (
+            "created_at".to_string(),
+            AttributeValue::S(api_key.key.created_at.to_rfc3339()),
+        ),
+        (
+            "created_by".to_string(),
+            AttributeValue::S(api_key.key.created_by),
+        ),
+        (
+            "secret_hash".to_string(),
+            AttributeValue::S(api_key.secret_hash),
+        ),
+    ])
+}
+
+fn api_key_from_item(item: &HashMap<String, AttributeValue>) -> Result<StoredApiKey> {
+    Ok(StoredApiKey {
+        key: ApiKey {
+            key_id: string_attr(item, "key_id")?,
+            name: string_attr(item, "name")?,
+            role: match item.get("role") {
+                Some(AttributeValue::S(role)) => role.parse()?,
+                _ => Role::default(),
+            },
+            created_at: timestamp_attr(item, "created_at")?,
+            created_by: string_attr(item, "created_by")?,
+        },
+        secret_hash: string_attr(item, "secret_hash")?,
+    })
+}
+
+fn revision_to_item(revision: &ServerRevision) -> Result<HashMap<String, AttributeValue>> {
+    Ok(HashMap::from([
+        (
+            "server_id".to_string(),
+            AttributeValue::S(revision.server_id.clone()),
+        ),
+        (
+            "revision".to_string(),
+            AttributeValue::N(revision.revision.to_string()),
+        ),
+        (
+            "action".to_string(),
+            AttributeValue::S(revision.action.as_str().to_string()),
+        ),
+        (
+            "recorded_at".to_string(),
+            AttributeValue::S(revision.recorded_at.to_rfc3339()),
+        ),
+        // The snapshot is stored as JSON so old revisions survive schema changes unchanged
+        (
+            "server".to_string(),
+            AttributeValue::S(serde_json::to_string(&revision.server)?),
+        ),
+    ]))
+}
+
+fn revision_from_item(item: &HashMap<String, AttributeValue>) -> Result<ServerRevision> {
+    Ok(ServerRevision {
+        server_id: string_attr(item, "server_id")?,
+        revision: match item.get("revision") {
+            Some(AttributeValue::N(revision)) => revision.parse()?,
+            _ => return Err(anyhow!("Item is missing number attribute 'revision'")),
+        },
+        action: string_attr(item, "action")?.parse()?,
+        recorded_at: timestamp_attr(item, "recorded_at")?,
+        server: serde_json::from_str(&string_attr(item, "server")?)?,
+    })
+}
+
+/// Fixed-width timestamps, so that `created_key` and `event_key` sort chronologically.
+fn sortable_timestamp(ts: &DateTime<Utc>) -> String {
+    ts.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+fn audit_event_to_item(event: &AuditEvent) -> Result<HashMap<String, AttributeValue>> {
+    let recorded_at = sortable_timestamp(&event.recorded_at);
+    let mut item = HashMap::from([
+        (
+            "server_id".to_string(),
+            AttributeValue::S(event.target_id.clone()),
+        ),
+        (
+            "event_key".to_string(),
+            AttributeValue::S(format!("{}#{}", recorded_at, event.event_id)),
+        ),
+        ("log".to_string(), AttributeValue::S(AUDIT_LOG.to_string())),
+        (
+            "event_id".to_string(),
+            AttributeValue::S(event.event_id.clone()),
+        ),
+        ("recorded_at".to_string(), AttributeValue::S(recorded_at)),
+        ("actor".to_string(), AttributeValue::S(event.actor.clone())),
+        (
+            "action".to_string(),
+            AttributeValue::S(event.action.as_str().to_string()),
+        ),
+        (
+            "target".to_string(),
+            AttributeValue::S(event.target.as_str().to_string()),
+        ),
+    ]);
+
+    if let Some(request_id) = &event.request_id {
+        item.insert(
+            "request_id".to_string(),
+            AttributeValue::S(request_id.clone()),
+        );
+    }
+    if let Some(client_request_id) = &event.client_request_id {
+        item.insert(
+            "client_request_id".to_string(),
+            AttributeValue::S(client_request_id.clone()),
+        );
+    }
+    if let Some(before) = &event.before {
+        item.insert(
+            "before".to_string(),
+            AttributeValue::S(serde_json::to_string(before)?),
+        );
+    }
+    if let Some(after) = &event.after {
+        item.insert(
+            "after".to_string(),
+            AttributeValue::S(serde_json::to_string(after)?),
+        );
+    }
+
+    Ok(item)
+}
+
+fn audit_event_from_item(item: &HashMap<String, AttributeValue>) -> Result<AuditEvent> {
+    let snapshot = |name: &str| -> Result<Option<AuditRecord>> {
+        match item.get(name) {
+            Some(AttributeValue::S(json)) => Ok(Some(serde_json::from_str(json)?)),
+            _ => Ok(None),
+        }
+    };
+
+    Ok(AuditEvent {
+        event_id: string_attr(item, "event_id")?,
+        recorded_at: timestamp_attr(item, "recorded_at")?,
+        actor: string_attr(item, "actor")?,
+        action: string_attr(item, "action")?.parse()?,
+        // Events from before config files and API keys were audited have no target
+        target: match string_attr(item, "target") {
+            Ok(target) => target.parse()?,
+            Err(_) => AuditTarget::Server,
+        },
+        target_id: string_attr(item, "server_id")?,
+        request_id: string_attr(item, "request_id").ok(),
+        client_request_id: string_attr(item, "client_request_id").ok(),
+        before: snapshot("before")?,
+        after: snapshot("after")?,
+    })
+}
+
+fn string_attr(item: &HashMap<String, AttributeValue>, name: &str) -> Result<String> {
+    match item.get(name) {
+        Some(AttributeValue::S(value)) => Ok(value.clone()),
+        _ => Err(anyhow!("Item is missing string attribute '{}'", name)),
+    }
+}
+
+fn timestamp_attr(item: &HashMap<String, AttributeValue>, name: &str) -> Result<DateTime<Utc>> {
+    let value = string_attr(item, name)?;
+    Ok(DateTime::parse_from_rfc3339(&value)?.with_timezone(&Utc))
+}
+
+fn server_from_item(item: &HashMap<String, AttributeValue>) -> Result<ServerConfig> {
+    Ok(ServerConfig {
+        server_id: string_attr(item, "server_id")?,
+        server_name: string_attr(item, "server_name")?,
+        config_file_path: string_attr(item, "config_file_path")?,
+        description: string_attr(item, "description").ok(),
+        created_at: timestamp_attr(item, "created_at")?,
+        updated_at: timestamp_attr(item, "updated_at")?,
+        version: match item.get("version") {
+            Some(AttributeValue::N(version)) => version.parse()?,
+            _ => 0,
+        },
+        deleted_at: item
+            .contains_key("deleted_at")
+            .then(|| timestamp_attr(item, "deleted_at"))
+            .transpose()?,
+        labels: match item.get("labels") {
+            Some(AttributeValue::M(labels)) => labels
+                .iter()
+                .map(|(key, value)| match value {
+                    AttributeValue::S(value) => Ok((key.clone(), value.clone())),
+                    _ => Err(anyhow!("Label '{}' is not a string", key)),
+                })
+                .collect::<Result<_>>()?,
+            _ => Labels::new(),
+        },
+        config_id: string_attr(item, "config_id").ok(),
+        created_by: string_attr(item, "created_by").ok(),
+        updated_by: string_attr(item, "updated_by").ok(),
+        flake: match item.get("flake") {
+            Some(AttributeValue::M(flake)) => Some(FlakeRef {
+                uri: string_attr(flake, "uri")?,
+                attribute: string_attr(flake, "attribute")?,
+                rev: string_attr(flake, "rev").ok(),
+            }),
+            _ => None,
+        },
+        modules: match item.get("modules") {
+            Some(AttributeValue::L(modules)) => modules
+                .iter()
+                .map(|module| match module {
+                    AttributeValue::S(module) => Ok(module.clone()),
+                    _ => Err(anyhow!("Module entry is not a string")),
+                })
+                .collect::<Result<_>>()?,
+            _ => Vec::new(),
+        },
+    })
+}
+
+#[async_trait]
+impl ServerStore for DynamoDbStore {
+    async fn create_server(&self, server: ServerConfig, audit: &AuditContext) -> Result<()> {
+        self.ensure_name_available(&server.server_name, &server.server_id)
+            .await?;
+        self.ensure_config_file_exists(server.config_id.as_deref())
+            .await?;
+
+        let server_name = server.server_name.clone();
+        let config_id = server.config_id.clone();
+        let claim = self.claim_name(&server.server_name, &server.server_id)?;
+        let revision = self.put_revision(&ServerRevision::created(&server))?;
+        let module_moves = self.move_modules(&server.server_id, &[], &server.modules)?;
+        let audit_event = self.put_audit_event(&audit.server_event(None, Some(&server)))?;
+        let put = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(server_to_item(server)))
+            .build()?;
+
+        // The reference count follows the claim, server and revision; module items and the audit event come last
+        let mut transaction = self
+            .client
+            .transact_write_items()
+            .transact_items(claim)
+            .transact_items(TransactWriteItem::builder().put(put).build())
+            .transact_items(revision);
+        for step in self
+            .move_reference(None, config_id.as_deref())?
+            .into_iter()
+            .chain(module_moves)
+            .chain([audit_event])
+        {
+            transaction = transaction.transact_items(step);
+        }
+
+        match transaction.send().await {
+            Ok(_) => Ok(()),
+            Err(e) if step_failed(&e, 3) => Err(UnknownConfigFile {
+                config_id: config_id.unwrap_or_default(),
+            }
+            .into()),
+            Err(e) => Err(self.map_claim_error(e, &server_name).await),
+        }
+    }
+
+    async fn get_server(&self, server_id: &str) -> Result<Option<ServerConfig>> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::key(server_id)))
+            .consistent_read(true)
+            .send()
+            .await?;
+
+        result.item.as_ref().map(server_from_item).transpose()
+    }
+
+    async fn find_servers_by_name(&self, server_name: &str) -> Result<Vec<ServerConfig>> {
+        let mut servers = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(SERVER_NAME_INDEX)
+                .key_condition_expression("#server_name = :server_name")
+                .expression_attribute_names("#server_name", "server_name")
+                .expression_attribute_values(
+                    ":server_name",
+                    AttributeValue::S(server_name.to_string()),
+                )
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items.unwrap_or_default() {
+                servers.push(server_from_item(&item)?);
+            }
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        Ok(servers)
+    }
+
+    async fn find_servers_by_config_path(
+        &self,
+        config_file_path: &str,
+    ) -> Result<Vec<ServerConfig>> {
+        let mut servers = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(CONFIG_FILE_PATH_INDEX)
+                .key_condition_expression("#config_file_path = :config_file_path")
+                .expression_attribute_names("#config_file_path", "config_file_path")
+                .expression_attribute_values(
+                    ":config_file_path",
+                    AttributeValue::S(config_file_path.to_string()),
+                )
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items.unwrap_or_default() {
+                servers.push(server_from_item(&item)?);
+            }
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        servers.extend(self.find_servers_by_module(config_file_path).await?);
+        servers.sort_by(|a, b| (a.created_at, &a.server_id).cmp(&(b.created_at, &b.server_id)));
+        servers.dedup_by(|a, b| a.server_id == b.server_id);
+        Ok(servers)
+    }
+
+    async fn list_servers(&self, query: ListQuery) -> Result<ServerPage> {
+        let after = query
+            .cursor
+            .as_deref()
+            .map(decode_cursor::<CreationOrderPosition>)
+            .transpose()?;
+
+        // The first `limit + 1` servers of all shards together are among the
+        // first `limit + 1` of each
+        let shards =
+            (0..SERVER_LISTING_SHARDS).map(|shard| self.list_shard(shard, &query, after.as_ref()));
+        let mut servers: Vec<ServerConfig> = try_join_all(shards).await?.concat();
+        servers.sort_by(|a, b| (a.created_at, &a.server_id).cmp(&(b.created_at, &b.server_id)));
+
+        let next_cursor = if servers.len() > query.limit {
+            servers.truncate(query.limit);
+            servers
+                .last()
+                .map(|last| encode_cursor(&CreationOrderPosition::of(last)))
+                .transpose()?
+        } else {
+            None
+        };
+
+        Ok(ServerPage {
+            servers,
+            next_cursor,
+        })
+    }
+
+    async fn update_server(
+        &self,
+        server_id: &str,
+        changes: UpdateServerRequest,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<ServerConfig> {
+        loop {
+            let current = self.get_server(server_id).await?;
+            check_version(current.as_ref(), expected_version)?;
+            let current = match current {
+                Some(server) if server.is_deleted() => {
+                    return Err(ServerDeleted {
+                        server_id: server_id.to_string(),
+                    }
+                    .into());
+                }
+                Some(server) => server,
+                None => return Err(anyhow!("Server {} does not exist", server_id)),
+            };
+
+            let mut updated = current.clone();
+            apply_changes(&mut updated, changes.clone(), audit.at, &audit.actor)?;
+            let renamed = updated.server_name != current.server_name;
+            if renamed {
+                self.ensure_name_available(&updated.server_name, server_id)
+                    .await?;
+            }
+            let reference_moves =
+                self.move_reference(current.config_id.as_deref(), updated.config_id.as_deref())?;
+            if !reference_moves.is_empty() {
+                self.ensure_config_file_exists(updated.config_id.as_deref())
+                    .await?;
+            }
+            let module_moves = self.move_modules(server_id, &current.modules, &updated.modules)?;
+            // The claim, replace, revision, two reference moves, release and audit event leave room for the rest
+            if module_moves.len() > MAX_TRANSACTION_ITEMS - 7 {
+                return Err(anyhow!(
+                    "Server {} would change too many modules at once",
+                    server_id
+                ));
+            }
+
+            // A rename moves the name claim, which must stay the first step
+            let mut transaction = self.client.transact_write_items();
+            if renamed {
+                transaction =
+                    transaction.transact_items(self.claim_name(&updated.server_name, server_id)?);
+            }
+            let replace_step = usize::from(renamed);
+            let reference_step = replace_step + 2;
+            transaction = transaction
+                .transact_items(self.replace_server(&updated, &current)?)
+                .transact_items(self.put_revision(&ServerRevision::updated(&updated))?);
+            for step in reference_moves {
+                transaction = transaction.transact_items(step);
+            }
+            if renamed {
+                if let Some(release) = self.release_name(&current.server_name, server_id).await? {
+                    transaction = transaction.transact_items(release);
+                }
+            }
+            for step in module_moves {
+                transaction = transaction.transact_items(step);
+            }
+            transaction = transaction.transact_items(
+                self.put_audit_event(&audit.server_event(Some(&current), Some(&updated)))?,
+            );
+
+            match transaction.send().await {
+                Ok(_) => return Ok(updated),
+                Err(e) if step_failed(&e, replace_step) => {
+                    if expected_version.is_some() {
+                        return Err(self.version_mismatch(server_id).await);
+                    }
+                    // Someone else wrote the server in between; apply the changes on top of theirs
+                    tracing::info!("Server {} changed during update, retrying", server_id);
+                }
+                Err(e) if updated.config_id.is_some() && step_failed(&e, reference_step) => {
+                    return Err(UnknownConfigFile {
+                        config_id: updated.config_id.unwrap_or_default(),
+                    }
+                    .into());
+                }
+                Err(e) if renamed => {
+                    return Err(self.map_claim_error(e, &updated.server_name).await)
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    async fn delete_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<()> {
+        loop {
+            let current = self.get_server(server_id).await?;
+            check_version(current.as_ref(), expected_version)?;
+            let Some(current) = current.filter(|server| !server.is_deleted()) else {
+                return Ok(());
+            };
+
+            let mut deleted = current.clone();
+            mark_deleted(&mut deleted, audit.at, &audit.actor);
+
+            let mut transaction = self
+                .client
+                .transact_write_items()
+                .transact_items(self.replace_server(&deleted, &current)?)
+                .transact_items(self.put_revision(&ServerRevision::deleted(&deleted))?);
+            for step in self.move_reference(current.config_id.as_deref(), None)? {
+                transaction = transaction.transact_items(step);
+            }
+            if let Some(release) = self.release_name(&current.server_name, server_id).await? {
+                transaction = transaction.transact_items(release);
+            }
+            transaction = transaction.transact_items(
+                self.put_audit_event(&audit.server_event(Some(&current), Some(&deleted)))?,
+            );
+
+            match transaction.send().await {
+                Ok(_) => return Ok(()),
+                Err(e) if step_failed(&e, 0) => {
+                    if expected_version.is_some() {
+                        return Err(self.version_mismatch(server_id).await);
+                    }
+                    tracing::info!("Server {} changed during delete, retrying", server_id);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    async fn restore_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<ServerConfig> {
+        loop {
+            let current = self.get_server(server_id).await?;
+            check_version(current.as_ref(), expected_version)?;
+            let current = match current {
+                Some(server) if server.is_deleted() => server,
+                Some(_) => return Err(anyhow!("Server {} is not deleted", server_id)),
+                None => return Err(anyhow!("Server {} does not exist", server_id)),
+            };
+            self.ensure_name_available(&current.server_name, server_id)
+                .await?;
+
+            let mut restored = current.clone();
+            mark_restored(&mut restored, audit.at, &audit.actor);
+            // The config file may have been deleted while the server was in the trash
+            match self
+                .ensure_config_file_exists(restored.config_id.as_deref())
+                .await
+            {
+                Ok(()) => {}
+                Err(e) if e.is::<UnknownConfigFile>() => restored.config_id = None,
+                Err(e) => return Err(e),
+            }
+
+            // The server takes its name back, so the claim is the first step
+            let mut transaction = self
+                .client
+                .transact_write_items()
+                .transact_items(self.claim_name(&restored.server_name, server_id)?)
+                .transact_items(self.replace_server(&restored, &current)?)
+                .transact_items(self.put_revision(&ServerRevision::restored(&restored))?);
+            for step in self.move_reference(None, restored.config_id.as_deref())? {
+                transaction = transaction.transact_items(step);
+            }
+            transaction = transaction.transact_items(
+                self.put_audit_event(&audit.server_event(Some(&current), Some(&restored)))?,
+            );
+
+            match transaction.send().await {
+                Ok(_) => return Ok(restored),
+                Err(e) if step_failed(&e, 1) => {
+                    if expected_version.is_some() {
+                        return Err(self.version_mismatch(server_id).await);
+                    }
+                    tracing::info!("Server {} changed during restore, retrying", server_id);
+                }
+                Err(e) if step_failed(&e, 3) => {
+                    tracing::info!(
+                        "Config file of server {} was deleted during restore, retrying",
+                        server_id
+                    );
+                }
+                Err(e) => return Err(self.map_claim_error(e, &restored.server_name).await),
+            }
+        }
+    }
+
+    async fn purge_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<()> {
+        loop {
+            let current = self.get_server(server_id).await?;
+            check_version(current.as_ref(), expected_version)?;
+            let Some(current) = current else {
+                return Ok(());
+            };
+
+            let mut names = HashMap::new();
+            let mut values = HashMap::new();
+            let condition = version_condition(current.version, &mut names, &mut values);
+            let delete = Delete::builder()
+                .table_name(&self.table_name)
+                .set_key(Some(Self::key(server_id)))
+                .condition_expression(condition)
+                .set_expression_attribute_names(Some(names))
+                .set_expression_attribute_values(Some(values))
+                .build()?;
+
+            let mut transaction = self
+                .client
+                .transact_write_items()
+                .transact_items(TransactWriteItem::builder().delete(delete).build())
+                .transact_items(self.put_revision(&ServerRevision::purged(&current, audit.at))?);
+            if !current.is_deleted() {
+                for step in self.move_reference(current.config_id.as_deref(), None)? {
+                    transaction = transaction.transact_items(step);
+                }
+            }
+            if let Some(release) = self.release_name(&current.server_name, server_id).await? {
+                transaction = transaction.transact_items(release);
+            }
+            for step in self.move_modules(server_id, &current.modules, &[])? {
+                transaction = transaction.transact_items(step);
+            }
+            transaction = transaction
+                .transact_items(self.put_audit_event(&audit.server_event(Some(&current), None))?);
+
+            match transaction.send().await {
+                Ok(_) => return Ok(()),
+                Err(e) if step_failed(&e, 0) => {
+                    if expected_version.is_some() {
+                        return Err(self.version_mismatch(server_id).await);
+                    }
+                    tracing::info!("Server {} changed during purge, retrying", server_id);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    async fn purge_expired(
+        &self,
+        deleted_before: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> Result<usize> {
+        let mut expired = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .filter_expression("attribute_exists(deleted_at)")
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items.unwrap_or_default() {
+                let server = server_from_item(&item)?;
+                if server
+                    .deleted_at
+                    .is_some_and(|deleted_at| deleted_at < deleted_before)
+                {
+                    expired.push(server.server_id);
+                }
+            }
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        for server_id in &expired {
+            self.purge_server(server_id, audit, None).await?;
+        }
+        Ok(expired.len())
+    }
+
+    async fn list_revisions(&self, server_id: &str) -> Result<Vec<ServerRevision>> {
+        let mut revisions = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.revisions_table_name)
+                .key_condition_expression("server_id = :server_id")
+                .expression_attribute_values(":server_id", AttributeValue::S(server_id.to_string()))
+                .consistent_read(true)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items.unwrap_or_default() {
+                revisions.push(revision_from_item(&item)?);
+            }
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        Ok(revisions)
+    }
+
+    async fn list_audit_events(&self, query: AuditQuery) -> Result<AuditPage> {
+        let since_key = query
+            .since
+            .as_ref()
+            .map(sortable_timestamp)
+            .unwrap_or_default();
+        // The cursor is the server_id and event_key of DynamoDB's LastEvaluatedKey
+        let start_key = query
+            .cursor
+            .as_deref()
+            .map(decode_cursor::<(String, String)>)
+            .transpose()?
+            .map(|(server_id, event_key)| {
+                let mut key = HashMap::from([
+                    ("server_id".to_string(), AttributeValue::S(server_id)),
+                    ("event_key".to_string(), AttributeValue::S(event_key)),
+                ]);
+                if query.server_id.is_none() {
+                    key.insert("log".to_string(), AttributeValue::S(AUDIT_LOG.to_string()));
+                }
+                key
+            });
+
+        let request = self
+            .client
+            .query()
+            .table_name(&self.audit_table_name)
+            .expression_attribute_values(":since", AttributeValue::S(since_key))
+            .limit(query.limit as i32)
+            .set_exclusive_start_key(start_key);
+        let request = match &query.server_id {
+            Some(server_id) => request
+                .key_condition_expression("server_id = :server_id AND event_key >= :since")
+                .expression_attribute_values(":server_id", AttributeValue::S(server_id.clone())),
+            None => request
+                .index_name(AUDIT_LOG_INDEX)
+                .key_condition_expression("#log = :log AND event_key >= :since")
+                .expression_attribute_names("#log", "log")
+                .expression_attribute_values(":log", AttributeValue::S(AUDIT_LOG.to_string())),
+        };
+        let result = request.send().await?;
+
+        let events = result
+            .items
+            .unwrap_or_default()
+            .iter()
+            .map(audit_event_from_item)
+            .collect::<Result<Vec<_>>>()?;
+        let next_cursor = match result.last_evaluated_key {
+            Some(key) => Some(encode_cursor(&(
+                string_attr(&key, "server_id")?,
+                string_attr(&key, "event_key")?,
+            ))?),
+            None => None,
+        };
+
+        Ok(AuditPage {
+            events,
+            next_cursor,
+        })
+    }
+
+    async fn create_config_file(
+        &self,
+        config_file: ConfigFile,
+        audit: &AuditContext,
+    ) -> Result<()> {
+        // Catches config files that predate path claims; the claim in the
+        // transaction covers concurrent requests
+        if let Some(existing) = self.find_config_file_by_path(&config_file.path).await? {
+            return Err(ConfigPathConflict {
+                path: config_file.path,
+                existing_config_id: existing.config_id,
+            }
+            .into());
+        }
+
+        let config_id = config_file.config_id.clone();
+        let path = config_file.path.clone();
+        let event = audit.event(
+            AuditTarget::ConfigFile,
+            &config_id,
+            None,
+            Some(AuditRecord::ConfigFile(config_file.clone())),
+        );
+        let put = Put::builder()
+            .table_name(&self.config_files_table_name)
+            .set_item(Some(config_file_to_item(config_file)))
+            .condition_expression("attribute_not_exists(config_id)")
+            .build()?;
+        let result = self
+            .client
+            .transact_write_items()
+            .transact_items(self.claim_path(&path, &config_id)?)
+            .transact_items(TransactWriteItem::builder().put(put).build())
+            .transact_items(self.put_audit_event(&event)?)
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if step_failed(&e, 0) => match self.path_owner(&path).await? {
+                Some(existing_config_id) => Err(ConfigPathConflict {
+                    path,
+                    existing_config_id,
+                }
+                .into()),
+                None => Err(anyhow!(
+                    "Path claim for '{}' failed but no owner was found",
+                    path
+                )),
+            },
+            Err(e) if step_failed(&e, 1) => {
+                Err(anyhow!("Config file {} already exists", config_id))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_config_file(&self, config_id: &str) -> Result<Option<ConfigFile>> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.config_files_table_name)
+            .set_key(Some(Self::config_file_key(config_id)))
+            .consistent_read(true)
+            .send()
+            .await?;
+
+        result.item.as_ref().map(config_file_from_item).transpose()
+    }
+
+    async fn find_config_file_by_path(&self, path: &str) -> Result<Option<ConfigFile>> {
+        let result = self
+            .client
+            .query()
+            .table_name(&self.config_files_table_name)
+            .index_name(CONFIG_PATH_INDEX)
+            .key_condition_expression("#path = :path")
+            .expression_attribute_names("#path", "path")
+            .expression_attribute_values(":path", AttributeValue::S(path.to_string()))
+            .limit(1)
+            .send()
+            .await?;
+
+        result
+            .items
+            .unwrap_or_default()
+            .first()
+            .map(config_file_from_item)
+            .transpose()
+    }
+
+    async fn list_config_files(&self) -> Result<Vec<ConfigFile>> {
+        let mut config_files = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self
+                .client
+                .scan()
+                .table_name(&self.config_files_table_name)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items.unwrap_or_default() {
+                config_files.push(config_file_from_item(&item)?);
+            }
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        config_files.sort_by(|a, b| (&a.path, &a.config_id).cmp(&(&b.path, &b.config_id)));
+        Ok(config_files)
+    }
+
+    async fn delete_config_file(&self, config_id: &str, audit: &AuditContext) -> Result<()> {
+        let Some(config_file) = self.get_config_file(config_id).await? else {
+            return Ok(());
+        };
+        // `server_count` only tracks servers referencing the config file by ID, so
+        // servers using its path or importing it as a module are checked first
+        let users = self.find_servers_by_config_path(&config_file.path).await?;
+        if users.iter().any(|server| !server.is_deleted()) {
+            return Err(self.config_file_in_use(config_id, &config_file.path).await);
+        }
+
+        let delete = Delete::builder()
+            .table_name(&self.config_files_table_name)
+            .set_key(Some(Self::config_file_key(config_id)))
+            .condition_expression("attribute_not_exists(server_count) OR server_count <= :zero")
+            .expression_attribute_values(":zero", AttributeValue::N("0".to_string()))
+            .build()?;
+        let mut transaction = self
+            .client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().delete(delete).build());
+        if let Some(release) = self.release_path(&config_file.path, config_id).await? {
+            transaction = transaction.transact_items(release);
+        }
+        let path = config_file.path.clone();
+        let event = audit.event(
+            AuditTarget::ConfigFile,
+            config_id,
+            Some(AuditRecord::ConfigFile(config_file)),
+            None,
+        );
+        transaction = transaction.transact_items(self.put_audit_event(&event)?);
+
+        match transaction.send().await {
+            Ok(_) => Ok(()),
+            Err(e) if step_failed(&e, 0) => Err(self.config_file_in_use(config_id, &path).await),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn create_api_key(&self, api_key: StoredApiKey, audit: &AuditContext) -> Result<()> {
+        let key_id = api_key.key.key_id.clone();
+        let event = audit.event(
+            AuditTarget::ApiKey,
+            &key_id,
+            None,
+            Some(AuditRecord::ApiKey(api_key.key.clone())),
+        );
+        let put = Put::builder()
+            .table_name(&self.api_keys_table_name)
+            .set_item(Some(api_key_to_item(api_key)))
+            .condition_expression("attribute_not_exists(key_id)")
+            .build()?;
+        let result = self
+            .client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().put(put).build())
+            .transact_items(self.put_audit_event(&event)?)
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if step_failed(&e, 0) => Err(anyhow!("API key {} already exists", key_id)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_api_key(&self, key_id: &str) -> Result<Option<StoredApiKey>> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.api_keys_table_name)
+            .set_key(Some(Self::api_key_key(key_id)))
+            .consistent_read(true)
+            .send()
+            .await?;
+
+        result.item.as_ref().map(api_key_from_item).transpose()
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>> {
+        let mut keys = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self
+                .client
+                .scan()
+                .table_name(&self.api_keys_table_name)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items.unwrap_or_default() {
+                keys.push(api_key_from_item(&item)?.key);
+            }
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        keys.sort_by(|a, b| (a.created_at, &a.key_id).cmp(&(b.created_at, &b.key_id)));
+        Ok(keys)
+    }
+
+    async fn delete_api_key(&self, key_id: &str, audit: &AuditContext) -> Result<()> {
+        let Some(api_key) = self.get_api_key(key_id).await? else {
+            return Ok(());
+        };
+        let delete = Delete::builder()
+            .table_name(&self.api_keys_table_name)
+            .set_key(Some(Self::api_key_key(key_id)))
+            .condition_expression("attribute_exists(key_id)")
+            .build()?;
+        let event = audit.event(
+            AuditTarget::ApiKey,
+            key_id,
+            Some(AuditRecord::ApiKey(api_key.key)),
+            None,
+        );
+        let result = self
+            .client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().delete(delete).build())
+            .transact_items(self.put_audit_event(&event)?)
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            // Someone else deleted it first, and recorded doing so
+            Err(e) if step_failed(&e, 0) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::test_server;
+    use aws_sdk_dynamodb::config::{BehaviorVersion, Region};
+
+    /// A store whose client is never sent anything.
+    fn store() -> DynamoDbStore {
+        let config = aws_sdk_dynamodb::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .build();
+        let tables = DynamoDbTables {
+            servers: "servers".to_string(),
+            names: "names".to_string(),
+            revisions: "revisions".to_string(),
+            audit: "audit".to_string(),
+            config_files: "config-files".to_string(),
+            config_paths: "config-paths".to_string(),
+            modules: "modules".to_string(),
+            api_keys: "api-keys".to_string(),
+        };
+        DynamoDbStore::new(aws_sdk_dynamodb::Client::from_conf(config), tables)
+    }
+
+    #[test]
+    fn servers_in_the_trash_are_not_given_an_expiry() {
+        let current = test_server("web-1", "/etc/nixos/web.nix");
+        let mut deleted = current.clone();
+        deleted.deleted_at = Some(Utc::now());
+        deleted.version += 1;
+
+        // Only `purge_expired` may remove them, together with their claims
+        let step = store().replace_server(&deleted, &current).unwrap();
+        let item = step.put.unwrap().item;
+        assert!(item.contains_key("deleted_at"));
+        assert!(!item.contains_key("purge_at"));
+    }
+
+    #[test]
+    fn servers_are_spread_over_every_listing_shard() {
+        let mut counts = [0; SERVER_LISTING_SHARDS as usize];
+        for _ in 0..800 {
+            let server_id = uuid::Uuid::new_v4().to_string();
+            let shard = listing_shard(&server_id);
+            assert_eq!(listing_shard(&server_id), shard);
+            counts[shard as usize] += 1;
+        }
+        assert!(counts.iter().all(|&count| count > 50), "{:?}", counts);
+
+        // Stored shards must never move
+        assert_eq!(listing_shard("5a1d6c2e-7f3b-4e8a-9c0d-1b2e3f4a5b6c"), 3);
+    }
+
+    #[test]
+    fn servers_are_listed_in_their_shard_by_creation_order() {
+        let server = test_server("web-1", "/etc/nixos/web.nix");
+        let item = server_to_item(server.clone());
+        assert_eq!(
+            item["listing"],
+            AttributeValue::S(format!("servers#{}", listing_shard(&server.server_id)))
+        );
+
+        let later = DateTime::parse_from_rfc3339("2030-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let (AttributeValue::S(first), AttributeValue::S(second)) = (
+            created_key(&server.created_at, &server.server_id),
+            created_key(&later, "0"),
+        ) else {
+            panic!("created keys are strings");
+        };
+        assert!(first < second);
+    }
+}
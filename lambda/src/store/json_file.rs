@@ -0,0 +1,508 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use homelab_api::api_key::ApiKey;
+use homelab_api::audit_event::AuditEvent;
+use homelab_api::config_file::ConfigFile;
+use homelab_api::server_config::{ServerConfig, UpdateServerRequest};
+use homelab_api::server_revision::ServerRevision;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use super::memory::{AuditLog, Inventory};
+use super::{
+    AuditContext, AuditPage, AuditQuery, ListQuery, ServerPage, ServerStore, StoredApiKey,
+};
+
+/// Store that keeps every server in a single JSON file on local disk.
+///
+/// The whole file is rewritten after each mutation, which is plenty for a
+/// homelab-sized inventory and keeps the file human-readable. The audit log
+/// grows without bound, so it goes to a separate file next to it
+/// (`<name>.audit.jsonl`, one event per line) that is only ever appended to.
+///
+/// Both files hold API key hashes and are only readable by their owner.
+pub struct JsonFileStore {
+    path: PathBuf,
+    audit_path: PathBuf,
+    inventory: Mutex<Inventory>,
+    audit: Mutex<AuditLog>,
+}
+
+/// On-disk layout of the store file.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoreFile {
+    Inventory {
+        servers: Vec<ServerConfig>,
+        #[serde(default)]
+        revisions: Vec<ServerRevision>,
+        /// Files written before the audit log moved to its own file keep it here.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        audit: Vec<AuditEvent>,
+        #[serde(default)]
+        config_files: Vec<ConfigFile>,
+        #[serde(default)]
+        api_keys: Vec<StoredApiKey>,
+    },
+    /// Files written before revision history were a bare array of servers.
+    Legacy(Vec<ServerConfig>),
+}
+
+impl JsonFileStore {
+    /// Opens the store at `path`, starting empty if the file does not exist yet.
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let audit_path = path.with_extension("audit.jsonl");
+        let (inventory, inline_audit) = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => {
+                let file: StoreFile = serde_json::from_str(&content)
+                    .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))?;
+                match file {
+                    StoreFile::Inventory {
+                        servers,
+                        revisions,
+                        audit,
+                        config_files,
+                        api_keys,
+                    } => (
+                        Inventory::from_parts((servers, revisions, config_files, api_keys)),
+                        audit,
+                    ),
+                    StoreFile::Legacy(servers) => (
+                        Inventory::from_parts((servers, Vec::new(), Vec::new(), Vec::new())),
+                        Vec::new(),
+                    ),
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                (Inventory::default(), Vec::new())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut events = read_audit_log(&audit_path).await?;
+
+        // Move an audit log still kept in the store file out into its own file
+        let migrate_audit = !inline_audit.is_empty();
+        if migrate_audit {
+            events.extend(inline_audit);
+            events.sort_by(|a, b| (a.recorded_at, &a.event_id).cmp(&(b.recorded_at, &b.event_id)));
+            events.dedup_by(|a, b| a.event_id == b.event_id);
+            let mut content = Vec::new();
+            for event in &events {
+                serde_json::to_writer(&mut content, event)?;
+                content.push(b'\n');
+            }
+            write_private(&audit_path, &content).await?;
+        }
+
+        let store = Self {
+            path,
+            audit_path,
+            inventory: Mutex::new(inventory),
+            audit: Mutex::new(AuditLog::new(events)),
+        };
+        if migrate_audit {
+            store.persist(&*store.inventory.lock().await).await?;
+        }
+        Ok(store)
+    }
+
+    /// Appends `events` to the audit log file, writes `updated` to disk and
+    /// only then makes both current, so a failed write leaves memory agreeing
+    /// with the files. If the store file cannot be written the audit log file
+    /// is cut back to where it was, leaving neither file with the change.
+    async fn commit(
+        &self,
+        inventory: &mut Inventory,
+        updated: Inventory,
+        events: Vec<AuditEvent>,
+    ) -> Result<()> {
+        let mut audit = self.audit.lock().await;
+        let mut content = Vec::new();
+        for event in &events {
+            serde_json::to_writer(&mut content, event)?;
+            content.push(b'\n');
+        }
+        let mut file = private_options()
+            .append(true)
+            .create(true)
+            .open(&self.audit_path)
+            .await?;
+        let audit_len = file.metadata().await?.len();
+        let appended = async {
+            file.write_all(&content).await?;
+            file.sync_data().await?;
+            self.persist(&updated).await
+        }
+        .await;
+        if let Err(e) = appended {
+            file.set_len(audit_len).await?;
+            return Err(e);
+        }
+
+        *inventory = updated;
+        audit.record(events);
+        Ok(())
+    }
+
+    async fn persist(&self, inventory: &Inventory) -> Result<()> {
+        let (servers, revisions, config_files, api_keys) = inventory.to_parts();
+        let content = serde_json::to_vec_pretty(&StoreFile::Inventory {
+            servers,
+            revisions,
+            audit: Vec::new(),
+            config_files,
+            api_keys,
+        })?;
+        write_private(&self.path, &content).await
+    }
+}
+
+/// Replaces the file at `path` with `content`, readable by its owner only.
+///
+/// The content goes to a sibling file that is renamed into place, so a crash
+/// never leaves a truncated file behind.
+async fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let tmp_path = path.with_file_name(name);
+
+    // A leftover from a crash may have been created with other permissions
+    match tokio::fs::remove_file(&tmp_path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut file = private_options()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .await?;
+    file.write_all(content).await?;
+    file.sync_all().await?;
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+/// Options for creating a file only its owner can read.
+fn private_options() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    #[cfg(unix)]
+    options.mode(0o600);
+    options
+}
+
+/// Reads every event from the audit log file at `path`, which may not exist yet.
+async fn read_audit_log(path: &Path) -> Result<Vec<AuditEvent>> {
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                anyhow!(
+                    "Failed to parse {} line {}: {}",
+                    path.display(),
+                    index + 1,
+                    e
+                )
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl ServerStore for JsonFileStore {
+    async fn create_server(&self, server: ServerConfig, audit: &AuditContext) -> Result<()> {
+        let mut inventory = self.inventory.lock().await;
+        let mut updated = inventory.clone();
+        let event = updated.create_server(server, audit)?;
+        self.commit(&mut inventory, updated, vec![event]).await
+    }
+
+    async fn get_server(&self, server_id: &str) -> Result<Option<ServerConfig>> {
+        let inventory = self.inventory.lock().await;
+        Ok(inventory.get_server(server_id))
+    }
+
+    async fn find_servers_by_name(&self, server_name: &str) -> Result<Vec<ServerConfig>> {
+        let inventory = self.inventory.lock().await;
+        Ok(inventory.find_servers_by_name(server_name))
+    }
+
+    async fn find_servers_by_config_path(
+        &self,
+        config_file_path: &str,
+    ) -> Result<Vec<ServerConfig>> {
+        let inventory = self.inventory.lock().await;
+        Ok(inventory.find_servers_by_config_path(config_file_path))
+    }
+
+    async fn list_servers(&self, query: ListQuery) -> Result<ServerPage> {
+        let inventory = self.inventory.lock().await;
+        inventory.list_servers(&query)
+    }
+
+    async fn update_server(
+        &self,
+        server_id: &str,
+        changes: UpdateServerRequest,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<ServerConfig> {
+        let mut inventory = self.inventory.lock().await;
+        let mut updated = inventory.clone();
+        let (server, event) = updated.update_server(server_id, changes, audit, expected_version)?;
+        self.commit(&mut inventory, updated, vec![event]).await?;
+        Ok(server)
+    }
+
+    async fn delete_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<()> {
+        let mut inventory = self.inventory.lock().await;
+        let mut updated = inventory.clone();
+        if let Some(event) = updated.delete_server(server_id, audit, expected_version)? {
+            self.commit(&mut inventory, updated, vec![event]).await?;
+        }
+        Ok(())
+    }
+
+    async fn restore_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<ServerConfig> {
+        let mut inventory = self.inventory.lock().await;
+        let mut updated = inventory.clone();
+        let (restored, event) = updated.restore_server(server_id, audit, expected_version)?;
+        self.commit(&mut inventory, updated, vec![event]).await?;
+        Ok(restored)
+    }
+
+    async fn purge_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<()> {
+        let mut inventory = self.inventory.lock().await;
+        let mut updated = inventory.clone();
+        if let Some(event) = updated.purge_server(server_id, audit, expected_version)? {
+            self.commit(&mut inventory, updated, vec![event]).await?;
+        }
+        Ok(())
+    }
+
+    async fn purge_expired(
+        &self,
+        deleted_before: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> Result<usize> {
+        let mut inventory = self.inventory.lock().await;
+        let mut updated = inventory.clone();
+        let events = updated.purge_expired(deleted_before, audit);
+        let purged = events.len();
+        if purged > 0 {
+            self.commit(&mut inventory, updated, events).await?;
+        }
+        Ok(purged)
+    }
+
+    async fn list_revisions(&self, server_id: &str) -> Result<Vec<ServerRevision>> {
+        let inventory = self.inventory.lock().await;
+        Ok(inventory.list_revisions(server_id))
+    }
+
+    async fn list_audit_events(&self, query: AuditQuery) -> Result<AuditPage> {
+        let audit = self.audit.lock().await;
+        audit.list(&query)
+    }
+
+    async fn create_config_file(
+        &self,
+        config_file: ConfigFile,
+        audit: &AuditContext,
+    ) -> Result<()> {
+        let mut inventory = self.inventory.lock().await;
+        let mut updated = inventory.clone();
+        let event = updated.create_config_file(config_file, audit)?;
+        self.commit(&mut inventory, updated, vec![event]).await
+    }
+
+    async fn get_config_file(&self, config_id: &str) -> Result<Option<ConfigFile>> {
+        let inventory = self.inventory.lock().await;
+        Ok(inventory.get_config_file(config_id))
+    }
+
+    async fn find_config_file_by_path(&self, path: &str) -> Result<Option<ConfigFile>> {
+        let inventory = self.inventory.lock().await;
+        Ok(inventory.find_config_file_by_path(path))
+    }
+
+    async fn list_config_files(&self) -> Result<Vec<ConfigFile>> {
+        let inventory = self.inventory.lock().await;
+        Ok(inventory.list_config_files())
+    }
+
+    async fn delete_config_file(&self, config_id: &str, audit: &AuditContext) -> Result<()> {
+        let mut inventory = self.inventory.lock().await;
+        let mut updated = inventory.clone();
+        if let Some(event) = updated.delete_config_file(config_id, audit)? {
+            self.commit(&mut inventory, updated, vec![event]).await?;
+        }
+        Ok(())
+    }
+
+    async fn create_api_key(&self, api_key: StoredApiKey, audit: &AuditContext) -> Result<()> {
+        let mut inventory = self.inventory.lock().await;
+        let mut updated = inventory.clone();
+        let event = updated.create_api_key(api_key, audit);
+        self.commit(&mut inventory, updated, vec![event]).await
+    }
+
+    async fn get_api_key(&self, key_id: &str) -> Result<Option<StoredApiKey>> {
+        let inventory = self.inventory.lock().await;
+        Ok(inventory.get_api_key(key_id))
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>> {
+        let inventory = self.inventory.lock().await;
+        Ok(inventory.list_api_keys())
+    }
+
+    async fn delete_api_key(&self, key_id: &str, audit: &AuditContext) -> Result<()> {
+        let mut inventory = self.inventory.lock().await;
+        let mut updated = inventory.clone();
+        if let Some(event) = updated.delete_api_key(key_id, audit) {
+            self.commit(&mut inventory, updated, vec![event]).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{test_audit, test_server};
+    use homelab_api::audit_event::AuditAction;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn failed_writes_leave_the_inventory_unchanged() {
+        let dir = std::env::temp_dir().join(format!("homelab-json-store-{}", Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let store = JsonFileStore::open(dir.join("servers.json")).await.unwrap();
+
+        let kept = test_server("web-1", "/etc/nixos/web.nix");
+        store
+            .create_server(kept.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        // Without its directory the store file cannot be written
+        std::fs::remove_dir_all(&dir).unwrap();
+        let lost = test_server("web-2", "/etc/nixos/web.nix");
+        assert!(store
+            .create_server(lost.clone(), &test_audit(AuditAction::Create))
+            .await
+            .is_err());
+        assert!(store.get_server(&lost.server_id).await.unwrap().is_none());
+
+        let changes = UpdateServerRequest {
+            description: Some("Front end".to_string()),
+            ..Default::default()
+        };
+        assert!(store
+            .update_server(
+                &kept.server_id,
+                changes,
+                &test_audit(AuditAction::Update),
+                None,
+            )
+            .await
+            .is_err());
+        let current = store.get_server(&kept.server_id).await.unwrap().unwrap();
+        assert_eq!(current.version, 1);
+        assert_eq!(current.description, None);
+        assert_eq!(
+            store.list_revisions(&kept.server_id).await.unwrap().len(),
+            1
+        );
+        assert_eq!(
+            store
+                .list_audit_events(AuditQuery {
+                    server_id: None,
+                    since: None,
+                    limit: 10,
+                    cursor: None,
+                })
+                .await
+                .unwrap()
+                .events
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn audit_events_are_appended_to_their_own_private_file() {
+        let dir = std::env::temp_dir().join(format!("homelab-json-store-{}", Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("servers.json");
+
+        // A store file from before the audit log had its own file
+        let old = test_server("web-1", "/etc/nixos/web.nix");
+        let old_event = test_audit(AuditAction::Create).server_event(None, Some(&old));
+        let legacy = serde_json::json!({ "servers": [old], "audit": [old_event] });
+        std::fs::write(&path, legacy.to_string()).unwrap();
+
+        let store = JsonFileStore::open(&path).await.unwrap();
+        let server = test_server("web-2", "/etc/nixos/web.nix");
+        store
+            .create_server(server.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains(&old_event.event_id));
+        let audit_path = dir.join("servers.audit.jsonl");
+        assert_eq!(
+            std::fs::read_to_string(&audit_path)
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+        #[cfg(unix)]
+        for file in [&path, &audit_path] {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let reopened = JsonFileStore::open(&path).await.unwrap();
+        let query = AuditQuery {
+            server_id: None,
+            since: None,
+            limit: 10,
+            cursor: None,
+        };
+        let events = reopened.list_audit_events(query).await.unwrap().events;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_id, old_event.event_id);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
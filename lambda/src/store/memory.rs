@@ -0,0 +1,816 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use homelab_api::api_key::ApiKey;
+use homelab_api::audit_event::{AuditEvent, AuditRecord, AuditTarget};
+use homelab_api::config_file::ConfigFile;
+use homelab_api::server_config::{ServerConfig, UpdateServerRequest};
+use homelab_api::server_revision::ServerRevision;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use super::{
+    apply_changes, check_version, ensure_name_available, mark_deleted, mark_restored, paginate,
+    paginate_audit, AuditContext, AuditPage, AuditQuery, ConfigFileInUse, ConfigPathConflict,
+    ListQuery, ServerDeleted, ServerPage, ServerStore, StoredApiKey, UnknownConfigFile,
+};
+
+/// Servers, their revision history, config files and API keys held entirely in memory.
+///
+/// Shared by `MemoryStore` and `JsonFileStore`, which only differ in how they
+/// lock it and whether they write it back to disk. Each change returns the
+/// audit events describing it, for the caller to append to its `AuditLog`
+/// under the same lock.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Inventory {
+    servers: HashMap<String, ServerConfig>,
+    revisions: HashMap<String, Vec<ServerRevision>>,
+    config_files: HashMap<String, ConfigFile>,
+    api_keys: HashMap<String, StoredApiKey>,
+}
+
+/// The audit log, held apart from `Inventory` so that copying the inventory
+/// for a change does not copy every event ever recorded.
+#[derive(Debug, Default)]
+pub(crate) struct AuditLog {
+    events: Vec<AuditEvent>,
+}
+
+impl AuditLog {
+    pub fn new(events: Vec<AuditEvent>) -> Self {
+        Self { events }
+    }
+
+    pub fn record(&mut self, events: impl IntoIterator<Item = AuditEvent>) {
+        self.events.extend(events);
+    }
+
+    pub fn list(&self, query: &AuditQuery) -> Result<AuditPage> {
+        paginate_audit(self.events.clone(), query)
+    }
+}
+
+/// Whether `server` imports `path` as one of its modules.
+fn imports(server: &ServerConfig, path: &str) -> bool {
+    server.modules.iter().any(|module| module == path)
+}
+
+/// Everything an `Inventory` holds, as flat lists.
+pub(crate) type InventoryParts = (
+    Vec<ServerConfig>,
+    Vec<ServerRevision>,
+    Vec<ConfigFile>,
+    Vec<StoredApiKey>,
+);
+
+impl Inventory {
+    pub fn from_parts((servers, revisions, config_files, api_keys): InventoryParts) -> Self {
+        let mut inventory = Self {
+            servers: servers
+                .into_iter()
+                .map(|server| (server.server_id.clone(), server))
+                .collect(),
+            revisions: HashMap::new(),
+            config_files: config_files
+                .into_iter()
+                .map(|config_file| (config_file.config_id.clone(), config_file))
+                .collect(),
+            api_keys: api_keys
+                .into_iter()
+                .map(|api_key| (api_key.key.key_id.clone(), api_key))
+                .collect(),
+        };
+        for revision in revisions {
+            inventory.record(revision);
+        }
+        inventory
+    }
+
+    /// Servers in creation order, revisions in the order they were recorded,
+    /// config files by path and API keys oldest first.
+    pub fn to_parts(&self) -> InventoryParts {
+        let mut servers: Vec<ServerConfig> = self.servers.values().cloned().collect();
+        servers.sort_by(|a, b| (a.created_at, &a.server_id).cmp(&(b.created_at, &b.server_id)));
+
+        let mut revisions: Vec<ServerRevision> =
+            self.revisions.values().flatten().cloned().collect();
+        revisions.sort_by_key(|revision| (revision.recorded_at, revision.revision));
+
+        let mut api_keys: Vec<StoredApiKey> = self.api_keys.values().cloned().collect();
+        api_keys.sort_by(|a, b| {
+            (a.key.created_at, &a.key.key_id).cmp(&(b.key.created_at, &b.key.key_id))
+        });
+
+        (servers, revisions, self.list_config_files(), api_keys)
+    }
+
+    fn ensure_config_file_exists(&self, config_id: Option<&str>) -> Result<()> {
+        match config_id {
+            Some(config_id) if !self.config_files.contains_key(config_id) => {
+                Err(UnknownConfigFile {
+                    config_id: config_id.to_string(),
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn record(&mut self, revision: ServerRevision) {
+        self.revisions
+            .entry(revision.server_id.clone())
+            .or_default()
+            .push(revision);
+    }
+
+    pub fn create_server(
+        &mut self,
+        server: ServerConfig,
+        audit: &AuditContext,
+    ) -> Result<AuditEvent> {
+        ensure_name_available(
+            self.servers.values(),
+            &server.server_name,
+            &server.server_id,
+        )?;
+        self.ensure_config_file_exists(server.config_id.as_deref())?;
+        self.record(ServerRevision::created(&server));
+        let event = audit.server_event(None, Some(&server));
+        self.servers.insert(server.server_id.clone(), server);
+        Ok(event)
+    }
+
+    pub fn get_server(&self, server_id: &str) -> Option<ServerConfig> {
+        self.servers.get(server_id).cloned()
+    }
+
+    pub fn find_servers_by_name(&self, server_name: &str) -> Vec<ServerConfig> {
+        self.servers
+            .values()
+            .filter(|server| server.server_name == server_name)
+            .cloned()
+            .collect()
+    }
+
+    pub fn find_servers_by_config_path(&self, config_file_path: &str) -> Vec<ServerConfig> {
+        let mut servers: Vec<ServerConfig> = self
+            .servers
+            .values()
+            .filter(|server| {
+                server.config_file_path == config_file_path || imports(server, config_file_path)
+            })
+            .cloned()
+            .collect();
+        servers.sort_by(|a, b| (a.created_at, &a.server_id).cmp(&(b.created_at, &b.server_id)));
+        servers
+    }
+
+    pub fn list_servers(&self, query: &ListQuery) -> Result<ServerPage> {
+        paginate(self.servers.values().cloned().collect(), query)
+    }
+
+    pub fn update_server(
+        &mut self,
+        server_id: &str,
+        changes: UpdateServerRequest,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<(ServerConfig, AuditEvent)> {
+        check_version(self.servers.get(server_id), expected_version)?;
+        if self
+            .servers
+            .get(server_id)
+            .is_some_and(ServerConfig::is_deleted)
+        {
+            return Err(ServerDeleted {
+                server_id: server_id.to_string(),
+            }
+            .into());
+        }
+        if let Some(server_name) = &changes.server_name {
+            ensure_name_available(self.servers.values(), server_name, server_id)?;
+        }
+        self.ensure_config_file_exists(changes.config_id.as_deref())?;
+
+        let server = self
+            .servers
+            .get_mut(server_id)
+            .ok_or_else(|| anyhow!("Server {} does not exist", server_id))?;
+        let before = server.clone();
+        apply_changes(server, changes, audit.at, &audit.actor)?;
+        let updated = server.clone();
+        self.record(ServerRevision::updated(&updated));
+        let event = audit.server_event(Some(&before), Some(&updated));
+        Ok((updated, event))
+    }
+
+    /// Moves a server into the trash, returning the event if anything changed.
+    pub fn delete_server(
+        &mut self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<Option<AuditEvent>> {
+        check_version(self.servers.get(server_id), expected_version)?;
+        let Some(server) = self
+            .servers
+            .get_mut(server_id)
+            .filter(|server| !server.is_deleted())
+        else {
+            return Ok(None);
+        };
+        let before = server.clone();
+        mark_deleted(server, audit.at, &audit.actor);
+        let deleted = server.clone();
+        self.record(ServerRevision::deleted(&deleted));
+        Ok(Some(audit.server_event(Some(&before), Some(&deleted))))
+    }
+
+    pub fn restore_server(
+        &mut self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<(ServerConfig, AuditEvent)> {
+        check_version(self.servers.get(server_id), expected_version)?;
+        let server_name = match self.servers.get(server_id) {
+            Some(server) if server.is_deleted() => server.server_name.clone(),
+            Some(_) => return Err(anyhow!("Server {} is not deleted", server_id)),
+            None => return Err(anyhow!("Server {} does not exist", server_id)),
+        };
+        ensure_name_available(self.servers.values(), &server_name, server_id)?;
+
+        let config_files = &self.config_files;
+        let server = self
+            .servers
+            .get_mut(server_id)
+            .ok_or_else(|| anyhow!("Server {} does not exist", server_id))?;
+        let before = server.clone();
+        mark_restored(server, audit.at, &audit.actor);
+        server.config_id = server
+            .config_id
+            .take()
+            .filter(|config_id| config_files.contains_key(config_id));
+        let restored = server.clone();
+        self.record(ServerRevision::restored(&restored));
+        let event = audit.server_event(Some(&before), Some(&restored));
+        Ok((restored, event))
+    }
+
+    /// Removes a server for good, returning the event if anything was removed.
+    pub fn purge_server(
+        &mut self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<Option<AuditEvent>> {
+        check_version(self.servers.get(server_id), expected_version)?;
+        Ok(self.servers.remove(server_id).map(|server| {
+            self.record(ServerRevision::purged(&server, audit.at));
+            audit.server_event(Some(&server), None)
+        }))
+    }
+
+    /// Purges servers deleted before `deleted_before`, returning an event for each.
+    pub fn purge_expired(
+        &mut self,
+        deleted_before: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> Vec<AuditEvent> {
+        let expired: Vec<String> = self
+            .servers
+            .values()
+            .filter(|server| {
+                server
+                    .deleted_at
+                    .is_some_and(|deleted_at| deleted_at < deleted_before)
+            })
+            .map(|server| server.server_id.clone())
+            .collect();
+
+        let mut events = Vec::new();
+        for server_id in &expired {
+            if let Some(server) = self.servers.remove(server_id) {
+                self.record(ServerRevision::purged(&server, audit.at));
+                events.push(audit.server_event(Some(&server), None));
+            }
+        }
+        events
+    }
+
+    pub fn list_revisions(&self, server_id: &str) -> Vec<ServerRevision> {
+        let mut revisions = self.revisions.get(server_id).cloned().unwrap_or_default();
+        revisions.sort_by_key(|revision| revision.revision);
+        revisions
+    }
+
+    pub fn create_config_file(
+        &mut self,
+        config_file: ConfigFile,
+        audit: &AuditContext,
+    ) -> Result<AuditEvent> {
+        if let Some(existing) = self.find_config_file_by_path(&config_file.path) {
+            return Err(ConfigPathConflict {
+                path: config_file.path,
+                existing_config_id: existing.config_id,
+            }
+            .into());
+        }
+        let event = audit.event(
+            AuditTarget::ConfigFile,
+            &config_file.config_id,
+            None,
+            Some(AuditRecord::ConfigFile(config_file.clone())),
+        );
+        self.config_files
+            .insert(config_file.config_id.clone(), config_file);
+        Ok(event)
+    }
+
+    pub fn get_config_file(&self, config_id: &str) -> Option<ConfigFile> {
+        self.config_files.get(config_id).cloned()
+    }
+
+    pub fn find_config_file_by_path(&self, path: &str) -> Option<ConfigFile> {
+        self.config_files
+            .values()
+            .find(|config_file| config_file.path == path)
+            .cloned()
+    }
+
+    pub fn list_config_files(&self) -> Vec<ConfigFile> {
+        let mut config_files: Vec<ConfigFile> = self.config_files.values().cloned().collect();
+        config_files.sort_by(|a, b| (&a.path, &a.config_id).cmp(&(&b.path, &b.config_id)));
+        config_files
+    }
+
+    /// Removes a config file, returning the event if anything was removed.
+    pub fn delete_config_file(
+        &mut self,
+        config_id: &str,
+        audit: &AuditContext,
+    ) -> Result<Option<AuditEvent>> {
+        let Some(path) = self
+            .config_files
+            .get(config_id)
+            .map(|config_file| config_file.path.clone())
+        else {
+            return Ok(None);
+        };
+        let mut users: Vec<&ServerConfig> = self
+            .servers
+            .values()
+            .filter(|server| !server.is_deleted())
+            .filter(|server| {
+                server.config_id.as_deref() == Some(config_id)
+                    || server.config_file_path == path
+                    || imports(server, &path)
+            })
+            .collect();
+        if !users.is_empty() {
+            users.sort_by(|a, b| (a.created_at, &a.server_id).cmp(&(b.created_at, &b.server_id)));
+            return Err(ConfigFileInUse {
+                config_id: config_id.to_string(),
+                server_ids: users
+                    .iter()
+                    .map(|server| server.server_id.clone())
+                    .collect(),
+            }
+            .into());
+        }
+        Ok(self.config_files.remove(config_id).map(|config_file| {
+            audit.event(
+                AuditTarget::ConfigFile,
+                config_id,
+                Some(AuditRecord::ConfigFile(config_file)),
+                None,
+            )
+        }))
+    }
+
+    pub fn create_api_key(&mut self, api_key: StoredApiKey, audit: &AuditContext) -> AuditEvent {
+        let event = audit.event(
+            AuditTarget::ApiKey,
+            &api_key.key.key_id,
+            None,
+            Some(AuditRecord::ApiKey(api_key.key.clone())),
+        );
+        self.api_keys.insert(api_key.key.key_id.clone(), api_key);
+        event
+    }
+
+    pub fn get_api_key(&self, key_id: &str) -> Option<StoredApiKey> {
+        self.api_keys.get(key_id).cloned()
+    }
+
+    pub fn list_api_keys(&self) -> Vec<ApiKey> {
+        let mut keys: Vec<ApiKey> = self
+            .api_keys
+            .values()
+            .map(|api_key| api_key.key.clone())
+            .collect();
+        keys.sort_by(|a, b| (a.created_at, &a.key_id).cmp(&(b.created_at, &b.key_id)));
+        keys
+    }
+
+    /// Removes an API key, returning the event if anything was removed.
+    pub fn delete_api_key(&mut self, key_id: &str, audit: &AuditContext) -> Option<AuditEvent> {
+        self.api_keys.remove(key_id).map(|api_key| {
+            audit.event(
+                AuditTarget::ApiKey,
+                key_id,
+                Some(AuditRecord::ApiKey(api_key.key)),
+                None,
+            )
+        })
+    }
+}
+
+/// Non-persistent store backed by a `HashMap`, for tests and local development.
+#[derive(Default)]
+pub struct MemoryStore {
+    inventory: RwLock<Inventory>,
+    audit: RwLock<AuditLog>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a change to the inventory and appends the events it returns to
+    /// the audit log, holding both locks so readers never see one without the other.
+    fn change<T>(
+        &self,
+        change: impl FnOnce(&mut Inventory) -> Result<(T, Vec<AuditEvent>)>,
+    ) -> Result<T> {
+        let mut inventory = self
+            .inventory
+            .write()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        let mut audit = self
+            .audit
+            .write()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        let (value, events) = change(&mut inventory)?;
+        audit.record(events);
+        Ok(value)
+    }
+}
+
+#[async_trait]
+impl ServerStore for MemoryStore {
+    async fn create_server(&self, server: ServerConfig, audit: &AuditContext) -> Result<()> {
+        self.change(|inventory| Ok(((), vec![inventory.create_server(server, audit)?])))
+    }
+
+    async fn get_server(&self, server_id: &str) -> Result<Option<ServerConfig>> {
+        let inventory = self
+            .inventory
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        Ok(inventory.get_server(server_id))
+    }
+
+    async fn find_servers_by_name(&self, server_name: &str) -> Result<Vec<ServerConfig>> {
+        let inventory = self
+            .inventory
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        Ok(inventory.find_servers_by_name(server_name))
+    }
+
+    async fn find_servers_by_config_path(
+        &self,
+        config_file_path: &str,
+    ) -> Result<Vec<ServerConfig>> {
+        let inventory = self
+            .inventory
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        Ok(inventory.find_servers_by_config_path(config_file_path))
+    }
+
+    async fn list_servers(&self, query: ListQuery) -> Result<ServerPage> {
+        let inventory = self
+            .inventory
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        inventory.list_servers(&query)
+    }
+
+    async fn update_server(
+        &self,
+        server_id: &str,
+        changes: UpdateServerRequest,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<ServerConfig> {
+        self.change(|inventory| {
+            let (updated, event) =
+                inventory.update_server(server_id, changes, audit, expected_version)?;
+            Ok((updated, vec![event]))
+        })
+    }
+
+    async fn delete_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<()> {
+        self.change(|inventory| {
+            let event = inventory.delete_server(server_id, audit, expected_version)?;
+            Ok(((), event.into_iter().collect()))
+        })
+    }
+
+    async fn restore_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<ServerConfig> {
+        self.change(|inventory| {
+            let (restored, event) = inventory.restore_server(server_id, audit, expected_version)?;
+            Ok((restored, vec![event]))
+        })
+    }
+
+    async fn purge_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<()> {
+        self.change(|inventory| {
+            let event = inventory.purge_server(server_id, audit, expected_version)?;
+            Ok(((), event.into_iter().collect()))
+        })
+    }
+
+    async fn purge_expired(
+        &self,
+        deleted_before: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> Result<usize> {
+        self.change(|inventory| {
+            let events = inventory.purge_expired(deleted_before, audit);
+            Ok((events.len(), events))
+        })
+    }
+
+    async fn list_revisions(&self, server_id: &str) -> Result<Vec<ServerRevision>> {
+        let inventory = self
+            .inventory
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        Ok(inventory.list_revisions(server_id))
+    }
+
+    async fn list_audit_events(&self, query: AuditQuery) -> Result<AuditPage> {
+        let audit = self
+            .audit
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        audit.list(&query)
+    }
+
+    async fn create_config_file(
+        &self,
+        config_file: ConfigFile,
+        audit: &AuditContext,
+    ) -> Result<()> {
+        self.change(|inventory| Ok(((), vec![inventory.create_config_file(config_file, audit)?])))
+    }
+
+    async fn get_config_file(&self, config_id: &str) -> Result<Option<ConfigFile>> {
+        let inventory = self
+            .inventory
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        Ok(inventory.get_config_file(config_id))
+    }
+
+    async fn find_config_file_by_path(&self, path: &str) -> Result<Option<ConfigFile>> {
+        let inventory = self
+            .inventory
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        Ok(inventory.find_config_file_by_path(path))
+    }
+
+    async fn list_config_files(&self) -> Result<Vec<ConfigFile>> {
+        let inventory = self
+            .inventory
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        Ok(inventory.list_config_files())
+    }
+
+    async fn delete_config_file(&self, config_id: &str, audit: &AuditContext) -> Result<()> {
+        self.change(|inventory| {
+            let event = inventory.delete_config_file(config_id, audit)?;
+            Ok(((), event.into_iter().collect()))
+        })
+    }
+
+    async fn create_api_key(&self, api_key: StoredApiKey, audit: &AuditContext) -> Result<()> {
+        self.change(|inventory| Ok(((), vec![inventory.create_api_key(api_key, audit)])))
+    }
+
+    async fn get_api_key(&self, key_id: &str) -> Result<Option<StoredApiKey>> {
+        let inventory = self
+            .inventory
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        Ok(inventory.get_api_key(key_id))
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>> {
+        let inventory = self
+            .inventory
+            .read()
+            .map_err(|_| anyhow!("Store lock poisoned"))?;
+        Ok(inventory.list_api_keys())
+    }
+
+    async fn delete_api_key(&self, key_id: &str, audit: &AuditContext) -> Result<()> {
+        self.change(|inventory| {
+            let event = inventory.delete_api_key(key_id, audit);
+            Ok(((), event.into_iter().collect()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{test_audit, test_server, TooManyModules};
+    use crate::validation::MAX_MODULES;
+    use homelab_api::audit_event::AuditAction;
+    use homelab_api::server_revision::RevisionAction;
+
+    #[tokio::test]
+    async fn update_server_rejects_deleted_servers() {
+        let store = MemoryStore::new();
+        let server = test_server("web-1", "/etc/nixos/web.nix");
+        store
+            .create_server(server.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+        store
+            .delete_server(&server.server_id, &test_audit(AuditAction::Delete), None)
+            .await
+            .unwrap();
+
+        let changes = UpdateServerRequest {
+            description: Some("Front end".to_string()),
+            ..Default::default()
+        };
+        let error = store
+            .update_server(
+                &server.server_id,
+                changes,
+                &test_audit(AuditAction::Update),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(error.is::<ServerDeleted>());
+        assert_eq!(
+            store
+                .get_server(&server.server_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .version,
+            2
+        );
+        assert_eq!(
+            store.list_revisions(&server.server_id).await.unwrap().len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn servers_are_found_by_config_path_including_the_trash() {
+        let store = MemoryStore::new();
+        let web = test_server("web-1", "/etc/nixos/web.nix");
+        let mut importer = test_server("web-2", "/etc/nixos/other.nix");
+        importer.modules = vec!["/etc/nixos/web.nix".to_string()];
+        let db = test_server("db-1", "/etc/nixos/db.nix");
+        for server in [&web, &importer, &db] {
+            store
+                .create_server(server.clone(), &test_audit(AuditAction::Create))
+                .await
+                .unwrap();
+        }
+        store
+            .delete_server(&web.server_id, &test_audit(AuditAction::Delete), None)
+            .await
+            .unwrap();
+
+        let found = store
+            .find_servers_by_config_path("/etc/nixos/web.nix")
+            .await
+            .unwrap();
+        let ids: Vec<&str> = found
+            .iter()
+            .map(|server| server.server_id.as_str())
+            .collect();
+        assert_eq!(ids, [web.server_id.as_str(), importer.server_id.as_str()]);
+        assert!(found[0].is_deleted());
+        assert!(store
+            .find_servers_by_config_path("/etc/nixos")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn purge_expired_removes_only_servers_deleted_long_enough_ago() {
+        let store = MemoryStore::new();
+        let mut servers = Vec::new();
+        for name in ["web-1", "web-2", "web-3"] {
+            let server = test_server(name, "/etc/nixos/web.nix");
+            store
+                .create_server(server.clone(), &test_audit(AuditAction::Create))
+                .await
+                .unwrap();
+            servers.push(server.server_id);
+        }
+        let long_ago = AuditContext {
+            at: Utc::now() - chrono::Duration::days(40),
+            ..test_audit(AuditAction::Delete)
+        };
+        store
+            .delete_server(&servers[0], &long_ago, None)
+            .await
+            .unwrap();
+        store
+            .delete_server(&servers[1], &test_audit(AuditAction::Delete), None)
+            .await
+            .unwrap();
+
+        let purge = test_audit(AuditAction::Purge);
+        let purged = store
+            .purge_expired(purge.at - chrono::Duration::days(30), &purge)
+            .await
+            .unwrap();
+        assert_eq!(purged, 1);
+        assert!(store.get_server(&servers[0]).await.unwrap().is_none());
+        assert!(store.get_server(&servers[1]).await.unwrap().is_some());
+        assert!(store.get_server(&servers[2]).await.unwrap().is_some());
+        let revisions = store.list_revisions(&servers[0]).await.unwrap();
+        assert_eq!(revisions.last().unwrap().action, RevisionAction::Purge);
+    }
+
+    #[tokio::test]
+    async fn update_server_limits_the_resulting_modules() {
+        let store = MemoryStore::new();
+        let mut server = test_server("web-1", "/etc/nixos/web.nix");
+        server.modules = (0..MAX_MODULES)
+            .map(|index| format!("/etc/nixos/module-{}.nix", index))
+            .collect();
+        store
+            .create_server(server.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        let add = UpdateServerRequest {
+            add_modules: vec!["/etc/nixos/extra.nix".to_string()],
+            ..Default::default()
+        };
+        let error = store
+            .update_server(
+                &server.server_id,
+                add.clone(),
+                &test_audit(AuditAction::Update),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(error.is::<TooManyModules>());
+        let stored = store.get_server(&server.server_id).await.unwrap().unwrap();
+        assert_eq!(stored.modules, server.modules);
+        assert_eq!(stored.version, 1);
+
+        // Swapping one module for another stays within the limit
+        let swap = UpdateServerRequest {
+            remove_modules: vec!["/etc/nixos/module-0.nix".to_string()],
+            ..add
+        };
+        let updated = store
+            .update_server(
+                &server.server_id,
+                swap,
+                &test_audit(AuditAction::Update),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.modules.len(), MAX_MODULES);
+        assert_eq!(updated.modules.last().unwrap(), "/etc/nixos/extra.nix");
+    }
+}
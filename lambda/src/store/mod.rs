@@ -0,0 +1,715 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use homelab_api::api_key::ApiKey;
+use homelab_api::audit_event::{AuditAction, AuditEvent, AuditRecord, AuditTarget};
+use homelab_api::config_file::ConfigFile;
+use homelab_api::server_config::{ServerConfig, UpdateServerRequest};
+use homelab_api::server_revision::ServerRevision;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::validation::MAX_MODULES;
+
+pub mod dynamodb;
+pub mod json_file;
+pub mod memory;
+pub mod sqlite;
+
+/// Days a deleted server stays in the trash unless `TRASH_RETENTION_DAYS` says otherwise.
+pub const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+/// A change about to be made: how it is recorded in the audit log, by whom,
+/// when, and through which request. Every mutating `ServerStore` method
+/// appends the matching `AuditEvent` in the same atomic write as the change.
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    pub action: AuditAction,
+    /// Caller making the change, also stamped onto servers as `created_by`/`updated_by`.
+    pub actor: String,
+    pub request_id: Option<String>,
+    pub client_request_id: Option<String>,
+    /// When the change happens; used as the server's `updated_at` or `deleted_at`.
+    pub at: DateTime<Utc>,
+}
+
+impl AuditContext {
+    /// The audit event for this change to the record `target_id`.
+    pub(crate) fn event(
+        &self,
+        target: AuditTarget,
+        target_id: &str,
+        before: Option<AuditRecord>,
+        after: Option<AuditRecord>,
+    ) -> AuditEvent {
+        AuditEvent {
+            event_id: Uuid::new_v4().to_string(),
+            recorded_at: self.at,
+            actor: self.actor.clone(),
+            action: self.action,
+            target,
+            target_id: target_id.to_string(),
+            request_id: self.request_id.clone(),
+            client_request_id: self.client_request_id.clone(),
+            before,
+            after,
+        }
+    }
+
+    /// The audit event for this change to a server.
+    pub(crate) fn server_event(
+        &self,
+        before: Option<&ServerConfig>,
+        after: Option<&ServerConfig>,
+    ) -> AuditEvent {
+        let server_id = before.or(after).map(|server| server.server_id.as_str());
+        self.event(
+            AuditTarget::Server,
+            server_id.unwrap_or_default(),
+            before.map(|server| AuditRecord::Server(Box::new(server.clone()))),
+            after.map(|server| AuditRecord::Server(Box::new(server.clone()))),
+        )
+    }
+}
+
+/// Parameters for a single page of `ServerStore::list_servers`.
+#[derive(Debug, Clone)]
+pub struct ListQuery {
+    pub limit: usize,
+    /// Opaque cursor returned as `next_cursor` by the previous page.
+    pub cursor: Option<String>,
+    /// Whether servers in the trash are listed too.
+    pub include_deleted: bool,
+    /// Only servers carrying all of these `key=value` labels.
+    pub labels: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerPage {
+    pub servers: Vec<ServerConfig>,
+    pub next_cursor: Option<String>,
+}
+
+/// Parameters for a single page of `ServerStore::list_audit_events`.
+#[derive(Debug, Clone)]
+pub struct AuditQuery {
+    /// Only events about this server (or config file or API key).
+    pub server_id: Option<String>,
+    /// Only events recorded at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    pub limit: usize,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditPage {
+    pub events: Vec<AuditEvent>,
+    pub next_cursor: Option<String>,
+}
+
+/// An API key together with the hash of its secret, as stored. The secret itself is never stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    /// Hex-encoded SHA-256 of the token's secret part.
+    pub secret_hash: String,
+}
+
+/// Returned (wrapped in `anyhow::Error`) when a cursor cannot be decoded.
+#[derive(Debug)]
+pub struct InvalidCursor;
+
+impl fmt::Display for InvalidCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid pagination cursor")
+    }
+}
+
+impl std::error::Error for InvalidCursor {}
+
+/// Returned (wrapped in `anyhow::Error`) when a create or rename would give
+/// two servers the same `server_name`.
+#[derive(Debug)]
+pub struct NameConflict {
+    pub server_name: String,
+    pub existing_server_id: String,
+}
+
+impl fmt::Display for NameConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Server name '{}' is already used by server {}",
+            self.server_name, self.existing_server_id
+        )
+    }
+}
+
+impl std::error::Error for NameConflict {}
+
+/// Returned (wrapped in `anyhow::Error`) when a conditional update or delete
+/// expected a different version than the one stored.
+#[derive(Debug)]
+pub struct VersionMismatch {
+    /// Version currently stored, or `None` if the server does not exist.
+    pub current_version: Option<u64>,
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.current_version {
+            Some(version) => write!(
+                f,
+                "Server has been modified; current version is {}",
+                version
+            ),
+            None => write!(f, "Server does not exist"),
+        }
+    }
+}
+
+impl std::error::Error for VersionMismatch {}
+
+/// Returned (wrapped in `anyhow::Error`) when updating a server that is in
+/// the trash; it has to be restored first.
+#[derive(Debug)]
+pub struct ServerDeleted {
+    pub server_id: String,
+}
+
+impl fmt::Display for ServerDeleted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Server {} is deleted", self.server_id)
+    }
+}
+
+impl std::error::Error for ServerDeleted {}
+
+/// Returned (wrapped in `anyhow::Error`) when a server would reference a
+/// config file that does not exist.
+#[derive(Debug)]
+pub struct UnknownConfigFile {
+    pub config_id: String,
+}
+
+impl fmt::Display for UnknownConfigFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Config file {} does not exist", self.config_id)
+    }
+}
+
+impl std::error::Error for UnknownConfigFile {}
+
+/// Returned (wrapped in `anyhow::Error`) when deleting a config file that
+/// servers outside the trash still reference, use the path of, or import.
+#[derive(Debug)]
+pub struct ConfigFileInUse {
+    pub config_id: String,
+    pub server_ids: Vec<String>,
+}
+
+impl fmt::Display for ConfigFileInUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Config file {} is still used by {} server(s)",
+            self.config_id,
+            self.server_ids.len()
+        )
+    }
+}
+
+impl std::error::Error for ConfigFileInUse {}
+
+/// Returned (wrapped in `anyhow::Error`) when a second config file would be
+/// created for the same path.
+#[derive(Debug)]
+pub struct ConfigPathConflict {
+    pub path: String,
+    pub existing_config_id: String,
+}
+
+impl fmt::Display for ConfigPathConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Path '{}' already belongs to config file {}",
+            self.path, self.existing_config_id
+        )
+    }
+}
+
+impl std::error::Error for ConfigPathConflict {}
+
+/// Returned (wrapped in `anyhow::Error`) when an update would leave a server
+/// importing more than `MAX_MODULES` modules.
+#[derive(Debug)]
+pub struct TooManyModules {
+    pub max: usize,
+}
+
+impl fmt::Display for TooManyModules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "A server can import at most {} modules", self.max)
+    }
+}
+
+impl std::error::Error for TooManyModules {}
+
+/// Fails with `VersionMismatch` unless `current` exists and matches `expected_version`
+/// (when one is given).
+pub(crate) fn check_version(
+    current: Option<&ServerConfig>,
+    expected_version: Option<u64>,
+) -> Result<()> {
+    match (current, expected_version) {
+        (Some(server), Some(expected)) if server.version != expected => Err(VersionMismatch {
+            current_version: Some(server.version),
+        }
+        .into()),
+        (None, Some(_)) => Err(VersionMismatch {
+            current_version: None,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Encodes a backend-specific position as an opaque, URL-safe cursor.
+pub(crate) fn encode_cursor<T: Serialize>(position: &T) -> Result<String> {
+    Ok(URL_SAFE_NO_PAD.encode(serde_json::to_vec(position)?))
+}
+
+pub(crate) fn decode_cursor<T: DeserializeOwned>(cursor: &str) -> Result<T> {
+    let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| InvalidCursor)?;
+    Ok(serde_json::from_slice(&bytes).map_err(|_| InvalidCursor)?)
+}
+
+/// Cursor position for backends that list servers in creation order.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CreationOrderPosition {
+    pub created_at: DateTime<Utc>,
+    pub server_id: String,
+}
+
+impl CreationOrderPosition {
+    pub fn of(server: &ServerConfig) -> Self {
+        Self {
+            created_at: server.created_at,
+            server_id: server.server_id.clone(),
+        }
+    }
+}
+
+/// Pages through an in-memory list of servers in `(created_at, server_id)` order.
+pub(crate) fn paginate(mut servers: Vec<ServerConfig>, query: &ListQuery) -> Result<ServerPage> {
+    servers.sort_by(|a, b| (a.created_at, &a.server_id).cmp(&(b.created_at, &b.server_id)));
+    if !query.include_deleted {
+        servers.retain(|server| !server.is_deleted());
+    }
+    servers.retain(|server| server.matches_labels(&query.labels));
+
+    if let Some(cursor) = &query.cursor {
+        let after: CreationOrderPosition = decode_cursor(cursor)?;
+        servers.retain(|server| {
+            (server.created_at, &server.server_id) > (after.created_at, &after.server_id)
+        });
+    }
+
+    let next_cursor = if servers.len() > query.limit {
+        servers.truncate(query.limit);
+        servers
+            .last()
+            .map(|last| encode_cursor(&CreationOrderPosition::of(last)))
+            .transpose()?
+    } else {
+        None
+    };
+
+    Ok(ServerPage {
+        servers,
+        next_cursor,
+    })
+}
+
+/// Cursor position in the audit log, which is ordered by `(recorded_at, event_id)`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct AuditPosition {
+    pub recorded_at: DateTime<Utc>,
+    pub event_id: String,
+}
+
+impl AuditPosition {
+    pub fn of(event: &AuditEvent) -> Self {
+        Self {
+            recorded_at: event.recorded_at,
+            event_id: event.event_id.clone(),
+        }
+    }
+}
+
+/// Filters and pages through an in-memory list of audit events, oldest first.
+pub(crate) fn paginate_audit(mut events: Vec<AuditEvent>, query: &AuditQuery) -> Result<AuditPage> {
+    events.sort_by(|a, b| (a.recorded_at, &a.event_id).cmp(&(b.recorded_at, &b.event_id)));
+    if let Some(server_id) = &query.server_id {
+        events.retain(|event| &event.target_id == server_id);
+    }
+    if let Some(since) = query.since {
+        events.retain(|event| event.recorded_at >= since);
+    }
+
+    if let Some(cursor) = &query.cursor {
+        let after: AuditPosition = decode_cursor(cursor)?;
+        events.retain(|event| {
+            (event.recorded_at, &event.event_id) > (after.recorded_at, &after.event_id)
+        });
+    }
+
+    let next_cursor = if events.len() > query.limit {
+        events.truncate(query.limit);
+        events
+            .last()
+            .map(|last| encode_cursor(&AuditPosition::of(last)))
+            .transpose()?
+    } else {
+        None
+    };
+
+    Ok(AuditPage {
+        events,
+        next_cursor,
+    })
+}
+
+/// Fails with `NameConflict` if a server other than `server_id` is already
+/// called `server_name`. Servers in the trash do not hold on to their names.
+/// Used by backends that hold every record in memory.
+pub(crate) fn ensure_name_available<'a>(
+    servers: impl IntoIterator<Item = &'a ServerConfig>,
+    server_name: &str,
+    server_id: &str,
+) -> Result<()> {
+    match servers.into_iter().find(|server| {
+        server.server_name == server_name && server.server_id != server_id && !server.is_deleted()
+    }) {
+        Some(existing) => Err(NameConflict {
+            server_name: server_name.to_string(),
+            existing_server_id: existing.server_id.clone(),
+        }
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Applies `changes` to a whole record in place. Fails with `TooManyModules`,
+/// leaving the record untouched, if the server would import too many modules.
+pub(crate) fn apply_changes(
+    server: &mut ServerConfig,
+    changes: UpdateServerRequest,
+    updated_at: DateTime<Utc>,
+    updated_by: &str,
+) -> Result<()> {
+    let mut modules = server.modules.clone();
+    changes.apply_modules(&mut modules);
+    if modules.len() > MAX_MODULES {
+        return Err(TooManyModules { max: MAX_MODULES }.into());
+    }
+    server.modules = modules;
+    if let Some(server_name) = changes.server_name {
+        server.server_name = server_name;
+    }
+    if let Some(config_path) = changes.config_file_path {
+        server.config_file_path = config_path;
+        // Handlers fill in the path of any config file or flake given, so a path on its own detaches the server
+        server.config_id = changes.config_id;
+        server.flake = changes.flake;
+    }
+    if let Some(description) = changes.description {
+        // An empty description clears it
+        server.description = Some(description).filter(|description| !description.is_empty());
+    }
+    if let Some(labels) = changes.labels {
+        server.labels = labels;
+    }
+    server.updated_at = updated_at;
+    server.updated_by = Some(updated_by.to_string());
+    server.version += 1;
+    Ok(())
+}
+
+/// Moves a whole record into the trash in place.
+pub(crate) fn mark_deleted(server: &mut ServerConfig, deleted_at: DateTime<Utc>, deleted_by: &str) {
+    server.deleted_at = Some(deleted_at);
+    server.updated_at = deleted_at;
+    server.updated_by = Some(deleted_by.to_string());
+    server.version += 1;
+}
+
+/// Takes a whole record back out of the trash in place.
+pub(crate) fn mark_restored(
+    server: &mut ServerConfig,
+    restored_at: DateTime<Utc>,
+    restored_by: &str,
+) {
+    server.deleted_at = None;
+    server.updated_at = restored_at;
+    server.updated_by = Some(restored_by.to_string());
+    server.version += 1;
+}
+
+/// Persistence backend used by the API handlers.
+///
+/// DynamoDB in AWS, SQLite or a JSON file on a self-hosted box, or an
+/// in-memory map in tests.
+///
+/// Every change to a server also records a `ServerRevision`, and every change
+/// to a server, config file or API key appends an `AuditEvent` built from the
+/// given `AuditContext`, in the same atomic write as the change itself.
+#[async_trait]
+pub trait ServerStore: Send + Sync {
+    /// Inserts a new server, failing with `NameConflict` if the name is taken
+    /// and with `UnknownConfigFile` if its `config_id` does not exist.
+    async fn create_server(&self, server: ServerConfig, audit: &AuditContext) -> Result<()>;
+
+    async fn get_server(&self, server_id: &str) -> Result<Option<ServerConfig>>;
+
+    /// Returns every server whose `server_name` matches exactly, including any in the trash.
+    async fn find_servers_by_name(&self, server_name: &str) -> Result<Vec<ServerConfig>>;
+
+    /// Returns every server whose `config_file_path` matches exactly or that
+    /// imports the path as a module, including any in the trash.
+    async fn find_servers_by_config_path(
+        &self,
+        config_file_path: &str,
+    ) -> Result<Vec<ServerConfig>>;
+
+    /// Returns up to `query.limit` servers starting after `query.cursor`.
+    async fn list_servers(&self, query: ListQuery) -> Result<ServerPage>;
+
+    /// Applies the provided fields to an existing server, bumps `updated_at` and
+    /// `version`, records the actor as `updated_by`, and returns the updated record.
+    ///
+    /// Fails with `VersionMismatch` if `expected_version` is given and differs
+    /// from the stored version, with `ServerDeleted` if the server is in the
+    /// trash, with `NameConflict` if a rename would duplicate another server's
+    /// name, and with `UnknownConfigFile` if a new `config_id` does not exist.
+    async fn update_server(
+        &self,
+        server_id: &str,
+        changes: UpdateServerRequest,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<ServerConfig>;
+
+    /// Moves a server into the trash by setting `deleted_at`, which also bumps
+    /// `version` and frees its name and config file. Does nothing if the server is missing or
+    /// already deleted, but fails with `VersionMismatch` if `expected_version`
+    /// is given and does not match.
+    async fn delete_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<()>;
+
+    /// Takes a server back out of the trash and returns it. Fails with
+    /// `NameConflict` if another server has taken its name in the meantime; if
+    /// its config file has been deleted, the server keeps the path but loses the `config_id`.
+    async fn restore_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<ServerConfig>;
+
+    /// Removes a server for good, whether or not it is in the trash. Its
+    /// revisions are kept. Does nothing if the server is missing.
+    async fn purge_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<()>;
+
+    /// Purges every server that was moved to the trash before `deleted_before`
+    /// and returns how many were removed.
+    async fn purge_expired(
+        &self,
+        deleted_before: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> Result<usize>;
+
+    /// Returns every revision recorded for a server, oldest first. Revisions
+    /// outlive the server, so this still works after it has been deleted.
+    async fn list_revisions(&self, server_id: &str) -> Result<Vec<ServerRevision>>;
+
+    /// Returns up to `query.limit` audit events, oldest first, starting after `query.cursor`.
+    async fn list_audit_events(&self, query: AuditQuery) -> Result<AuditPage>;
+
+    /// Inserts a new config file, failing with `ConfigPathConflict` if its path already has one.
+    async fn create_config_file(&self, config_file: ConfigFile, audit: &AuditContext)
+        -> Result<()>;
+
+    async fn get_config_file(&self, config_id: &str) -> Result<Option<ConfigFile>>;
+
+    async fn find_config_file_by_path(&self, path: &str) -> Result<Option<ConfigFile>>;
+
+    /// Returns every config file, ordered by path.
+    async fn list_config_files(&self) -> Result<Vec<ConfigFile>>;
+
+    /// Removes a config file, failing with `ConfigFileInUse` while any server
+    /// outside the trash references it, has its path as `config_file_path`
+    /// (with or without a `config_id`), or imports its path as a module. Does
+    /// nothing if it does not exist.
+    async fn delete_config_file(&self, config_id: &str, audit: &AuditContext) -> Result<()>;
+
+    /// Inserts a new API key.
+    async fn create_api_key(&self, api_key: StoredApiKey, audit: &AuditContext) -> Result<()>;
+
+    async fn get_api_key(&self, key_id: &str) -> Result<Option<StoredApiKey>>;
+
+    /// Returns every API key, oldest first.
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>>;
+
+    /// Removes an API key. Does nothing if it does not exist.
+    async fn delete_api_key(&self, key_id: &str, audit: &AuditContext) -> Result<()>;
+}
+
+/// A new server at version 1, as `handle_add_server` would store it.
+#[cfg(test)]
+pub(crate) fn test_server(server_name: &str, config_file_path: &str) -> ServerConfig {
+    let now = Utc::now();
+    ServerConfig {
+        server_id: uuid::Uuid::new_v4().to_string(),
+        server_name: server_name.to_string(),
+        config_file_path: config_file_path.to_string(),
+        config_id: None,
+        flake: None,
+        description: None,
+        created_at: now,
+        updated_at: now,
+        created_by: Some("test".to_string()),
+        updated_by: Some("test".to_string()),
+        version: 1,
+        deleted_at: None,
+        labels: Default::default(),
+        modules: Vec::new(),
+    }
+}
+
+/// A change made now by `test`, outside of any request.
+#[cfg(test)]
+pub(crate) fn test_audit(action: AuditAction) -> AuditContext {
+    AuditContext {
+        action,
+        actor: "test".to_string(),
+        request_id: None,
+        client_request_id: None,
+        at: Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(limit: usize, cursor: Option<String>) -> ListQuery {
+        ListQuery {
+            limit,
+            cursor,
+            include_deleted: false,
+            labels: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn check_version_compares_the_stored_version() {
+        let server = test_server("web-1", "/etc/nixos/web.nix");
+        assert!(check_version(Some(&server), None).is_ok());
+        assert!(check_version(Some(&server), Some(1)).is_ok());
+
+        let error = check_version(Some(&server), Some(2)).unwrap_err();
+        let mismatch = error.downcast_ref::<VersionMismatch>().unwrap();
+        assert_eq!(mismatch.current_version, Some(1));
+        let error = check_version(None, Some(1)).unwrap_err();
+        assert_eq!(
+            error
+                .downcast_ref::<VersionMismatch>()
+                .unwrap()
+                .current_version,
+            None
+        );
+    }
+
+    #[test]
+    fn apply_changes_bumps_the_version() {
+        let mut server = test_server("web-1", "/etc/nixos/web.nix");
+        let changes = UpdateServerRequest {
+            description: Some("Front end".to_string()),
+            ..Default::default()
+        };
+        apply_changes(&mut server, changes, Utc::now(), "alice").unwrap();
+        assert_eq!(server.version, 2);
+        assert_eq!(server.updated_by.as_deref(), Some("alice"));
+        assert_eq!(server.description.as_deref(), Some("Front end"));
+    }
+
+    #[test]
+    fn names_are_only_taken_by_other_live_servers() {
+        let web = test_server("web-1", "/etc/nixos/web.nix");
+        let mut deleted = test_server("db-1", "/etc/nixos/db.nix");
+        mark_deleted(&mut deleted, Utc::now(), "test");
+        let servers = [web.clone(), deleted];
+
+        let error = ensure_name_available(&servers, "web-1", "other").unwrap_err();
+        let conflict = error.downcast_ref::<NameConflict>().unwrap();
+        assert_eq!(conflict.existing_server_id, web.server_id);
+        assert!(ensure_name_available(&servers, "web-1", &web.server_id).is_ok());
+        assert!(ensure_name_available(&servers, "db-1", "other").is_ok());
+        assert!(ensure_name_available(&servers, "web-2", "other").is_ok());
+    }
+
+    #[test]
+    fn paginate_pages_in_creation_order() {
+        let mut servers: Vec<ServerConfig> = ["web-1", "web-2", "web-3", "web-4", "web-5"]
+            .into_iter()
+            .map(|name| test_server(name, "/etc/nixos/web.nix"))
+            .collect();
+        // Servers created at the same moment are ordered by ID
+        let created_at = servers[0].created_at;
+        servers[1].created_at = created_at;
+        servers[2].created_at = created_at;
+        mark_deleted(&mut servers[3], Utc::now(), "test");
+        let mut expected: Vec<String> = servers
+            .iter()
+            .filter(|server| !server.is_deleted())
+            .map(|server| server.server_id.clone())
+            .collect();
+        expected[..3].sort();
+
+        let mut listed = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = paginate(servers.clone(), &query(2, cursor)).unwrap();
+            assert!(page.servers.len() <= 2);
+            listed.extend(page.servers.into_iter().map(|server| server.server_id));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(listed, expected);
+    }
+
+    #[test]
+    fn paginate_rejects_garbage_cursors() {
+        let servers = vec![test_server("web-1", "/etc/nixos/web.nix")];
+        for cursor in ["garbage!", "bm90IGpzb24"] {
+            let error = paginate(servers.clone(), &query(2, Some(cursor.to_string()))).unwrap_err();
+            assert!(error.is::<InvalidCursor>());
+        }
+    }
+}
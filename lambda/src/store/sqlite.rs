@@ -0,0 +1,1605 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use homelab_api::api_key::{ApiKey, Role};
+use homelab_api::audit_event::{AuditEvent, AuditRecord, AuditTarget};
+use homelab_api::config_file::ConfigFile;
+use homelab_api::flake_ref::FlakeRef;
+use homelab_api::server_config::{Labels, ServerConfig, UpdateServerRequest};
+use homelab_api::server_revision::ServerRevision;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use super::{
+    apply_changes, check_version, decode_cursor, encode_cursor, mark_deleted, mark_restored,
+    AuditContext, AuditPage, AuditPosition, AuditQuery, ConfigFileInUse, ConfigPathConflict,
+    CreationOrderPosition, ListQuery, NameConflict, ServerDeleted, ServerPage, ServerStore,
+    StoredApiKey, UnknownConfigFile, VersionMismatch,
+};
+
+/// Schema migrations, applied in order. The index of the last applied
+/// migration is tracked in SQLite's `user_version` pragma, so entries must
+/// only ever be appended.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE servers (
+        server_id        TEXT PRIMARY KEY NOT NULL,
+        server_name      TEXT NOT NULL,
+        config_file_path TEXT NOT NULL,
+        description      TEXT,
+        created_at       TEXT NOT NULL,
+        updated_at       TEXT NOT NULL
+    );",
+    "CREATE INDEX servers_server_name ON servers (server_name);",
+    // Servers sharing a name with an older one get their ID prefix appended, so the index can be built
+    "UPDATE servers SET server_name = server_name || '-' || substr(server_id, 1, 8)
+     WHERE EXISTS (
+         SELECT 1 FROM servers AS older
+         WHERE older.server_name = servers.server_name
+           AND (older.created_at, older.server_id) < (servers.created_at, servers.server_id));
+     DROP INDEX servers_server_name;
+     CREATE UNIQUE INDEX servers_server_name ON servers (server_name);",
+    "ALTER TABLE servers ADD COLUMN version INTEGER NOT NULL DEFAULT 0;",
+    // `server` holds the JSON snapshot of the record at that revision
+    "CREATE TABLE server_revisions (
+        server_id   TEXT NOT NULL,
+        revision    INTEGER NOT NULL,
+        action      TEXT NOT NULL,
+        recorded_at TEXT NOT NULL,
+        server      TEXT NOT NULL,
+        PRIMARY KEY (server_id, revision)
+    );",
+    // Servers in the trash give up their name, so only live servers need unique names
+    "ALTER TABLE servers ADD COLUMN deleted_at TEXT;
+     DROP INDEX servers_server_name;
+     CREATE UNIQUE INDEX servers_server_name ON servers (server_name) WHERE deleted_at IS NULL;",
+    // `before` and `after` hold JSON snapshots of the server
+    "CREATE TABLE audit_events (
+        event_id    TEXT PRIMARY KEY NOT NULL,
+        recorded_at TEXT NOT NULL,
+        actor       TEXT NOT NULL,
+        action      TEXT NOT NULL,
+        server_id   TEXT NOT NULL,
+        request_id  TEXT,
+        before      TEXT,
+        after       TEXT
+    );
+    CREATE INDEX audit_events_recorded_at ON audit_events (recorded_at, event_id);
+    CREATE INDEX audit_events_server_id ON audit_events (server_id, recorded_at, event_id);",
+    // JSON object of `key: value` labels, filtered with `json_each`
+    "ALTER TABLE servers ADD COLUMN labels TEXT NOT NULL DEFAULT '{}';",
+    "CREATE INDEX servers_config_file_path ON servers (config_file_path);",
+    "CREATE TABLE config_files (
+        config_id   TEXT PRIMARY KEY NOT NULL,
+        path        TEXT NOT NULL UNIQUE,
+        description TEXT,
+        owner       TEXT,
+        created_at  TEXT NOT NULL,
+        updated_at  TEXT NOT NULL
+    );
+    ALTER TABLE servers ADD COLUMN config_id TEXT;
+    CREATE INDEX servers_config_id ON servers (config_id);",
+    // JSON array of module paths, in import order
+    "ALTER TABLE servers ADD COLUMN modules TEXT NOT NULL DEFAULT '[]';",
+    // JSON object of the flake reference, or NULL for servers configured by path
+    "ALTER TABLE servers ADD COLUMN flake TEXT;",
+    // `secret_hash` is the hex SHA-256 of the token's secret; the secret itself is never stored
+    "CREATE TABLE api_keys (
+        key_id      TEXT PRIMARY KEY NOT NULL,
+        name        TEXT NOT NULL,
+        role        TEXT NOT NULL DEFAULT 'read_only',
+        secret_hash TEXT NOT NULL,
+        created_at  TEXT NOT NULL,
+        created_by  TEXT NOT NULL
+    );",
+    // Callers as recorded in the audit log; NULL for servers changed before they were recorded
+    "ALTER TABLE servers ADD COLUMN created_by TEXT;
+     ALTER TABLE servers ADD COLUMN updated_by TEXT;",
+    // One row per module a server imports, so servers can be found by module path
+    "CREATE TABLE server_modules (
+        server_id TEXT NOT NULL,
+        module    TEXT NOT NULL,
+        PRIMARY KEY (server_id, module)
+    );
+    CREATE INDEX server_modules_module ON server_modules (module);
+    INSERT OR IGNORE INTO server_modules (server_id, module)
+        SELECT servers.server_id, modules.value FROM servers, json_each(servers.modules) AS modules;",
+    // Audit events also cover config files and API keys; `before` and `after` snapshot whichever `target` is
+    "ALTER TABLE audit_events RENAME COLUMN server_id TO target_id;
+     ALTER TABLE audit_events ADD COLUMN target TEXT NOT NULL DEFAULT 'server';
+     DROP INDEX audit_events_server_id;
+     CREATE INDEX audit_events_target_id ON audit_events (target_id, recorded_at, event_id);",
+    // The `X-Request-Id` a client sent, kept apart from the request ID the service assigned
+    "ALTER TABLE audit_events ADD COLUMN client_request_id TEXT;",
+];
+
+const SERVER_COLUMNS: &str =
+    "server_id, server_name, config_file_path, description, created_at, updated_at, \
+     version, deleted_at, labels, config_id, modules, flake, created_by, updated_by";
+
+const CONFIG_FILE_COLUMNS: &str = "config_id, path, description, owner, created_at, updated_at";
+
+const API_KEY_COLUMNS: &str = "key_id, name, role, created_at, created_by, secret_hash";
+
+const AUDIT_EVENT_COLUMNS: &str =
+    "event_id, recorded_at, actor, action, target, target_id, request_id, before, after, \
+     client_request_id";
+
+/// Store backed by a local SQLite database file.
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Opens (or creates) the database at `path` and brings its schema up to date.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut conn = Connection::open(path)?;
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Runs `f` against the connection on the blocking thread pool.
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().map_err(|_| anyhow!("Store lock poisoned"))?;
+            f(&mut conn)
+        })
+        .await?
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let current: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)
+            .map_err(|e| anyhow!("SQLite migration {} failed: {}", index + 1, e))?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+        tracing::info!("Applied SQLite migration {}", index + 1);
+    }
+
+    Ok(())
+}
+
+/// Fixed-width timestamps so that `ORDER BY created_at` sorts chronologically.
+fn format_timestamp(ts: &DateTime<Utc>) -> String {
+    ts.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+fn parse_timestamp(value: String) -> rusqlite::Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&value)
+        .map(|ts| ts.with_timezone(&Utc))
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+        })
+}
+
+/// Fails with `NameConflict` if a server other than `server_id` is already called `server_name`.
+fn ensure_name_available(conn: &Connection, server_name: &str, server_id: &str) -> Result<()> {
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT server_id FROM servers WHERE server_name = ?1 AND server_id != ?2 AND deleted_at IS NULL",
+            params![server_name, server_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    match existing {
+        Some(existing_server_id) => Err(NameConflict {
+            server_name: server_name.to_string(),
+            existing_server_id,
+        }
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Fails with `UnknownConfigFile` if `config_id` is set but names no config file.
+fn ensure_config_file_exists(conn: &Connection, config_id: Option<&str>) -> Result<()> {
+    let Some(config_id) = config_id else {
+        return Ok(());
+    };
+    if select_config_file(conn, config_id)?.is_none() {
+        return Err(UnknownConfigFile {
+            config_id: config_id.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn parse_labels(value: String) -> rusqlite::Result<Labels> {
+    serde_json::from_str(&value).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+fn parse_flake(value: Option<String>) -> rusqlite::Result<Option<FlakeRef>> {
+    value
+        .map(|value| serde_json::from_str(&value))
+        .transpose()
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+        })
+}
+
+fn parse_modules(value: String) -> rusqlite::Result<Vec<String>> {
+    serde_json::from_str(&value).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+fn server_from_row(row: &Row) -> rusqlite::Result<ServerConfig> {
+    Ok(ServerConfig {
+        server_id: row.get(0)?,
+        server_name: row.get(1)?,
+        config_file_path: row.get(2)?,
+        description: row.get(3)?,
+        created_at: parse_timestamp(row.get(4)?)?,
+        updated_at: parse_timestamp(row.get(5)?)?,
+        version: row.get(6)?,
+        deleted_at: row
+            .get::<_, Option<String>>(7)?
+            .map(parse_timestamp)
+            .transpose()?,
+        labels: parse_labels(row.get(8)?)?,
+        config_id: row.get(9)?,
+        modules: parse_modules(row.get(10)?)?,
+        flake: parse_flake(row.get(11)?)?,
+        created_by: row.get(12)?,
+        updated_by: row.get(13)?,
+    })
+}
+
+fn config_file_from_row(row: &Row) -> rusqlite::Result<ConfigFile> {
+    Ok(ConfigFile {
+        config_id: row.get(0)?,
+        path: row.get(1)?,
+        description: row.get(2)?,
+        owner: row.get(3)?,
+        created_at: parse_timestamp(row.get(4)?)?,
+        updated_at: parse_timestamp(row.get(5)?)?,
+    })
+}
+
+fn parse_role(value: String) -> rusqlite::Result<Role> {
+    value.parse().map_err(|e: anyhow::Error| {
+        rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+fn api_key_from_row(row: &Row) -> rusqlite::Result<StoredApiKey> {
+    Ok(StoredApiKey {
+        key: ApiKey {
+            key_id: row.get(0)?,
+            name: row.get(1)?,
+            role: parse_role(row.get(2)?)?,
+            created_at: parse_timestamp(row.get(3)?)?,
+            created_by: row.get(4)?,
+        },
+        secret_hash: row.get(5)?,
+    })
+}
+
+fn select_config_file(conn: &Connection, config_id: &str) -> Result<Option<ConfigFile>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM config_files WHERE config_id = ?1",
+                CONFIG_FILE_COLUMNS
+            ),
+            params![config_id],
+            config_file_from_row,
+        )
+        .optional()?)
+}
+
+fn select_config_file_by_path(conn: &Connection, path: &str) -> Result<Option<ConfigFile>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM config_files WHERE path = ?1",
+                CONFIG_FILE_COLUMNS
+            ),
+            params![path],
+            config_file_from_row,
+        )
+        .optional()?)
+}
+
+fn select_server(conn: &Connection, server_id: &str) -> Result<Option<ServerConfig>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM servers WHERE server_id = ?1",
+                SERVER_COLUMNS
+            ),
+            params![server_id],
+            server_from_row,
+        )
+        .optional()?)
+}
+
+/// Overwrites every column of an existing server with `server`, provided the
+/// stored row is still at `current_version`.
+fn write_server(conn: &Connection, server: &ServerConfig, current_version: u64) -> Result<()> {
+    let written = conn.execute(
+        "UPDATE servers SET
+            server_name = ?2,
+            config_file_path = ?3,
+            description = ?4,
+            created_at = ?5,
+            updated_at = ?6,
+            version = ?7,
+            deleted_at = ?8,
+            labels = ?9,
+            config_id = ?10,
+            modules = ?11,
+            flake = ?12,
+            created_by = ?13,
+            updated_by = ?14
+         WHERE server_id = ?1 AND version = ?15",
+        params![
+            server.server_id,
+            server.server_name,
+            server.config_file_path,
+            server.description,
+            format_timestamp(&server.created_at),
+            format_timestamp(&server.updated_at),
+            server.version,
+            server.deleted_at.as_ref().map(format_timestamp),
+            serde_json::to_string(&server.labels)?,
+            server.config_id,
+            serde_json::to_string(&server.modules)?,
+            server
+                .flake
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            server.created_by,
+            server.updated_by,
+            current_version,
+        ],
+    )?;
+    if written == 0 {
+        let current_version = select_server(conn, &server.server_id)?.map(|server| server.version);
+        return Err(VersionMismatch { current_version }.into());
+    }
+    write_modules(conn, server)
+}
+
+/// Replaces the `server_modules` rows of `server` with its current modules.
+fn write_modules(conn: &Connection, server: &ServerConfig) -> Result<()> {
+    conn.execute(
+        "DELETE FROM server_modules WHERE server_id = ?1",
+        params![server.server_id],
+    )?;
+    let mut stmt =
+        conn.prepare("INSERT OR IGNORE INTO server_modules (server_id, module) VALUES (?1, ?2)")?;
+    for module in &server.modules {
+        stmt.execute(params![server.server_id, module])?;
+    }
+    Ok(())
+}
+
+/// Deletes a server row and records its purge, inside the caller's transaction.
+fn purge(conn: &Connection, server: &ServerConfig, audit: &AuditContext) -> Result<()> {
+    conn.execute(
+        "DELETE FROM servers WHERE server_id = ?1",
+        params![server.server_id],
+    )?;
+    conn.execute(
+        "DELETE FROM server_modules WHERE server_id = ?1",
+        params![server.server_id],
+    )?;
+    insert_revision(conn, &ServerRevision::purged(server, audit.at))?;
+    insert_audit_event(conn, &audit.server_event(Some(server), None))
+}
+
+fn insert_revision(conn: &Connection, revision: &ServerRevision) -> Result<()> {
+    conn.execute(
+        "INSERT INTO server_revisions (server_id, revision, action, recorded_at, server)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            revision.server_id,
+            revision.revision,
+            revision.action.as_str(),
+            format_timestamp(&revision.recorded_at),
+            serde_json::to_string(&revision.server)?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn revision_from_row(row: &Row) -> Result<ServerRevision> {
+    let action: String = row.get(2)?;
+    let server: String = row.get(4)?;
+    Ok(ServerRevision {
+        server_id: row.get(0)?,
+        revision: row.get(1)?,
+        action: action.parse()?,
+        recorded_at: parse_timestamp(row.get(3)?)?,
+        server: serde_json::from_str(&server)?,
+    })
+}
+
+fn insert_audit_event(conn: &Connection, event: &AuditEvent) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO audit_events ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            AUDIT_EVENT_COLUMNS
+        ),
+        params![
+            event.event_id,
+            format_timestamp(&event.recorded_at),
+            event.actor,
+            event.action.as_str(),
+            event.target.as_str(),
+            event.target_id,
+            event.request_id,
+            event
+                .before
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            event
+                .after
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            event.client_request_id,
+        ],
+    )?;
+    Ok(())
+}
+
+fn audit_event_from_row(row: &Row) -> Result<AuditEvent> {
+    let action: String = row.get(3)?;
+    let target: String = row.get(4)?;
+    let before: Option<String> = row.get(7)?;
+    let after: Option<String> = row.get(8)?;
+    Ok(AuditEvent {
+        event_id: row.get(0)?,
+        recorded_at: parse_timestamp(row.get(1)?)?,
+        actor: row.get(2)?,
+        action: action.parse()?,
+        target: target.parse()?,
+        target_id: row.get(5)?,
+        request_id: row.get(6)?,
+        client_request_id: row.get(9)?,
+        before: before.as_deref().map(serde_json::from_str).transpose()?,
+        after: after.as_deref().map(serde_json::from_str).transpose()?,
+    })
+}
+
+#[async_trait]
+impl ServerStore for SqliteStore {
+    async fn create_server(&self, server: ServerConfig, audit: &AuditContext) -> Result<()> {
+        let audit = audit.clone();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            ensure_name_available(&tx, &server.server_name, &server.server_id)?;
+            ensure_config_file_exists(&tx, server.config_id.as_deref())?;
+            tx.execute(
+                &format!(
+                    "INSERT INTO servers ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                    SERVER_COLUMNS
+                ),
+                params![
+                    server.server_id,
+                    server.server_name,
+                    server.config_file_path,
+                    server.description,
+                    format_timestamp(&server.created_at),
+                    format_timestamp(&server.updated_at),
+                    server.version,
+                    server.deleted_at.as_ref().map(format_timestamp),
+                    serde_json::to_string(&server.labels)?,
+                    server.config_id,
+                    serde_json::to_string(&server.modules)?,
+                    server.flake.as_ref().map(serde_json::to_string).transpose()?,
+                    server.created_by,
+                    server.updated_by,
+                ],
+            )?;
+            write_modules(&tx, &server)?;
+            insert_revision(&tx, &ServerRevision::created(&server))?;
+            insert_audit_event(&tx, &audit.server_event(None, Some(&server)))?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_server(&self, server_id: &str) -> Result<Option<ServerConfig>> {
+        let server_id = server_id.to_string();
+        self.with_conn(move |conn| select_server(conn, &server_id))
+            .await
+    }
+
+    async fn find_servers_by_name(&self, server_name: &str) -> Result<Vec<ServerConfig>> {
+        let server_name = server_name.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM servers WHERE server_name = ?1 ORDER BY created_at",
+                SERVER_COLUMNS
+            ))?;
+            let servers = stmt
+                .query_map(params![server_name], server_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(servers)
+        })
+        .await
+    }
+
+    async fn find_servers_by_config_path(
+        &self,
+        config_file_path: &str,
+    ) -> Result<Vec<ServerConfig>> {
+        let config_file_path = config_file_path.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM servers
+                 WHERE config_file_path = ?1
+                    OR server_id IN (SELECT server_id FROM server_modules WHERE module = ?1)
+                 ORDER BY created_at, server_id",
+                SERVER_COLUMNS
+            ))?;
+            let servers = stmt
+                .query_map(params![config_file_path], server_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(servers)
+        })
+        .await
+    }
+
+    async fn list_servers(&self, query: ListQuery) -> Result<ServerPage> {
+        let after = query
+            .cursor
+            .as_deref()
+            .map(decode_cursor::<CreationOrderPosition>)
+            .transpose()?;
+
+        self.with_conn(move |conn| {
+            let (after_created_at, after_server_id) = match &after {
+                Some(position) => (format_timestamp(&position.created_at), position.server_id.clone()),
+                None => (String::new(), String::new()),
+            };
+
+            // One condition per label selector, binding its key and value after the fixed parameters
+            let mut label_conditions = String::new();
+            let mut values: Vec<Value> = vec![
+                Value::Text(after_created_at),
+                Value::Text(after_server_id),
+                Value::Integer(query.limit as i64 + 1),
+                Value::Integer(query.include_deleted.into()),
+            ];
+            for (key, value) in query.labels {
+                label_conditions.push_str(&format!(
+                    " AND EXISTS (SELECT 1 FROM json_each(servers.labels) WHERE key = ?{} AND value = ?{})",
+                    values.len() + 1,
+                    values.len() + 2
+                ));
+                values.push(Value::Text(key));
+                values.push(Value::Text(value));
+            }
+
+            // Fetch one extra row to find out whether another page follows
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM servers
+                 WHERE (created_at, server_id) > (?1, ?2) AND (?4 OR deleted_at IS NULL){}
+                 ORDER BY created_at, server_id
+                 LIMIT ?3",
+                SERVER_COLUMNS, label_conditions
+            ))?;
+            let mut servers = stmt
+                .query_map(params_from_iter(values), server_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            let next_cursor = if servers.len() > query.limit {
+                servers.truncate(query.limit);
+                servers.last().map(|last| encode_cursor(&CreationOrderPosition::of(last))).transpose()?
+            } else {
+                None
+            };
+
+            Ok(ServerPage { servers, next_cursor })
+        })
+        .await
+    }
+
+    async fn update_server(
+        &self,
+        server_id: &str,
+        changes: UpdateServerRequest,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<ServerConfig> {
+        let server_id = server_id.to_string();
+        let audit = audit.clone();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let current = select_server(&tx, &server_id)?;
+            check_version(current.as_ref(), expected_version)?;
+            let mut server = match current {
+                Some(server) if server.is_deleted() => {
+                    return Err(ServerDeleted { server_id }.into())
+                }
+                Some(server) => server,
+                None => return Err(anyhow!("Server {} does not exist", server_id)),
+            };
+            if let Some(server_name) = &changes.server_name {
+                ensure_name_available(&tx, server_name, &server_id)?;
+            }
+            ensure_config_file_exists(&tx, changes.config_id.as_deref())?;
+
+            let before = server.clone();
+            apply_changes(&mut server, changes, audit.at, &audit.actor)?;
+            write_server(&tx, &server, before.version)?;
+            insert_revision(&tx, &ServerRevision::updated(&server))?;
+            insert_audit_event(&tx, &audit.server_event(Some(&before), Some(&server)))?;
+            tx.commit()?;
+            Ok(server)
+        })
+        .await
+    }
+
+    async fn delete_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<()> {
+        let server_id = server_id.to_string();
+        let audit = audit.clone();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let current = select_server(&tx, &server_id)?;
+            check_version(current.as_ref(), expected_version)?;
+            if let Some(mut server) = current.filter(|server| !server.is_deleted()) {
+                let before = server.clone();
+                mark_deleted(&mut server, audit.at, &audit.actor);
+                write_server(&tx, &server, before.version)?;
+                insert_revision(&tx, &ServerRevision::deleted(&server))?;
+                insert_audit_event(&tx, &audit.server_event(Some(&before), Some(&server)))?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn restore_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<ServerConfig> {
+        let server_id = server_id.to_string();
+        let audit = audit.clone();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let current = select_server(&tx, &server_id)?;
+            check_version(current.as_ref(), expected_version)?;
+            let mut server = match current {
+                Some(server) if server.is_deleted() => server,
+                Some(_) => return Err(anyhow!("Server {} is not deleted", server_id)),
+                None => return Err(anyhow!("Server {} does not exist", server_id)),
+            };
+            ensure_name_available(&tx, &server.server_name, &server_id)?;
+
+            let before = server.clone();
+            mark_restored(&mut server, audit.at, &audit.actor);
+            // The config file may have been deleted while the server was in the trash
+            if let Some(config_id) = server.config_id.clone() {
+                if select_config_file(&tx, &config_id)?.is_none() {
+                    server.config_id = None;
+                }
+            }
+            write_server(&tx, &server, before.version)?;
+            insert_revision(&tx, &ServerRevision::restored(&server))?;
+            insert_audit_event(&tx, &audit.server_event(Some(&before), Some(&server)))?;
+            tx.commit()?;
+            Ok(server)
+        })
+        .await
+    }
+
+    async fn purge_server(
+        &self,
+        server_id: &str,
+        audit: &AuditContext,
+        expected_version: Option<u64>,
+    ) -> Result<()> {
+        let server_id = server_id.to_string();
+        let audit = audit.clone();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let current = select_server(&tx, &server_id)?;
+            check_version(current.as_ref(), expected_version)?;
+            if let Some(server) = current {
+                purge(&tx, &server, &audit)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn purge_expired(
+        &self,
+        deleted_before: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> Result<usize> {
+        let audit = audit.clone();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let expired = {
+                let mut stmt = tx.prepare(&format!(
+                    "SELECT {} FROM servers WHERE deleted_at IS NOT NULL AND deleted_at < ?1",
+                    SERVER_COLUMNS
+                ))?;
+                let servers = stmt
+                    .query_map(params![format_timestamp(&deleted_before)], server_from_row)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                servers
+            };
+
+            for server in &expired {
+                purge(&tx, server, &audit)?;
+            }
+            tx.commit()?;
+            Ok(expired.len())
+        })
+        .await
+    }
+
+    async fn list_revisions(&self, server_id: &str) -> Result<Vec<ServerRevision>> {
+        let server_id = server_id.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT server_id, revision, action, recorded_at, server FROM server_revisions
+                 WHERE server_id = ?1
+                 ORDER BY revision",
+            )?;
+            let mut rows = stmt.query(params![server_id])?;
+            let mut revisions = Vec::new();
+            while let Some(row) = rows.next()? {
+                revisions.push(revision_from_row(row)?);
+            }
+            Ok(revisions)
+        })
+        .await
+    }
+
+    async fn list_audit_events(&self, query: AuditQuery) -> Result<AuditPage> {
+        let after = query
+            .cursor
+            .as_deref()
+            .map(decode_cursor::<AuditPosition>)
+            .transpose()?;
+
+        self.with_conn(move |conn| {
+            let (after_recorded_at, after_event_id) = match &after {
+                Some(position) => (
+                    format_timestamp(&position.recorded_at),
+                    position.event_id.clone(),
+                ),
+                None => (String::new(), String::new()),
+            };
+
+            // Fetch one extra row to find out whether another page follows
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM audit_events
+                 WHERE (?1 IS NULL OR target_id = ?1)
+                   AND (?2 IS NULL OR recorded_at >= ?2)
+                   AND (recorded_at, event_id) > (?3, ?4)
+                 ORDER BY recorded_at, event_id
+                 LIMIT ?5",
+                AUDIT_EVENT_COLUMNS
+            ))?;
+            let mut rows = stmt.query(params![
+                query.server_id,
+                query.since.as_ref().map(format_timestamp),
+                after_recorded_at,
+                after_event_id,
+                query.limit as i64 + 1,
+            ])?;
+            let mut events = Vec::new();
+            while let Some(row) = rows.next()? {
+                events.push(audit_event_from_row(row)?);
+            }
+
+            let next_cursor = if events.len() > query.limit {
+                events.truncate(query.limit);
+                events
+                    .last()
+                    .map(|last| encode_cursor(&AuditPosition::of(last)))
+                    .transpose()?
+            } else {
+                None
+            };
+
+            Ok(AuditPage {
+                events,
+                next_cursor,
+            })
+        })
+        .await
+    }
+
+    async fn create_config_file(
+        &self,
+        config_file: ConfigFile,
+        audit: &AuditContext,
+    ) -> Result<()> {
+        let audit = audit.clone();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            if let Some(existing) = select_config_file_by_path(&tx, &config_file.path)? {
+                return Err(ConfigPathConflict {
+                    path: config_file.path,
+                    existing_config_id: existing.config_id,
+                }
+                .into());
+            }
+            tx.execute(
+                &format!(
+                    "INSERT INTO config_files ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    CONFIG_FILE_COLUMNS
+                ),
+                params![
+                    config_file.config_id,
+                    config_file.path,
+                    config_file.description,
+                    config_file.owner,
+                    format_timestamp(&config_file.created_at),
+                    format_timestamp(&config_file.updated_at),
+                ],
+            )?;
+            let event = audit.event(
+                AuditTarget::ConfigFile,
+                &config_file.config_id,
+                None,
+                Some(AuditRecord::ConfigFile(config_file.clone())),
+            );
+            insert_audit_event(&tx, &event)?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_config_file(&self, config_id: &str) -> Result<Option<ConfigFile>> {
+        let config_id = config_id.to_string();
+        self.with_conn(move |conn| select_config_file(conn, &config_id))
+            .await
+    }
+
+    async fn find_config_file_by_path(&self, path: &str) -> Result<Option<ConfigFile>> {
+        let path = path.to_string();
+        self.with_conn(move |conn| select_config_file_by_path(conn, &path))
+            .await
+    }
+
+    async fn list_config_files(&self) -> Result<Vec<ConfigFile>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM config_files ORDER BY path, config_id",
+                CONFIG_FILE_COLUMNS
+            ))?;
+            let config_files = stmt
+                .query_map([], config_file_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(config_files)
+        })
+        .await
+    }
+
+    async fn delete_config_file(&self, config_id: &str, audit: &AuditContext) -> Result<()> {
+        let config_id = config_id.to_string();
+        let audit = audit.clone();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let Some(config_file) = select_config_file(&tx, &config_id)? else {
+                return Ok(());
+            };
+            let server_ids = {
+                let mut stmt = tx.prepare(
+                    "SELECT server_id FROM servers
+                     WHERE deleted_at IS NULL
+                       AND (config_id = ?1
+                            OR config_file_path = ?2
+                            OR server_id IN (SELECT server_id FROM server_modules WHERE module = ?2))
+                     ORDER BY created_at, server_id",
+                )?;
+                let server_ids = stmt
+                    .query_map(params![config_id, config_file.path], |row| row.get(0))?
+                    .collect::<rusqlite::Result<Vec<String>>>()?;
+                server_ids
+            };
+            if !server_ids.is_empty() {
+                return Err(ConfigFileInUse { config_id, server_ids }.into());
+            }
+            tx.execute("DELETE FROM config_files WHERE config_id = ?1", params![config_id])?;
+            let event = audit.event(
+                AuditTarget::ConfigFile,
+                &config_id,
+                Some(AuditRecord::ConfigFile(config_file)),
+                None,
+            );
+            insert_audit_event(&tx, &event)?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn create_api_key(&self, api_key: StoredApiKey, audit: &AuditContext) -> Result<()> {
+        let audit = audit.clone();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                &format!(
+                    "INSERT INTO api_keys ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    API_KEY_COLUMNS
+                ),
+                params![
+                    api_key.key.key_id,
+                    api_key.key.name,
+                    api_key.key.role.as_str(),
+                    format_timestamp(&api_key.key.created_at),
+                    api_key.key.created_by,
+                    api_key.secret_hash,
+                ],
+            )?;
+            let event = audit.event(
+                AuditTarget::ApiKey,
+                &api_key.key.key_id,
+                None,
+                Some(AuditRecord::ApiKey(api_key.key.clone())),
+            );
+            insert_audit_event(&tx, &event)?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_api_key(&self, key_id: &str) -> Result<Option<StoredApiKey>> {
+        let key_id = key_id.to_string();
+        self.with_conn(move |conn| {
+            Ok(conn
+                .query_row(
+                    &format!("SELECT {} FROM api_keys WHERE key_id = ?1", API_KEY_COLUMNS),
+                    params![key_id],
+                    api_key_from_row,
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM api_keys ORDER BY created_at, key_id",
+                API_KEY_COLUMNS
+            ))?;
+            let keys = stmt
+                .query_map([], api_key_from_row)?
+                .map(|api_key| api_key.map(|api_key| api_key.key))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(keys)
+        })
+        .await
+    }
+
+    async fn delete_api_key(&self, key_id: &str, audit: &AuditContext) -> Result<()> {
+        let key_id = key_id.to_string();
+        let audit = audit.clone();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let api_key = tx
+                .query_row(
+                    &format!("SELECT {} FROM api_keys WHERE key_id = ?1", API_KEY_COLUMNS),
+                    params![key_id],
+                    api_key_from_row,
+                )
+                .optional()?;
+            if let Some(api_key) = api_key {
+                tx.execute("DELETE FROM api_keys WHERE key_id = ?1", params![key_id])?;
+                let event = audit.event(
+                    AuditTarget::ApiKey,
+                    &key_id,
+                    Some(AuditRecord::ApiKey(api_key.key)),
+                    None,
+                );
+                insert_audit_event(&tx, &event)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{test_audit, test_server, InvalidCursor};
+    use homelab_api::audit_event::AuditAction;
+    use homelab_api::server_revision::RevisionAction;
+
+    /// A change made now by `alice`.
+    fn by_alice(action: AuditAction) -> AuditContext {
+        AuditContext {
+            actor: "alice".to_string(),
+            ..test_audit(action)
+        }
+    }
+
+    #[tokio::test]
+    async fn update_server_changes_only_given_fields() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        let config_file = ConfigFile {
+            config_id: "cfg-web".to_string(),
+            path: "/etc/nixos/web.nix".to_string(),
+            description: None,
+            owner: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        store
+            .create_config_file(config_file, &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        let mut server = test_server("web-1", "/etc/nixos/web.nix");
+        server.config_id = Some("cfg-web".to_string());
+        server.description = Some("Front end".to_string());
+        server.labels.insert("role".to_string(), "web".to_string());
+        server.modules = vec![
+            "/etc/nixos/a.nix".to_string(),
+            "/etc/nixos/b.nix".to_string(),
+        ];
+        store
+            .create_server(server.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        let changes = UpdateServerRequest {
+            add_modules: vec!["/etc/nixos/c.nix".to_string()],
+            remove_modules: vec!["/etc/nixos/a.nix".to_string()],
+            ..Default::default()
+        };
+        let updated = store
+            .update_server(
+                &server.server_id,
+                changes,
+                &by_alice(AuditAction::Update),
+                Some(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.version, 2);
+        assert_eq!(updated.modules, ["/etc/nixos/b.nix", "/etc/nixos/c.nix"]);
+        assert_eq!(updated.description.as_deref(), Some("Front end"));
+        assert_eq!(updated.labels, server.labels);
+        assert_eq!(updated.config_id.as_deref(), Some("cfg-web"));
+        assert_eq!(updated.created_by.as_deref(), Some("test"));
+        assert_eq!(updated.updated_by.as_deref(), Some("alice"));
+
+        // A bare path detaches the server from its config file, and an empty description clears it
+        let changes = UpdateServerRequest {
+            config_file_path: Some("/etc/nixos/other.nix".to_string()),
+            description: Some(String::new()),
+            ..Default::default()
+        };
+        let updated = store
+            .update_server(
+                &server.server_id,
+                changes,
+                &by_alice(AuditAction::Update),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.config_file_path, "/etc/nixos/other.nix");
+        assert_eq!(updated.config_id, None);
+        assert_eq!(updated.description, None);
+
+        let stored = store.get_server(&server.server_id).await.unwrap().unwrap();
+        assert_eq!(stored.version, 3);
+        assert_eq!(stored.modules, updated.modules);
+        assert_eq!(
+            store.list_revisions(&server.server_id).await.unwrap().len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn update_server_checks_the_version() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        let server = test_server("web-1", "/etc/nixos/web.nix");
+        store
+            .create_server(server.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        let changes = UpdateServerRequest {
+            description: Some("Front end".to_string()),
+            ..Default::default()
+        };
+        let error = store
+            .update_server(
+                &server.server_id,
+                changes,
+                &by_alice(AuditAction::Update),
+                Some(7),
+            )
+            .await
+            .unwrap_err();
+        let mismatch = error.downcast_ref::<VersionMismatch>().unwrap();
+        assert_eq!(mismatch.current_version, Some(1));
+        assert_eq!(
+            store
+                .get_server(&server.server_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .version,
+            1
+        );
+
+        let error = store
+            .update_server(
+                "missing",
+                UpdateServerRequest::default(),
+                &by_alice(AuditAction::Update),
+                None,
+            )
+            .await;
+        assert!(error.is_err());
+    }
+
+    #[tokio::test]
+    async fn update_server_rejects_deleted_servers() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        let server = test_server("web-1", "/etc/nixos/web.nix");
+        store
+            .create_server(server.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+        store
+            .delete_server(&server.server_id, &by_alice(AuditAction::Delete), None)
+            .await
+            .unwrap();
+
+        let changes = UpdateServerRequest {
+            description: Some("Front end".to_string()),
+            ..Default::default()
+        };
+        let error = store
+            .update_server(
+                &server.server_id,
+                changes,
+                &by_alice(AuditAction::Update),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(error.is::<ServerDeleted>());
+        assert_eq!(
+            store
+                .get_server(&server.server_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .version,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn servers_are_found_by_module() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        let config_file = ConfigFile {
+            config_id: "cfg-common".to_string(),
+            path: "/etc/nixos/common.nix".to_string(),
+            description: None,
+            owner: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        store
+            .create_config_file(config_file, &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        let mut server = test_server("web-1", "/etc/nixos/web.nix");
+        server.modules = vec!["/etc/nixos/common.nix".to_string()];
+        store
+            .create_server(server.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+        let other = test_server("db-1", "/etc/nixos/common.nix");
+        store
+            .create_server(other.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        let found = store
+            .find_servers_by_config_path("/etc/nixos/common.nix")
+            .await
+            .unwrap();
+        let ids: Vec<&str> = found
+            .iter()
+            .map(|server| server.server_id.as_str())
+            .collect();
+        assert_eq!(ids, [server.server_id.as_str(), other.server_id.as_str()]);
+
+        let error = store
+            .delete_config_file("cfg-common", &test_audit(AuditAction::Delete))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ConfigFileInUse>().unwrap().server_ids,
+            [server.server_id.clone(), other.server_id.clone()]
+        );
+
+        let changes = UpdateServerRequest {
+            remove_modules: vec!["/etc/nixos/common.nix".to_string()],
+            ..Default::default()
+        };
+        store
+            .update_server(
+                &server.server_id,
+                changes,
+                &by_alice(AuditAction::Update),
+                None,
+            )
+            .await
+            .unwrap();
+        let found = store
+            .find_servers_by_config_path("/etc/nixos/common.nix")
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+
+        // A server using the path keeps the config file even without a `config_id`
+        let error = store
+            .delete_config_file("cfg-common", &test_audit(AuditAction::Delete))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ConfigFileInUse>().unwrap().server_ids,
+            [other.server_id.as_str()]
+        );
+
+        store
+            .delete_server(&other.server_id, &test_audit(AuditAction::Delete), None)
+            .await
+            .unwrap();
+        store
+            .delete_config_file("cfg-common", &test_audit(AuditAction::Delete))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn changes_are_audited_with_the_change() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        let server = test_server("web-1", "/etc/nixos/web.nix");
+        store
+            .create_server(server.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+        let (api_key, _) =
+            crate::auth::new_api_key("ci".to_string(), Role::Operator, "test".to_string()).unwrap();
+        let key_id = api_key.key.key_id.clone();
+        store
+            .create_api_key(api_key, &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        // Neither a rejected change nor deleting a missing key leaves an event behind
+        let changes = UpdateServerRequest {
+            description: Some("Front end".to_string()),
+            ..Default::default()
+        };
+        store
+            .update_server(
+                &server.server_id,
+                changes,
+                &by_alice(AuditAction::Update),
+                Some(7),
+            )
+            .await
+            .unwrap_err();
+        store
+            .delete_api_key("missing", &by_alice(AuditAction::Delete))
+            .await
+            .unwrap();
+        store
+            .delete_api_key(&key_id, &by_alice(AuditAction::Delete))
+            .await
+            .unwrap();
+
+        let query = AuditQuery {
+            server_id: None,
+            since: None,
+            limit: 10,
+            cursor: None,
+        };
+        let events = store.list_audit_events(query).await.unwrap().events;
+        let targets: Vec<(AuditTarget, &str)> = events
+            .iter()
+            .map(|event| (event.target, event.target_id.as_str()))
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                (AuditTarget::Server, server.server_id.as_str()),
+                (AuditTarget::ApiKey, key_id.as_str()),
+                (AuditTarget::ApiKey, key_id.as_str()),
+            ]
+        );
+        assert_eq!(events[2].actor, "alice");
+        assert!(matches!(&events[2].before, Some(AuditRecord::ApiKey(key)) if key.name == "ci"));
+        assert!(events[2].after.is_none());
+    }
+
+    #[tokio::test]
+    async fn revisions_are_recorded_until_the_purge() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        let server = test_server("web-1", "/etc/nixos/web.nix");
+        store
+            .create_server(server.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+        let changes = UpdateServerRequest {
+            description: Some("Front end".to_string()),
+            ..Default::default()
+        };
+        store
+            .update_server(
+                &server.server_id,
+                changes,
+                &test_audit(AuditAction::Update),
+                None,
+            )
+            .await
+            .unwrap();
+        store
+            .purge_server(&server.server_id, &test_audit(AuditAction::Purge), None)
+            .await
+            .unwrap();
+
+        let revisions = store.list_revisions(&server.server_id).await.unwrap();
+        let recorded: Vec<(u64, RevisionAction)> = revisions
+            .iter()
+            .map(|revision| (revision.revision, revision.action))
+            .collect();
+        assert_eq!(
+            recorded,
+            [
+                (1, RevisionAction::Create),
+                (2, RevisionAction::Update),
+                (3, RevisionAction::Purge)
+            ]
+        );
+        assert_eq!(
+            revisions[1].server.description.as_deref(),
+            Some("Front end")
+        );
+        // The purge keeps the last record, while the server itself is gone
+        assert_eq!(revisions[2].server.version, 2);
+        assert!(store.get_server(&server.server_id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn names_conflict_on_create_and_rename() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        let web = test_server("web-1", "/etc/nixos/web.nix");
+        let db = test_server("db-1", "/etc/nixos/db.nix");
+        for server in [&web, &db] {
+            store
+                .create_server(server.clone(), &test_audit(AuditAction::Create))
+                .await
+                .unwrap();
+        }
+
+        let error = store
+            .create_server(
+                test_server("web-1", "/etc/nixos/other.nix"),
+                &test_audit(AuditAction::Create),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error
+                .downcast_ref::<NameConflict>()
+                .unwrap()
+                .existing_server_id,
+            web.server_id
+        );
+
+        let rename = UpdateServerRequest {
+            server_name: Some("web-1".to_string()),
+            ..Default::default()
+        };
+        let error = store
+            .update_server(
+                &db.server_id,
+                rename,
+                &test_audit(AuditAction::Update),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(error.is::<NameConflict>());
+        let stored = store.get_server(&db.server_id).await.unwrap().unwrap();
+        assert_eq!(stored.server_name, "db-1");
+        assert_eq!(stored.version, 1);
+    }
+
+    #[tokio::test]
+    async fn servers_are_found_by_name_including_the_trash() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        let deleted = test_server("web-1", "/etc/nixos/web.nix");
+        store
+            .create_server(deleted.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+        store
+            .delete_server(&deleted.server_id, &test_audit(AuditAction::Delete), None)
+            .await
+            .unwrap();
+        let live = test_server("web-1", "/etc/nixos/web.nix");
+        store
+            .create_server(live.clone(), &test_audit(AuditAction::Create))
+            .await
+            .unwrap();
+
+        let found = store.find_servers_by_name("web-1").await.unwrap();
+        let ids: Vec<&str> = found
+            .iter()
+            .map(|server| server.server_id.as_str())
+            .collect();
+        assert_eq!(ids, [deleted.server_id.as_str(), live.server_id.as_str()]);
+        assert!(store.find_servers_by_name("web").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn list_servers_pages_in_creation_order() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        let mut created = Vec::new();
+        for name in ["web-1", "web-2", "web-3", "db-1", "db-2"] {
+            let server = test_server(name, "/etc/nixos/web.nix");
+            created.push(server.server_id.clone());
+            store
+                .create_server(server, &test_audit(AuditAction::Create))
+                .await
+                .unwrap();
+        }
+        store
+            .delete_server(&created[1], &test_audit(AuditAction::Delete), None)
+            .await
+            .unwrap();
+
+        let page = |include_deleted, cursor| ListQuery {
+            limit: 2,
+            cursor,
+            include_deleted,
+            labels: Vec::new(),
+        };
+        let mut listed = Vec::new();
+        let mut cursor = None;
+        loop {
+            let result = store.list_servers(page(false, cursor)).await.unwrap();
+            listed.extend(result.servers.into_iter().map(|server| server.server_id));
+            cursor = result.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        let live: Vec<String> = created
+            .iter()
+            .filter(|server_id| **server_id != created[1])
+            .cloned()
+            .collect();
+        assert_eq!(listed, live);
+
+        let all = store.list_servers(page(true, None)).await.unwrap();
+        assert_eq!(all.servers[1].server_id, created[1]);
+        let error = store
+            .list_servers(page(false, Some("garbage".to_string())))
+            .await
+            .unwrap_err();
+        assert!(error.is::<InvalidCursor>());
+    }
+
+    #[tokio::test]
+    async fn list_servers_filters_by_labels() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        for (name, role, env) in [
+            ("db-1", "db", "prod"),
+            ("db-2", "db", "test"),
+            ("web-1", "web", "prod"),
+        ] {
+            let mut server = test_server(name, "/etc/nixos/web.nix");
+            server.labels.insert("role".to_string(), role.to_string());
+            server.labels.insert("env".to_string(), env.to_string());
+            store
+                .create_server(server, &test_audit(AuditAction::Create))
+                .await
+                .unwrap();
+        }
+
+        let names = |labels: &[(&str, &str)]| {
+            let query = ListQuery {
+                limit: 10,
+                cursor: None,
+                include_deleted: false,
+                labels: labels
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            };
+            async {
+                store
+                    .list_servers(query)
+                    .await
+                    .unwrap()
+                    .servers
+                    .into_iter()
+                    .map(|server| server.server_name)
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(names(&[("role", "db")]).await, ["db-1", "db-2"]);
+        assert_eq!(names(&[("role", "db"), ("env", "prod")]).await, ["db-1"]);
+        assert!(names(&[("site", "basement")]).await.is_empty());
+    }
+
+    #[test]
+    fn unique_names_migration_renames_duplicates() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.execute_batch(MIGRATIONS[1]).unwrap();
+        conn.pragma_update(None, "user_version", 2).unwrap();
+        for (server_id, created_at) in [
+            ("22222222-b", "2024-01-02T00:00:00.000000000Z"),
+            ("11111111-a", "2024-01-01T00:00:00.000000000Z"),
+        ] {
+            conn.execute(
+                "INSERT INTO servers (server_id, server_name, config_file_path, created_at, updated_at)
+                 VALUES (?1, 'web-1', '/etc/nixos/web.nix', ?2, ?2)",
+                params![server_id, created_at],
+            )
+            .unwrap();
+        }
+
+        migrate(&mut conn).unwrap();
+        let mut stmt = conn
+            .prepare("SELECT server_name FROM servers ORDER BY created_at")
+            .unwrap();
+        let names = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(names, ["web-1", "web-1-22222222"]);
+    }
+}
@@ -0,0 +1,77 @@
+//! API keys, managed under `/keys` and sent as bearer tokens.
+
+use serde_json::json;
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn requests_without_a_key_are_unauthorized() {
+    let api = TestApi::new().await;
+
+    let resp = api
+        .send(http::Request::builder().method("GET").uri("/servers"), None)
+        .await;
+    assert_eq!(resp.status(), 401);
+    assert_eq!(error_code(&resp), "unauthorized");
+    assert_eq!(resp.headers().get("WWW-Authenticate").unwrap(), "Bearer");
+
+    let request = http::Request::builder()
+        .method("GET")
+        .uri("/servers")
+        .header("Authorization", "Bearer hlk_unknown_secret");
+    let resp = api.send(request, None).await;
+    assert_eq!(resp.status(), 401);
+    assert_eq!(error_code(&resp), "unauthorized");
+}
+
+#[tokio::test]
+async fn api_keys() {
+    let api = TestApi::new().await;
+
+    let resp = api
+        .call(
+            "POST",
+            "/keys",
+            Some(json!({ "name": "ci", "role": "operator" })),
+        )
+        .await;
+    assert_eq!(resp.status(), 201);
+    let key_id = body(&resp)["key"]["key_id"].as_str().unwrap().to_string();
+    let token = body(&resp)["token"].as_str().unwrap().to_string();
+    assert_eq!(body(&resp)["key"]["role"], "operator");
+
+    let resp = api
+        .call("POST", "/keys", Some(json!({ "name": " " })))
+        .await;
+    assert_eq!(resp.status(), 422);
+    assert_eq!(body(&resp)["details"][0]["field"], "name");
+
+    let resp = api.call("GET", "/keys", None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["count"], 2);
+    assert!(body(&resp)["keys"][0].get("secret_hash").is_none());
+
+    let whoami = http::Request::builder()
+        .method("GET")
+        .uri("/whoami")
+        .header("Authorization", format!("Bearer {}", token));
+    let resp = api.send(whoami, None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["authenticated_by"], "api_key");
+    assert_eq!(body(&resp)["key_id"], key_id.as_str());
+    assert_eq!(body(&resp)["role"], "operator");
+
+    let resp = api.call("DELETE", &format!("/keys/{}", key_id), None).await;
+    assert_eq!(resp.status(), 200);
+
+    let revoked = http::Request::builder()
+        .method("GET")
+        .uri("/whoami")
+        .header("Authorization", format!("Bearer {}", token));
+    let resp = api.send(revoked, None).await;
+    assert_eq!(resp.status(), 401);
+
+    let resp = api.call("DELETE", &format!("/keys/{}", key_id), None).await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "api_key_not_found");
+}
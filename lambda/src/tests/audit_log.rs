@@ -0,0 +1,115 @@
+//! The audit log at `GET /audit`.
+
+use serde_json::{json, Value};
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn audit_log_records_changes() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    api.call(
+        "PUT",
+        &format!("/servers/{}", server_id),
+        Some(json!({ "description": "Front end" })),
+    )
+    .await;
+    api.add_server("web-2").await;
+
+    // The admin key the fixture created is audited too
+    let resp = api.call("GET", "/audit", None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["count"], 4);
+    assert_eq!(body(&resp)["events"][0]["target"], "api_key");
+
+    let resp = api
+        .call("GET", &format!("/audit?server_id={}", server_id), None)
+        .await;
+    assert_eq!(body(&resp)["count"], 2);
+    assert!(body(&resp)["events"][0]["actor"]
+        .as_str()
+        .unwrap()
+        .starts_with("api-key/"));
+    assert_eq!(
+        body(&resp)["events"][1]["before"]["description"],
+        Value::Null
+    );
+    assert_eq!(
+        body(&resp)["events"][1]["after"]["description"],
+        "Front end"
+    );
+
+    let resp = api
+        .call(
+            "POST",
+            "/configs",
+            Some(json!({ "path": "/etc/nixos/common.nix" })),
+        )
+        .await;
+    let config_id = body(&resp)["config_id"].as_str().unwrap().to_string();
+    api.call("DELETE", &format!("/configs/{}", config_id), None)
+        .await;
+    let resp = api
+        .call("GET", &format!("/audit?server_id={}", config_id), None)
+        .await;
+    assert_eq!(body(&resp)["count"], 2);
+    assert_eq!(body(&resp)["events"][1]["target"], "config_file");
+    assert_eq!(body(&resp)["events"][1]["action"], "delete");
+    assert_eq!(
+        body(&resp)["events"][1]["before"]["path"],
+        "/etc/nixos/common.nix"
+    );
+
+    let resp = api.call("GET", "/audit?limit=1", None).await;
+    assert_eq!(body(&resp)["count"], 1);
+    assert!(body(&resp)["next_cursor"].is_string());
+
+    let resp = api.call("GET", "/audit?since=yesterday", None).await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(error_code(&resp), "invalid_parameter");
+    assert_eq!(body(&resp)["details"][0]["field"], "since");
+}
+
+#[tokio::test]
+async fn audit_events_keep_the_client_request_id_apart() {
+    let api = TestApi::new().await;
+
+    let request = api
+        .request("POST", "/servers")
+        .header("X-Request-Id", "req-2");
+    let resp = api
+        .send(
+            request,
+            Some(json!({ "server_name": "web-1", "config_file_path": "/etc/nixos/web.nix" })),
+        )
+        .await;
+    let server_id = body(&resp)["server_id"].as_str().unwrap().to_string();
+    let resp = api
+        .call("GET", &format!("/audit?server_id={}", server_id), None)
+        .await;
+    let event = &body(&resp)["events"][0];
+    assert_eq!(event["client_request_id"], "req-2");
+    assert_ne!(event["request_id"], "req-2");
+    assert!(uuid::Uuid::parse_str(event["request_id"].as_str().unwrap()).is_ok());
+}
+
+#[tokio::test]
+async fn rejected_changes_are_not_audited() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    api.add_server("web-2").await;
+
+    let resp = api
+        .call(
+            "PUT",
+            &format!("/servers/{}", server_id),
+            Some(json!({ "server_name": "web-2" })),
+        )
+        .await;
+    assert_eq!(resp.status(), 409);
+    let resp = api
+        .call("GET", &format!("/audit?server_id={}", server_id), None)
+        .await;
+    assert_eq!(body(&resp)["count"], 1);
+    assert_eq!(body(&resp)["events"][0]["action"], "create");
+}
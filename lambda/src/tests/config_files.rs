@@ -0,0 +1,143 @@
+//! Registered NixOS configuration files, under `/configs`.
+
+use serde_json::json;
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn config_files() {
+    let api = TestApi::new().await;
+    let config = json!({ "path": "/etc/nixos/web.nix", "owner": "ops" });
+
+    let resp = api.call("POST", "/configs", Some(config.clone())).await;
+    assert_eq!(resp.status(), 201);
+    let config_id = body(&resp)["config_id"].as_str().unwrap().to_string();
+
+    let resp = api.call("POST", "/configs", Some(config)).await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "config_path_conflict");
+    assert_eq!(body(&resp)["existing_config_id"], config_id.as_str());
+
+    let resp = api
+        .call("POST", "/configs", Some(json!({ "path": "relative.nix" })))
+        .await;
+    assert_eq!(resp.status(), 422);
+    assert_eq!(body(&resp)["details"][0]["field"], "path");
+
+    let resp = api.call("GET", "/configs", None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["count"], 1);
+
+    let resp = api
+        .call("GET", &format!("/configs/{}", config_id), None)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["path"], "/etc/nixos/web.nix");
+
+    // Paths are sent with their slashes encoded, so they stay one segment
+    let resp = api
+        .call("GET", "/configs/%2Fetc%2Fnixos%2Fweb.nix", None)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["config_id"], config_id.as_str());
+
+    let resp = api.call("GET", "/configs/missing", None).await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "config_file_not_found");
+}
+
+#[tokio::test]
+async fn servers_of_a_config_file() {
+    let api = TestApi::new().await;
+    let resp = api
+        .call(
+            "POST",
+            "/configs",
+            Some(json!({ "path": "/etc/nixos/web.nix" })),
+        )
+        .await;
+    let config_id = body(&resp)["config_id"].as_str().unwrap().to_string();
+
+    let resp = api
+        .call(
+            "POST",
+            "/servers",
+            Some(json!({ "server_name": "web-1", "config_id": config_id })),
+        )
+        .await;
+    assert_eq!(resp.status(), 201);
+    let server_id = body(&resp)["server_id"].as_str().unwrap().to_string();
+
+    let resp = api
+        .call(
+            "POST",
+            "/servers",
+            Some(json!({ "server_name": "web-2", "config_id": "missing" })),
+        )
+        .await;
+    assert_eq!(resp.status(), 422);
+    assert_eq!(body(&resp)["details"][0]["field"], "config_id");
+
+    let resp = api
+        .call("GET", &format!("/configs/{}/servers", config_id), None)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["config_file_path"], "/etc/nixos/web.nix");
+    assert_eq!(body(&resp)["servers"][0]["server_id"], server_id.as_str());
+
+    let resp = api
+        .call("GET", "/configs/%2Fetc%2Fnixos%2Fother.nix/servers", None)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["count"], 0);
+
+    let resp = api
+        .call("DELETE", &format!("/configs/{}", config_id), None)
+        .await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "config_file_in_use");
+    assert_eq!(body(&resp)["server_ids"][0], server_id.as_str());
+
+    api.call("DELETE", &format!("/servers/{}", server_id), None)
+        .await;
+    let resp = api
+        .call("DELETE", &format!("/configs/{}", config_id), None)
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = api
+        .call("DELETE", &format!("/configs/{}", config_id), None)
+        .await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "config_file_not_found");
+}
+
+#[tokio::test]
+async fn servers_on_the_same_path_keep_a_config_file_in_use() {
+    let api = TestApi::new().await;
+    // Added by path only, before the config file was registered
+    let server_id = api.add_server("web-1").await;
+    let resp = api
+        .call(
+            "POST",
+            "/configs",
+            Some(json!({ "path": "/etc/nixos/web.nix" })),
+        )
+        .await;
+    let config_id = body(&resp)["config_id"].as_str().unwrap().to_string();
+
+    let resp = api
+        .call("DELETE", "/configs/%2Fetc%2Fnixos%2Fweb.nix", None)
+        .await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "config_file_in_use");
+    assert_eq!(body(&resp)["server_ids"][0], server_id.as_str());
+
+    // Servers in the trash do not
+    api.call("DELETE", &format!("/servers/{}", server_id), None)
+        .await;
+    let resp = api
+        .call("DELETE", &format!("/configs/{}", config_id), None)
+        .await;
+    assert_eq!(resp.status(), 200);
+}
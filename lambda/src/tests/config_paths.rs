@@ -0,0 +1,88 @@
+//! Which servers use a NixOS configuration file, with `GET /configs/{config}/servers`.
+
+use serde_json::json;
+
+use super::{body, TestApi};
+
+fn server_ids(resp: &lambda_http::Response<lambda_http::Body>) -> Vec<String> {
+    body(resp)["servers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|server| server["server_id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn servers_are_found_by_their_config_path() {
+    let api = TestApi::new().await;
+    let web_1 = api.add_server("web-1").await;
+    let web_2 = api.add_server("web-2").await;
+    api.call(
+        "POST",
+        "/servers",
+        Some(json!({ "server_name": "db-1", "config_file_path": "/etc/nixos/db.nix" })),
+    )
+    .await;
+
+    // Paths need not be registered as config files to be looked up
+    let resp = api
+        .call("GET", "/configs/%2Fetc%2Fnixos%2Fweb.nix/servers", None)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["config_file_path"], "/etc/nixos/web.nix");
+    assert_eq!(body(&resp)["count"], 2);
+    assert_eq!(server_ids(&resp), [web_1.as_str(), web_2.as_str()]);
+
+    let resp = api
+        .call("GET", "/configs/%2Fetc%2Fnixos%2Fmissing.nix/servers", None)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["count"], 0);
+
+    // Servers in the trash are only listed on request
+    api.call("DELETE", &format!("/servers/{}", web_1), None)
+        .await;
+    let resp = api
+        .call("GET", "/configs/%2Fetc%2Fnixos%2Fweb.nix/servers", None)
+        .await;
+    assert_eq!(server_ids(&resp), [web_2.as_str()]);
+    let resp = api
+        .call(
+            "GET",
+            "/configs/%2Fetc%2Fnixos%2Fweb.nix/servers?include_deleted=true",
+            None,
+        )
+        .await;
+    assert_eq!(server_ids(&resp), [web_1.as_str(), web_2.as_str()]);
+
+    let resp = api
+        .call(
+            "GET",
+            "/configs/%2Fetc%2Fnixos%2Fweb.nix/servers?include_deleted=yes",
+            None,
+        )
+        .await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn moved_servers_leave_their_old_path() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    api.call(
+        "PUT",
+        &format!("/servers/{}", server_id),
+        Some(json!({ "config_file_path": "/etc/nixos/web-v2.nix" })),
+    )
+    .await;
+
+    let resp = api
+        .call("GET", "/configs/%2Fetc%2Fnixos%2Fweb.nix/servers", None)
+        .await;
+    assert_eq!(body(&resp)["count"], 0);
+    let resp = api
+        .call("GET", "/configs/%2Fetc%2Fnixos%2Fweb-v2.nix/servers", None)
+        .await;
+    assert_eq!(server_ids(&resp), [server_id]);
+}
@@ -0,0 +1,33 @@
+//! Error responses, which every route shares.
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn errors_carry_the_request_id() {
+    let api = TestApi::new().await;
+
+    // A client's own request ID is never taken as the service's
+    let request = api
+        .request("GET", "/servers/missing")
+        .header("X-Request-Id", "req-1");
+    let resp = api.send(request, None).await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "server_not_found");
+    let request_id = body(&resp)["request_id"].as_str().unwrap().to_string();
+    assert_ne!(request_id, "req-1");
+    assert!(uuid::Uuid::parse_str(&request_id).is_ok());
+
+    // Each request gets its own
+    let resp = api.call("GET", "/servers/missing", None).await;
+    assert_ne!(body(&resp)["request_id"], request_id.as_str());
+}
+
+#[tokio::test]
+async fn malformed_bodies_are_invalid() {
+    let api = TestApi::new().await;
+    let request = api.request("POST", "/servers");
+    let resp = api.send(request, None).await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(error_code(&resp), "invalid_body");
+    assert!(body(&resp)["request_id"].is_string());
+}
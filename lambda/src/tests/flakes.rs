@@ -0,0 +1,60 @@
+//! Servers configured through a flake rather than a bare configuration file.
+
+use serde_json::json;
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn servers_store_the_flake_and_its_path() {
+    let api = TestApi::new().await;
+    let new_server = json!({
+        "server_name": "web-1",
+        "flake": { "uri": "github:ourorg/infra", "attribute": "nixosConfigurations.web-1" },
+    });
+    let resp = api.call("POST", "/servers", Some(new_server)).await;
+    assert_eq!(resp.status(), 201);
+    let server_id = body(&resp)["server_id"].as_str().unwrap().to_string();
+
+    let resp = api
+        .call("GET", &format!("/servers/{}", server_id), None)
+        .await;
+    assert_eq!(
+        body(&resp)["config_file_path"],
+        "github:ourorg/infra#nixosConfigurations.web-1"
+    );
+    assert_eq!(body(&resp)["flake"]["uri"], "github:ourorg/infra");
+
+    // Setting a bare path detaches the server from its flake
+    let changes = json!({ "config_file_path": "/etc/nixos/web.nix" });
+    let resp = api
+        .call("PUT", &format!("/servers/{}", server_id), Some(changes))
+        .await;
+    assert_eq!(resp.status(), 200);
+    let resp = api
+        .call("GET", &format!("/servers/{}", server_id), None)
+        .await;
+    assert_eq!(body(&resp)["config_file_path"], "/etc/nixos/web.nix");
+    assert!(body(&resp).get("flake").is_none());
+}
+
+#[tokio::test]
+async fn invalid_flakes_are_rejected() {
+    let api = TestApi::new().await;
+    let new_server = json!({
+        "server_name": "web-1",
+        "flake": { "uri": "ftp://example.com/infra", "attribute": "nixosConfigurations.web-1" },
+    });
+    let resp = api.call("POST", "/servers", Some(new_server)).await;
+    assert_eq!(resp.status(), 422);
+    assert_eq!(body(&resp)["details"][0]["field"], "flake");
+
+    let new_server = json!({
+        "server_name": "web-1",
+        "config_file_path": "/etc/nixos/web.nix",
+        "flake": { "uri": "github:ourorg/infra", "attribute": "nixosConfigurations.web-1" },
+    });
+    let resp = api.call("POST", "/servers", Some(new_server)).await;
+    assert_eq!(resp.status(), 422);
+    assert_eq!(error_code(&resp), "invalid_field");
+    assert_eq!(body(&resp)["details"][0]["field"], "flake");
+}
@@ -0,0 +1,56 @@
+//! Fetching a single server with `GET /servers/{id}`.
+
+use serde_json::json;
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn get_server_returns_the_full_record() {
+    let api = TestApi::new().await;
+    let resp = api
+        .call(
+            "POST",
+            "/servers",
+            Some(json!({
+                "server_name": "web-1",
+                "config_file_path": "/etc/nixos/web.nix",
+                "description": "Front end",
+            })),
+        )
+        .await;
+    let server_id = body(&resp)["server_id"].as_str().unwrap().to_string();
+
+    let resp = api
+        .call("GET", &format!("/servers/{}", server_id), None)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let server = body(&resp);
+    assert_eq!(server["server_id"], server_id.as_str());
+    assert_eq!(server["server_name"], "web-1");
+    assert_eq!(server["config_file_path"], "/etc/nixos/web.nix");
+    assert_eq!(server["description"], "Front end");
+    assert_eq!(server["version"], 1);
+    assert!(server["created_at"].is_string());
+    assert!(server["deleted_at"].is_null());
+}
+
+#[tokio::test]
+async fn get_server_finds_servers_in_the_trash() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let uri = format!("/servers/{}", server_id);
+    api.call("DELETE", &uri, None).await;
+
+    let resp = api.call("GET", &uri, None).await;
+    assert_eq!(resp.status(), 200);
+    assert!(body(&resp)["deleted_at"].is_string());
+}
+
+#[tokio::test]
+async fn get_server_of_unknown_id_is_not_found() {
+    let api = TestApi::new().await;
+
+    let resp = api.call("GET", "/servers/missing", None).await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "server_not_found");
+}
@@ -0,0 +1,59 @@
+//! Revision history with `GET /servers/{id}/history`.
+
+use serde_json::json;
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn history_records_every_change() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let uri = format!("/servers/{}", server_id);
+    api.call("PUT", &uri, Some(json!({ "description": "Front end" })))
+        .await;
+    api.call("DELETE", &uri, None).await;
+    api.call("POST", &format!("{}/restore", uri), None).await;
+
+    let resp = api.call("GET", &format!("{}/history", uri), None).await;
+    assert_eq!(resp.status(), 200);
+    let history = body(&resp);
+    assert_eq!(history["server_id"], server_id.as_str());
+    assert_eq!(history["count"], 4);
+    let revisions = history["revisions"].as_array().unwrap();
+    let actions: Vec<&str> = revisions
+        .iter()
+        .map(|revision| revision["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["create", "update", "delete", "restore"]);
+    for (index, revision) in revisions.iter().enumerate() {
+        assert_eq!(revision["revision"], index as u64 + 1);
+        assert_eq!(revision["server"]["version"], index as u64 + 1);
+    }
+    assert!(revisions[0]["server"]["description"].is_null());
+    assert_eq!(revisions[1]["server"]["description"], "Front end");
+    assert!(revisions[2]["server"]["deleted_at"].is_string());
+}
+
+#[tokio::test]
+async fn history_of_a_purged_server_ends_with_the_purge() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let uri = format!("/servers/{}", server_id);
+    api.call("DELETE", &format!("{}?purge=true", uri), None)
+        .await;
+
+    let resp = api.call("GET", &format!("{}/history", uri), None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["count"], 2);
+    assert_eq!(body(&resp)["revisions"][1]["action"], "purge");
+    assert_eq!(body(&resp)["revisions"][1]["revision"], 2);
+}
+
+#[tokio::test]
+async fn history_of_unknown_servers_is_not_found() {
+    let api = TestApi::new().await;
+
+    let resp = api.call("GET", "/servers/missing/history", None).await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "server_not_found");
+}
@@ -0,0 +1,93 @@
+//! Labels on servers and filtering `GET /servers` by them.
+
+use serde_json::json;
+
+use super::{body, error_code, TestApi};
+
+async fn add_labelled(api: &TestApi, name: &str, labels: serde_json::Value) {
+    let resp = api
+        .call(
+            "POST",
+            "/servers",
+            Some(json!({
+                "server_name": name,
+                "config_file_path": "/etc/nixos/web.nix",
+                "labels": labels,
+            })),
+        )
+        .await;
+    assert_eq!(resp.status(), 201);
+}
+
+fn names(resp: &lambda_http::Response<lambda_http::Body>) -> Vec<String> {
+    body(resp)["servers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|server| server["server_name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn list_servers_filters_by_every_label() {
+    let api = TestApi::new().await;
+    add_labelled(&api, "db-1", json!({ "role": "db", "env": "prod" })).await;
+    add_labelled(&api, "db-2", json!({ "role": "db", "env": "test" })).await;
+    add_labelled(&api, "web-1", json!({ "role": "web", "env": "prod" })).await;
+    api.add_server("web-2").await;
+
+    let resp = api.call("GET", "/servers?label=role=db", None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(names(&resp), ["db-1", "db-2"]);
+
+    let resp = api
+        .call("GET", "/servers?label=role=db&label=env=prod", None)
+        .await;
+    assert_eq!(names(&resp), ["db-1"]);
+
+    let resp = api.call("GET", "/servers?label=role=cache", None).await;
+    assert_eq!(body(&resp)["count"], 0);
+
+    for selector in ["role", "=db"] {
+        let resp = api
+            .call("GET", &format!("/servers?label={}", selector), None)
+            .await;
+        assert_eq!(resp.status(), 400);
+        assert_eq!(error_code(&resp), "invalid_parameter");
+        assert_eq!(body(&resp)["details"][0]["field"], "label");
+    }
+}
+
+#[tokio::test]
+async fn labels_are_replaced_as_a_whole() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let uri = format!("/servers/{}", server_id);
+
+    let resp = api
+        .call(
+            "PUT",
+            &uri,
+            Some(json!({ "labels": { "role": "web", "env": "prod" } })),
+        )
+        .await;
+    assert_eq!(resp.status(), 200);
+    let resp = api
+        .call("PUT", &uri, Some(json!({ "labels": { "role": "db" } })))
+        .await;
+    assert_eq!(resp.status(), 200);
+    let resp = api.call("GET", &uri, None).await;
+    assert_eq!(body(&resp)["labels"], json!({ "role": "db" }));
+
+    // Leaving labels out keeps them
+    api.call("PUT", &uri, Some(json!({ "description": "Database" })))
+        .await;
+    let resp = api.call("GET", &uri, None).await;
+    assert_eq!(body(&resp)["labels"], json!({ "role": "db" }));
+
+    let resp = api
+        .call("PUT", &uri, Some(json!({ "labels": { "a=b": "c" } })))
+        .await;
+    assert_eq!(resp.status(), 422);
+    assert_eq!(body(&resp)["details"][0]["field"], "labels");
+}
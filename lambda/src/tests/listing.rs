@@ -0,0 +1,65 @@
+//! Paging through `GET /servers`.
+
+use serde_json::Value;
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn list_servers_pages_through_every_server_once() {
+    let api = TestApi::new().await;
+    for name in ["web-1", "web-2", "web-3", "web-4", "web-5"] {
+        api.add_server(name).await;
+    }
+
+    let resp = api.call("GET", "/servers", None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["count"], 5);
+    assert!(body(&resp)["next_cursor"].is_null());
+    let all: Vec<String> = body(&resp)["servers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|server| server["server_id"].as_str().unwrap().to_string())
+        .collect();
+
+    let mut listed = Vec::new();
+    let mut uri = "/servers?limit=2".to_string();
+    loop {
+        let resp = api.call("GET", &uri, None).await;
+        assert_eq!(resp.status(), 200);
+        let page = body(&resp);
+        assert!(page["count"].as_u64().unwrap() <= 2);
+        listed.extend(
+            page["servers"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|server| server["server_id"].as_str().unwrap().to_string()),
+        );
+        match &page["next_cursor"] {
+            Value::String(cursor) => uri = format!("/servers?limit=2&cursor={}", cursor),
+            _ => break,
+        }
+    }
+    assert_eq!(listed, all);
+}
+
+#[tokio::test]
+async fn list_servers_rejects_invalid_paging() {
+    let api = TestApi::new().await;
+
+    for uri in [
+        "/servers?limit=0",
+        "/servers?limit=1001",
+        "/servers?limit=ten",
+    ] {
+        let resp = api.call("GET", uri, None).await;
+        assert_eq!(resp.status(), 400);
+        assert_eq!(error_code(&resp), "invalid_parameter");
+        assert_eq!(body(&resp)["details"][0]["field"], "limit");
+    }
+
+    let resp = api.call("GET", "/servers?cursor=garbage", None).await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(body(&resp)["details"][0]["field"], "cursor");
+}
@@ -0,0 +1,229 @@
+//! Requests routed through `function_handler` against a store, checking
+//! status codes, error codes and `ETag`s the way a client sees them.
+//!
+//! `TestApi` is shared by the submodules, one per area of the API.
+
+use homelab_api::api_key::Role;
+use homelab_api::audit_event::AuditAction;
+use lambda_http::{Body, Request, RequestExt, Response};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::auth::{self, Auth};
+use crate::function_handler;
+use crate::store::json_file::JsonFileStore;
+use crate::store::memory::MemoryStore;
+use crate::store::sqlite::SqliteStore;
+use crate::store::{test_audit, ServerStore};
+
+mod api_keys;
+mod audit_log;
+mod config_files;
+mod config_paths;
+mod errors;
+mod flakes;
+mod get_server;
+mod history;
+mod labels;
+mod listing;
+mod modules;
+mod roles;
+mod rollback;
+mod server_names;
+mod trash;
+mod unique_names;
+mod validation;
+mod versions;
+
+/// A store with an admin key, and the token of that key.
+struct TestApi {
+    store: Box<dyn ServerStore>,
+    auth: Auth,
+    token: String,
+}
+
+impl TestApi {
+    /// An API backed by a `MemoryStore`.
+    async fn new() -> Self {
+        Self::with_store(MemoryStore::new()).await
+    }
+
+    async fn with_store(store: impl ServerStore + 'static) -> Self {
+        let auth = Auth::new(true, Role::ReadOnly, Vec::new());
+        let token = create_key(&store, Role::Admin).await;
+        Self {
+            store: Box::new(store),
+            auth,
+            token,
+        }
+    }
+
+    fn request(&self, method: &str, uri: &str) -> http::request::Builder {
+        http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", self.token))
+    }
+
+    /// The token of a new key with `role`.
+    async fn token(&self, role: Role) -> String {
+        create_key(self.store.as_ref(), role).await
+    }
+
+    async fn call(&self, method: &str, uri: &str, body: Option<Value>) -> Response<Body> {
+        self.send(self.request(method, uri), body).await
+    }
+
+    async fn send(&self, request: http::request::Builder, body: Option<Value>) -> Response<Body> {
+        let body = match body {
+            Some(body) => Body::from(body.to_string()),
+            None => Body::Empty,
+        };
+        let request: Request = request.body(body).expect("valid request");
+
+        let mut query: HashMap<String, Vec<String>> = HashMap::new();
+        if let Some(raw) = request.uri().query() {
+            for (key, value) in form_urlencoded::parse(raw.as_bytes()) {
+                query
+                    .entry(key.into_owned())
+                    .or_default()
+                    .push(value.into_owned());
+            }
+        }
+        let request = request.with_query_string_parameters(query);
+
+        function_handler(self.store.as_ref(), &self.auth, request)
+            .await
+            .expect("handler response")
+    }
+
+    /// Adds a server named `name` and returns its id.
+    async fn add_server(&self, name: &str) -> String {
+        let resp = self
+            .call(
+                "POST",
+                "/servers",
+                Some(json!({ "server_name": name, "config_file_path": "/etc/nixos/web.nix" })),
+            )
+            .await;
+        assert_eq!(resp.status(), 201);
+        body(&resp)["server_id"].as_str().unwrap().to_string()
+    }
+}
+
+async fn create_key(store: &dyn ServerStore, role: Role) -> String {
+    let (api_key, token) =
+        auth::new_api_key(format!("{:?}", role), role, "test".to_string()).unwrap();
+    store
+        .create_api_key(api_key, &test_audit(AuditAction::Create))
+        .await
+        .unwrap();
+    token
+}
+
+fn body(resp: &Response<Body>) -> Value {
+    match resp.body() {
+        Body::Text(text) => serde_json::from_str(text).expect("JSON body"),
+        other => panic!("Expected a text body, got {:?}", other),
+    }
+}
+
+fn error_code(resp: &Response<Body>) -> String {
+    body(resp)["code"].as_str().expect("error code").to_string()
+}
+
+fn etag(resp: &Response<Body>) -> Option<&str> {
+    resp.headers()
+        .get("ETag")
+        .map(|value| value.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn every_backend_serves_the_same_api() {
+    let dir = std::env::temp_dir().join(format!("homelab-api-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir(&dir).unwrap();
+    let apis = [
+        TestApi::new().await,
+        TestApi::with_store(SqliteStore::open(":memory:").unwrap()).await,
+        TestApi::with_store(JsonFileStore::open(dir.join("servers.json")).await.unwrap()).await,
+    ];
+
+    for api in apis {
+        let server_id = api.add_server("web-1").await;
+        let uri = format!("/servers/{}", server_id);
+
+        let resp = api.call("GET", "/servers", None).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(body(&resp)["count"], 1);
+        assert_eq!(body(&resp)["servers"][0]["server_id"], server_id.as_str());
+
+        let resp = api
+            .call("PUT", &uri, Some(json!({ "description": "Front end" })))
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp = api.call("GET", &uri, None).await;
+        assert_eq!(body(&resp)["description"], "Front end");
+        assert_eq!(body(&resp)["config_file_path"], "/etc/nixos/web.nix");
+
+        let resp = api.call("DELETE", &uri, None).await;
+        assert_eq!(resp.status(), 200);
+        let resp = api.call("GET", "/servers", None).await;
+        assert_eq!(body(&resp)["count"], 0);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn unknown_routes_are_not_found() {
+    let api = TestApi::new().await;
+
+    let resp = api.call("GET", "/nowhere", None).await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "route_not_found");
+
+    let resp = api.call("PATCH", "/servers", None).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn responses_carry_cors_headers() {
+    let api = TestApi::new().await;
+
+    let resp = api.call("GET", "/servers", None).await;
+    assert_eq!(
+        resp.headers().get("Access-Control-Allow-Origin").unwrap(),
+        "*"
+    );
+    assert_eq!(
+        resp.headers().get("Access-Control-Expose-Headers").unwrap(),
+        "ETag, X-Request-Id"
+    );
+
+    let resp = api.call("GET", "/nowhere", None).await;
+    assert_eq!(
+        resp.headers().get("Access-Control-Allow-Origin").unwrap(),
+        "*"
+    );
+}
+
+#[tokio::test]
+async fn preflights_are_answered_without_credentials() {
+    let api = TestApi::new().await;
+
+    let preflight = http::Request::builder()
+        .method("OPTIONS")
+        .uri("/servers/server-1")
+        .header("Origin", "https://dashboard.example.com")
+        .header("Access-Control-Request-Method", "PUT")
+        .header("Access-Control-Request-Headers", "authorization, if-match");
+    let resp = api.send(preflight, None).await;
+    assert_eq!(resp.status(), 204);
+    assert_eq!(
+        resp.headers().get("Access-Control-Allow-Methods").unwrap(),
+        "GET, POST, PUT, DELETE, OPTIONS"
+    );
+    assert!(resp.headers()["Access-Control-Allow-Headers"]
+        .to_str()
+        .unwrap()
+        .contains("If-Match"));
+}
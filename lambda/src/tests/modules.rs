@@ -0,0 +1,88 @@
+//! NixOS modules servers import alongside their configuration.
+
+use serde_json::json;
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn modules_are_changed_in_import_order() {
+    let api = TestApi::new().await;
+    let new_server = json!({
+        "server_name": "web-1",
+        "config_file_path": "/etc/nixos/web.nix",
+        "modules": ["/etc/nixos/common.nix", "/etc/nixos/users.nix"],
+    });
+    let resp = api.call("POST", "/servers", Some(new_server)).await;
+    assert_eq!(resp.status(), 201);
+    let server_id = body(&resp)["server_id"].as_str().unwrap().to_string();
+
+    let changes = json!({
+        "add_modules": ["/etc/nixos/common.nix", "/etc/nixos/backup.nix"],
+        "remove_modules": ["/etc/nixos/users.nix"],
+    });
+    let resp = api
+        .call("PUT", &format!("/servers/{}", server_id), Some(changes))
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = api
+        .call("GET", &format!("/servers/{}", server_id), None)
+        .await;
+    assert_eq!(
+        body(&resp)["modules"],
+        json!(["/etc/nixos/common.nix", "/etc/nixos/backup.nix"])
+    );
+
+    // Other changes leave the modules alone
+    let changes = json!({ "description": "Web server" });
+    api.call("PUT", &format!("/servers/{}", server_id), Some(changes))
+        .await;
+    let resp = api
+        .call("GET", &format!("/servers/{}", server_id), None)
+        .await;
+    assert_eq!(body(&resp)["modules"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn servers_importing_a_config_file_as_a_module() {
+    let api = TestApi::new().await;
+    let resp = api
+        .call(
+            "POST",
+            "/configs",
+            Some(json!({ "path": "/etc/nixos/common.nix" })),
+        )
+        .await;
+    let config_id = body(&resp)["config_id"].as_str().unwrap().to_string();
+
+    let new_server = json!({
+        "server_name": "web-1",
+        "config_file_path": "/etc/nixos/web.nix",
+        "modules": ["/etc/nixos/common.nix"],
+    });
+    let resp = api.call("POST", "/servers", Some(new_server)).await;
+    assert_eq!(resp.status(), 201);
+    let server_id = body(&resp)["server_id"].as_str().unwrap().to_string();
+
+    let resp = api
+        .call("GET", &format!("/configs/{}/servers", config_id), None)
+        .await;
+    assert_eq!(body(&resp)["servers"][0]["server_id"], server_id.as_str());
+
+    let resp = api
+        .call("DELETE", &format!("/configs/{}", config_id), None)
+        .await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "config_file_in_use");
+    assert_eq!(body(&resp)["server_ids"][0], server_id.as_str());
+
+    let changes = json!({ "remove_modules": ["/etc/nixos/common.nix"] });
+    let resp = api
+        .call("PUT", &format!("/servers/{}", server_id), Some(changes))
+        .await;
+    assert_eq!(resp.status(), 200);
+    let resp = api
+        .call("DELETE", &format!("/configs/{}", config_id), None)
+        .await;
+    assert_eq!(resp.status(), 200);
+}
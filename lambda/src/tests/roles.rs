@@ -0,0 +1,88 @@
+//! What each role may do.
+
+use homelab_api::api_key::Role;
+use serde_json::json;
+
+use super::{body, error_code, TestApi};
+use crate::auth::Auth;
+use crate::required_role;
+
+#[test]
+fn routes_require_the_least_role_that_may_call_them() {
+    assert_eq!(
+        required_role(&http::Method::GET, &["servers"]),
+        Role::ReadOnly
+    );
+    assert_eq!(
+        required_role(&http::Method::POST, &["servers"]),
+        Role::Operator
+    );
+    assert_eq!(
+        required_role(&http::Method::PUT, &["servers", "server-1"]),
+        Role::Operator
+    );
+    assert_eq!(
+        required_role(&http::Method::DELETE, &["servers", "server-1"]),
+        Role::Admin
+    );
+    assert_eq!(required_role(&http::Method::GET, &["keys"]), Role::Admin);
+}
+
+#[tokio::test]
+async fn anonymous_requests_are_admin_when_auth_is_not_required() {
+    let mut api = TestApi::new().await;
+    api.auth = Auth::new(false, Role::ReadOnly, Vec::new());
+
+    let resp = api
+        .send(http::Request::builder().method("GET").uri("/whoami"), None)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["authenticated_by"], "anonymous");
+    assert_eq!(body(&resp)["role"], "admin");
+}
+
+#[tokio::test]
+async fn roles_limit_routes() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let read_only = api.token(Role::ReadOnly).await;
+    let operator = api.token(Role::Operator).await;
+
+    let as_role = |token: &str, method: &str, uri: &str| {
+        http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", token))
+    };
+
+    let resp = api.send(as_role(&read_only, "GET", "/servers"), None).await;
+    assert_eq!(resp.status(), 200);
+
+    let new_server = json!({ "server_name": "web-2", "config_file_path": "/etc/nixos/web.nix" });
+    let resp = api
+        .send(
+            as_role(&read_only, "POST", "/servers"),
+            Some(new_server.clone()),
+        )
+        .await;
+    assert_eq!(resp.status(), 403);
+    assert_eq!(error_code(&resp), "forbidden");
+    assert_eq!(body(&resp)["required_role"], "operator");
+
+    let resp = api
+        .send(as_role(&operator, "POST", "/servers"), Some(new_server))
+        .await;
+    assert_eq!(resp.status(), 201);
+
+    let resp = api
+        .send(
+            as_role(&operator, "DELETE", &format!("/servers/{}", server_id)),
+            None,
+        )
+        .await;
+    assert_eq!(resp.status(), 403);
+    assert_eq!(body(&resp)["required_role"], "admin");
+
+    let resp = api.send(as_role(&operator, "GET", "/keys"), None).await;
+    assert_eq!(resp.status(), 403);
+}
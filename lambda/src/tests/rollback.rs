@@ -0,0 +1,109 @@
+//! Rolling a server back to an earlier revision.
+
+use serde_json::json;
+
+use super::{body, error_code, etag, TestApi};
+
+#[tokio::test]
+async fn rollback_restores_the_revision_as_a_new_one() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let uri = format!("/servers/{}", server_id);
+    let changes = json!({
+        "server_name": "web-2",
+        "description": "Front end",
+        "labels": { "role": "web" },
+        "modules": ["/etc/nixos/common.nix"],
+    });
+    api.call("PUT", &uri, Some(changes)).await;
+
+    let resp = api
+        .call(
+            "POST",
+            &format!("{}/rollback", uri),
+            Some(json!({ "revision": 1 })),
+        )
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(etag(&resp), Some("\"3\""));
+    assert_eq!(body(&resp)["restored_revision"], 1);
+    assert_eq!(body(&resp)["version"], 3);
+
+    let resp = api.call("GET", &uri, None).await;
+    let server = body(&resp);
+    assert_eq!(server["server_name"], "web-1");
+    assert!(server["description"].is_null());
+    assert!(server["labels"].is_null());
+    assert!(server["modules"].is_null());
+
+    // The rollback is a revision of its own, so it can be undone too
+    let resp = api
+        .call(
+            "POST",
+            &format!("{}/rollback", uri),
+            Some(json!({ "revision": 2 })),
+        )
+        .await;
+    assert_eq!(resp.status(), 200);
+    let resp = api.call("GET", &uri, None).await;
+    assert_eq!(body(&resp)["server_name"], "web-2");
+    assert_eq!(body(&resp)["labels"]["role"], "web");
+    let resp = api.call("GET", &format!("{}/history", uri), None).await;
+    assert_eq!(body(&resp)["count"], 4);
+}
+
+#[tokio::test]
+async fn rollback_cannot_take_a_name_now_in_use() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let uri = format!("/servers/{}", server_id);
+    api.call("PUT", &uri, Some(json!({ "server_name": "web-2" })))
+        .await;
+    let other_id = api.add_server("web-1").await;
+
+    let resp = api
+        .call(
+            "POST",
+            &format!("{}/rollback", uri),
+            Some(json!({ "revision": 1 })),
+        )
+        .await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "name_conflict");
+    assert_eq!(body(&resp)["existing_server_id"], other_id.as_str());
+}
+
+#[tokio::test]
+async fn rollback_to_unknown_revisions_or_servers_is_not_found() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+
+    let resp = api
+        .call(
+            "POST",
+            &format!("/servers/{}/rollback", server_id),
+            Some(json!({ "revision": 42 })),
+        )
+        .await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "revision_not_found");
+
+    let resp = api
+        .call(
+            "POST",
+            "/servers/missing/rollback",
+            Some(json!({ "revision": 1 })),
+        )
+        .await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "server_not_found");
+
+    let resp = api
+        .call(
+            "POST",
+            &format!("/servers/{}/rollback", server_id),
+            Some(json!({ "revision": "first" })),
+        )
+        .await;
+    assert_eq!(resp.status(), 422);
+}
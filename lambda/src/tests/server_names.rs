@@ -0,0 +1,45 @@
+//! Looking servers up by name with `GET /servers/by-name/{name}`.
+
+use super::{body, TestApi};
+
+#[tokio::test]
+async fn find_servers_by_name() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    api.add_server("web-2").await;
+
+    let resp = api.call("GET", "/servers/by-name/web-1", None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["count"], 1);
+    assert_eq!(body(&resp)["servers"][0]["server_id"], server_id.as_str());
+
+    let resp = api.call("GET", "/servers/by-name/web-3", None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["count"], 0);
+}
+
+#[tokio::test]
+async fn servers_in_the_trash_are_only_found_on_request() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    api.call("DELETE", &format!("/servers/{}", server_id), None)
+        .await;
+
+    let resp = api.call("GET", "/servers/by-name/web-1", None).await;
+    assert_eq!(body(&resp)["count"], 0);
+
+    let resp = api
+        .call("GET", "/servers/by-name/web-1?include_deleted=true", None)
+        .await;
+    assert_eq!(body(&resp)["count"], 1);
+    assert_eq!(body(&resp)["servers"][0]["server_id"], server_id.as_str());
+
+    // The name is free again, so a new server can take it
+    let new_id = api.add_server("web-1").await;
+    let resp = api
+        .call("GET", "/servers/by-name/web-1?include_deleted=true", None)
+        .await;
+    assert_eq!(body(&resp)["count"], 2);
+    let resp = api.call("GET", "/servers/by-name/web-1", None).await;
+    assert_eq!(body(&resp)["servers"][0]["server_id"], new_id.as_str());
+}
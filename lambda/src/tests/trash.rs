@@ -0,0 +1,92 @@
+//! The trash: deleting, restoring and purging servers.
+
+use serde_json::json;
+
+use super::{body, error_code, etag, TestApi};
+
+#[tokio::test]
+async fn delete_restore_and_purge() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let uri = format!("/servers/{}", server_id);
+
+    let resp = api.call("POST", &format!("{}/restore", uri), None).await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "server_not_deleted");
+
+    let resp = api.call("DELETE", &uri, None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["purged"], false);
+
+    let resp = api.call("DELETE", &uri, None).await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "server_deleted");
+
+    let resp = api
+        .call("PUT", &uri, Some(json!({ "description": "Front end" })))
+        .await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "server_deleted");
+
+    let resp = api
+        .call(
+            "POST",
+            &format!("{}/rollback", uri),
+            Some(json!({ "revision": 1 })),
+        )
+        .await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "server_deleted");
+
+    let resp = api.call("GET", "/servers", None).await;
+    assert_eq!(body(&resp)["count"], 0);
+    let resp = api.call("GET", "/servers?include_deleted=true", None).await;
+    assert_eq!(body(&resp)["count"], 1);
+
+    let resp = api.call("POST", &format!("{}/restore", uri), None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(etag(&resp), Some("\"3\""));
+
+    let resp = api
+        .call("DELETE", &format!("{}?purge=true", uri), None)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(body(&resp)["purged"], true);
+
+    let resp = api.call("GET", &uri, None).await;
+    assert_eq!(resp.status(), 404);
+
+    let resp = api.call("DELETE", "/servers/missing", None).await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "server_not_found");
+
+    let resp = api
+        .call("DELETE", "/servers/missing?purge=maybe", None)
+        .await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(body(&resp)["details"][0]["field"], "purge");
+}
+
+#[tokio::test]
+async fn restore_fails_once_the_name_is_taken() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let uri = format!("/servers/{}", server_id);
+    api.call("DELETE", &uri, None).await;
+    let other_id = api.add_server("web-1").await;
+
+    let resp = api.call("POST", &format!("{}/restore", uri), None).await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "name_conflict");
+    assert_eq!(body(&resp)["existing_server_id"], other_id.as_str());
+
+    // Renaming the other server frees the name again
+    api.call(
+        "PUT",
+        &format!("/servers/{}", other_id),
+        Some(json!({ "server_name": "web-2" })),
+    )
+    .await;
+    let resp = api.call("POST", &format!("{}/restore", uri), None).await;
+    assert_eq!(resp.status(), 200);
+}
@@ -0,0 +1,52 @@
+//! Server names are unique among servers outside the trash.
+
+use serde_json::json;
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn server_names_are_unique() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+
+    let resp = api
+        .call(
+            "POST",
+            "/servers",
+            Some(json!({ "server_name": "web-1", "config_file_path": "/etc/nixos/other.nix" })),
+        )
+        .await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "name_conflict");
+    assert_eq!(body(&resp)["existing_server_id"], server_id.as_str());
+}
+
+#[tokio::test]
+async fn renames_cannot_take_a_used_name() {
+    let api = TestApi::new().await;
+    let taken_id = api.add_server("web-1").await;
+    let server_id = api.add_server("web-2").await;
+    let uri = format!("/servers/{}", server_id);
+
+    let resp = api
+        .call("PUT", &uri, Some(json!({ "server_name": "web-1" })))
+        .await;
+    assert_eq!(resp.status(), 409);
+    assert_eq!(error_code(&resp), "name_conflict");
+    assert_eq!(body(&resp)["existing_server_id"], taken_id.as_str());
+
+    // Keeping its own name is not a conflict
+    let resp = api
+        .call("PUT", &uri, Some(json!({ "server_name": "web-2" })))
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = api
+        .call("PUT", &uri, Some(json!({ "server_name": "web-3" })))
+        .await;
+    assert_eq!(resp.status(), 200);
+    let resp = api.call("GET", &uri, None).await;
+    assert_eq!(body(&resp)["server_name"], "web-3");
+    let resp = api.call("GET", "/servers/by-name/web-2", None).await;
+    assert_eq!(body(&resp)["count"], 0);
+}
@@ -0,0 +1,79 @@
+//! Request bodies that fail validation, reported field by field.
+
+use serde_json::json;
+
+use super::{body, error_code, TestApi};
+
+#[tokio::test]
+async fn add_server_rejects_invalid_bodies() {
+    let api = TestApi::new().await;
+
+    let resp = api.call("POST", "/servers", None).await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(error_code(&resp), "invalid_body");
+
+    let resp = api
+        .call("POST", "/servers", Some(json!(["not", "an", "object"])))
+        .await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(error_code(&resp), "invalid_body");
+
+    let resp = api
+        .call(
+            "POST",
+            "/servers",
+            Some(json!({ "server_name": "web-1", "colour": "red" })),
+        )
+        .await;
+    assert_eq!(resp.status(), 422);
+    assert_eq!(error_code(&resp), "invalid_field");
+    assert_eq!(body(&resp)["details"][0]["field"], "colour");
+
+    let resp = api
+        .call(
+            "POST",
+            "/servers",
+            Some(json!({ "server_name": "-web-", "config_file_path": "etc/web" })),
+        )
+        .await;
+    assert_eq!(resp.status(), 422);
+    let details = body(&resp)["details"].as_array().unwrap().clone();
+    let fields: Vec<&str> = details
+        .iter()
+        .map(|detail| detail["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["server_name", "config_file_path"]);
+
+    let resp = api
+        .call("POST", "/servers", Some(json!({ "server_name": "web-1" })))
+        .await;
+    assert_eq!(resp.status(), 422);
+    assert_eq!(body(&resp)["details"][0]["field"], "config_file_path");
+}
+
+#[tokio::test]
+async fn update_server_rejects_invalid_fields() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+
+    let changes = json!({ "labels": { "a=b": "c" }, "add_modules": ["", "/etc/nixos/common.nix"] });
+    let resp = api
+        .call("PUT", &format!("/servers/{}", server_id), Some(changes))
+        .await;
+    assert_eq!(resp.status(), 422);
+    let details = body(&resp)["details"].as_array().unwrap().clone();
+    let fields: Vec<&str> = details
+        .iter()
+        .map(|detail| detail["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields[0], "labels");
+    assert!(fields[1..]
+        .iter()
+        .all(|field| field.starts_with("add_modules")));
+
+    // Nothing was changed
+    let resp = api
+        .call("GET", &format!("/servers/{}", server_id), None)
+        .await;
+    assert_eq!(body(&resp)["version"], 1);
+}
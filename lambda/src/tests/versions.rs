@@ -0,0 +1,107 @@
+//! Server versions, `ETag`s and conditional changes with `If-Match`.
+
+use serde_json::json;
+
+use super::{body, error_code, etag, TestApi};
+
+#[tokio::test]
+async fn add_server_returns_created_with_etag() {
+    let api = TestApi::new().await;
+
+    let resp = api
+        .call(
+            "POST",
+            "/servers",
+            Some(json!({ "server_name": "web-1", "config_file_path": "/etc/nixos/web.nix" })),
+        )
+        .await;
+    assert_eq!(resp.status(), 201);
+    assert_eq!(etag(&resp), Some("\"1\""));
+    assert_eq!(body(&resp)["version"], 1);
+
+    let server_id = body(&resp)["server_id"].as_str().unwrap().to_string();
+    let resp = api
+        .call("GET", &format!("/servers/{}", server_id), None)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(etag(&resp), Some("\"1\""));
+    assert_eq!(body(&resp)["server_name"], "web-1");
+    assert_eq!(body(&resp)["created_by"], body(&resp)["updated_by"]);
+}
+
+#[tokio::test]
+async fn update_server_bumps_version_and_checks_if_match() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let uri = format!("/servers/{}", server_id);
+
+    let resp = api
+        .call("PUT", &uri, Some(json!({ "description": "Front end" })))
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(etag(&resp), Some("\"2\""));
+    assert_eq!(body(&resp)["version"], 2);
+
+    let stale = api.request("PUT", &uri).header("If-Match", "\"1\"");
+    let resp = api
+        .send(stale, Some(json!({ "description": "Back end" })))
+        .await;
+    assert_eq!(resp.status(), 412);
+    assert_eq!(error_code(&resp), "version_mismatch");
+    assert_eq!(body(&resp)["current_version"], 2);
+
+    let current = api.request("PUT", &uri).header("If-Match", "W/\"2\"");
+    let resp = api
+        .send(current, Some(json!({ "description": "Back end" })))
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(etag(&resp), Some("\"3\""));
+
+    let invalid = api.request("PUT", &uri).header("If-Match", "three");
+    let resp = api
+        .send(invalid, Some(json!({ "description": "Back end" })))
+        .await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(body(&resp)["details"][0]["field"], "If-Match");
+
+    let resp = api
+        .call(
+            "PUT",
+            "/servers/missing",
+            Some(json!({ "description": "Nothing" })),
+        )
+        .await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(error_code(&resp), "server_not_found");
+}
+
+#[tokio::test]
+async fn deletes_and_restores_check_if_match() {
+    let api = TestApi::new().await;
+    let server_id = api.add_server("web-1").await;
+    let uri = format!("/servers/{}", server_id);
+
+    let stale = api.request("DELETE", &uri).header("If-Match", "\"2\"");
+    let resp = api.send(stale, None).await;
+    assert_eq!(resp.status(), 412);
+    assert_eq!(body(&resp)["current_version"], 1);
+
+    let current = api.request("DELETE", &uri).header("If-Match", "\"1\"");
+    let resp = api.send(current, None).await;
+    assert_eq!(resp.status(), 200);
+
+    let stale = api
+        .request("POST", &format!("{}/restore", uri))
+        .header("If-Match", "\"1\"");
+    let resp = api.send(stale, None).await;
+    assert_eq!(resp.status(), 412);
+    assert_eq!(error_code(&resp), "version_mismatch");
+    assert_eq!(body(&resp)["current_version"], 2);
+
+    let any = api
+        .request("POST", &format!("{}/restore", uri))
+        .header("If-Match", "*");
+    let resp = api.send(any, None).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(etag(&resp), Some("\"3\""));
+}
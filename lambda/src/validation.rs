@@ -0,0 +1,312 @@
+//! Checks request bodies before anything is stored.
+//!
+//! Every problem found is collected, so a client learns about all of its
+//! invalid fields from one `422 Unprocessable Entity` response.
+
+use homelab_api::api_key::CreateApiKeyRequest;
+use homelab_api::config_file::CreateConfigFileRequest;
+use homelab_api::flake_ref::FlakeRef;
+use homelab_api::responses::FieldError;
+use homelab_api::server_config::{
+    invalid_label_key, invalid_modules, CreateServerRequest, Labels, UpdateServerRequest,
+};
+
+use crate::error::ApiError;
+
+/// Longest server name; the limit for a fully qualified host name.
+const MAX_SERVER_NAME_LENGTH: usize = 253;
+/// Longest dot-separated part of a server name.
+const MAX_SERVER_NAME_PART_LENGTH: usize = 63;
+const MAX_PATH_LENGTH: usize = 1024;
+const MAX_DESCRIPTION_LENGTH: usize = 1024;
+const MAX_OWNER_LENGTH: usize = 256;
+const MAX_LABELS: usize = 64;
+const MAX_LABEL_KEY_LENGTH: usize = 63;
+const MAX_LABEL_VALUE_LENGTH: usize = 256;
+pub(crate) const MAX_MODULES: usize = 64;
+const MAX_KEY_NAME_LENGTH: usize = 128;
+
+/// Field errors found so far.
+#[derive(Default)]
+struct Violations(Vec<FieldError>);
+
+impl Violations {
+    fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    fn check_length(&mut self, field: &str, value: &str, max: usize) {
+        if value.chars().count() > max {
+            self.add(
+                field,
+                format!("{} must be at most {} characters", field, max),
+            );
+        }
+    }
+
+    fn into_result(self) -> Result<(), ApiError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::InvalidFields(self.0))
+        }
+    }
+}
+
+pub fn validate_create_server(request: &CreateServerRequest) -> Result<(), ApiError> {
+    let mut violations = Violations::default();
+    check_server_name(&mut violations, &request.server_name);
+    if let Some(path) = &request.config_file_path {
+        check_config_path(&mut violations, "config_file_path", path);
+    }
+    if let Some(flake) = &request.flake {
+        check_flake(&mut violations, flake);
+    }
+    if let Some(description) = &request.description {
+        violations.check_length("description", description, MAX_DESCRIPTION_LENGTH);
+    }
+    check_labels(&mut violations, &request.labels);
+    check_modules(&mut violations, "modules", &request.modules);
+    violations.into_result()
+}
+
+pub fn validate_update_server(request: &UpdateServerRequest) -> Result<(), ApiError> {
+    let mut violations = Violations::default();
+    if let Some(server_name) = &request.server_name {
+        check_server_name(&mut violations, server_name);
+    }
+    if let Some(path) = &request.config_file_path {
+        check_config_path(&mut violations, "config_file_path", path);
+    }
+    if let Some(flake) = &request.flake {
+        check_flake(&mut violations, flake);
+    }
+    if let Some(description) = &request.description {
+        violations.check_length("description", description, MAX_DESCRIPTION_LENGTH);
+    }
+    if let Some(labels) = &request.labels {
+        check_labels(&mut violations, labels);
+    }
+    if let Some(modules) = &request.modules {
+        check_modules(&mut violations, "modules", modules);
+    }
+    // Only modules being added are checked, so entries stored before the rules
+    // tightened can still be removed; the store checks the resulting count
+    check_modules(&mut violations, "add_modules", &request.add_modules);
+    violations.into_result()
+}
+
+pub fn validate_create_config_file(request: &CreateConfigFileRequest) -> Result<(), ApiError> {
+    let mut violations = Violations::default();
+    check_config_path(&mut violations, "path", &request.path);
+    if let Some(description) = &request.description {
+        violations.check_length("description", description, MAX_DESCRIPTION_LENGTH);
+    }
+    if let Some(owner) = &request.owner {
+        violations.check_length("owner", owner, MAX_OWNER_LENGTH);
+    }
+    violations.into_result()
+}
+
+pub fn validate_create_api_key(request: &CreateApiKeyRequest) -> Result<(), ApiError> {
+    let mut violations = Violations::default();
+    if request.name.trim().is_empty() {
+        violations.add("name", "name must not be empty");
+    }
+    violations.check_length("name", &request.name, MAX_KEY_NAME_LENGTH);
+    violations.into_result()
+}
+
+/// Server names must be usable as host names: dot-separated parts of letters,
+/// digits and `-`, none starting or ending with `-`.
+fn check_server_name(violations: &mut Violations, name: &str) {
+    const FIELD: &str = "server_name";
+
+    if name.is_empty() {
+        return violations.add(FIELD, "server_name must not be empty");
+    }
+    if name.len() > MAX_SERVER_NAME_LENGTH {
+        return violations.add(
+            FIELD,
+            format!(
+                "server_name must be at most {} characters",
+                MAX_SERVER_NAME_LENGTH
+            ),
+        );
+    }
+    for part in name.split('.') {
+        if part.is_empty() {
+            return violations.add(
+                FIELD,
+                "server_name must not start or end with '.' or contain '..'",
+            );
+        }
+        if !part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return violations.add(
+                FIELD,
+                "server_name may only contain letters, digits, '-' and '.'",
+            );
+        }
+        if part.starts_with('-') || part.ends_with('-') {
+            return violations.add(
+                FIELD,
+                format!("server_name part {:?} must not start or end with '-'", part),
+            );
+        }
+        if part.len() > MAX_SERVER_NAME_PART_LENGTH {
+            return violations.add(
+                FIELD,
+                format!(
+                    "server_name parts must be at most {} characters",
+                    MAX_SERVER_NAME_PART_LENGTH
+                ),
+            );
+        }
+    }
+}
+
+/// Configuration paths are absolute paths to a `.nix` file, or a flake
+/// reference written as `uri#attribute`.
+fn check_config_path(violations: &mut Violations, field: &str, path: &str) {
+    if path.is_empty() {
+        return violations.add(field, format!("{} must not be empty", field));
+    }
+    if path.chars().count() > MAX_PATH_LENGTH {
+        return violations.add(
+            field,
+            format!("{} must be at most {} characters", field, MAX_PATH_LENGTH),
+        );
+    }
+    if let Some((uri, attribute)) = path.split_once('#') {
+        let flake = FlakeRef {
+            uri: uri.to_string(),
+            attribute: attribute.to_string(),
+            rev: None,
+        };
+        if let Err(error) = flake.validate() {
+            violations.add(field, error);
+        }
+        return;
+    }
+    if !path.starts_with('/') || !path.ends_with(".nix") {
+        violations.add(
+            field,
+            format!(
+                "{} must be an absolute path to a .nix file or a flake reference (uri#attribute)",
+                field
+            ),
+        );
+    }
+}
+
+fn check_flake(violations: &mut Violations, flake: &FlakeRef) {
+    if let Err(error) = flake.validate() {
+        violations.add("flake", error);
+    }
+    violations.check_length("flake.uri", &flake.uri, MAX_PATH_LENGTH);
+    violations.check_length("flake.attribute", &flake.attribute, MAX_PATH_LENGTH);
+}
+
+fn check_labels(violations: &mut Violations, labels: &Labels) {
+    if labels.len() > MAX_LABELS {
+        violations.add(
+            "labels",
+            format!("A server can have at most {} labels", MAX_LABELS),
+        );
+    }
+    if let Some(key) = invalid_label_key(labels) {
+        violations.add(
+            "labels",
+            format!(
+                "Invalid label key {:?}; keys must be non-empty and must not contain '='",
+                key
+            ),
+        );
+    }
+    if labels
+        .keys()
+        .any(|key| key.chars().count() > MAX_LABEL_KEY_LENGTH)
+    {
+        violations.add(
+            "labels",
+            format!(
+                "Label keys must be at most {} characters",
+                MAX_LABEL_KEY_LENGTH
+            ),
+        );
+    }
+    for (key, value) in labels {
+        if value.chars().count() > MAX_LABEL_VALUE_LENGTH {
+            violations.add(
+                format!("labels.{}", key),
+                format!(
+                    "Label values must be at most {} characters",
+                    MAX_LABEL_VALUE_LENGTH
+                ),
+            );
+        }
+    }
+}
+
+fn check_modules(violations: &mut Violations, field: &str, modules: &[String]) {
+    if modules.len() > MAX_MODULES {
+        violations.add(
+            field,
+            format!("A server can import at most {} modules", MAX_MODULES),
+        );
+    }
+    if let Some(error) = invalid_modules(modules) {
+        violations.add(field, error);
+    }
+    for (index, module) in modules.iter().enumerate() {
+        check_config_path(violations, &format!("{}[{}]", field, index), module);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn violated_fields(result: Result<(), ApiError>) -> Vec<String> {
+        match result {
+            Err(ApiError::InvalidFields(violations)) => violations
+                .into_iter()
+                .map(|violation| violation.field)
+                .collect(),
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn modules_must_be_config_paths() {
+        let request = UpdateServerRequest {
+            modules: Some(vec![
+                "/etc/nixos/base.nix".to_string(),
+                "base.nix".to_string(),
+            ]),
+            add_modules: vec!["/etc/nixos/backup".to_string()],
+            remove_modules: vec![
+                "relative/old.nix".to_string(),
+                "github:me/nixos#web".to_string(),
+            ],
+            ..Default::default()
+        };
+        let fields = violated_fields(validate_update_server(&request));
+        assert_eq!(fields, ["modules[1]", "add_modules[0]"]);
+    }
+
+    #[test]
+    fn invalid_modules_can_be_removed() {
+        let request = UpdateServerRequest {
+            remove_modules: vec![
+                "relative/old.nix".to_string(),
+                "relative/old.nix".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert!(validate_update_server(&request).is_ok());
+    }
+}
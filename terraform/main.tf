@@ -24,6 +24,66 @@ resource "aws_dynamodb_table" "homelab_servers" {
     type = "S"
   }
 
+  attribute {
+    name = "server_name"
+    type = "S"
+  }
+
+  attribute {
+    name = "config_file_path"
+    type = "S"
+  }
+
+  attribute {
+    name = "config_id"
+    type = "S"
+  }
+
+  attribute {
+    name = "listing"
+    type = "S"
+  }
+
+  attribute {
+    name = "created_key"
+    type = "S"
+  }
+
+  # Every server in creation order, for paged listings. `listing` is one of eight
+  # shards (servers#0 to servers#7) picked by a hash of the server ID, so list
+  # reads and server writes are spread over eight partitions rather than one;
+  # the Lambda queries every shard and merges them
+  global_secondary_index {
+    name            = "listing-index"
+    hash_key        = "listing"
+    range_key       = "created_key"
+    projection_type = "ALL"
+  }
+
+  # Lookup of servers by their human-readable name
+  global_secondary_index {
+    name            = "server_name-index"
+    hash_key        = "server_name"
+    projection_type = "ALL"
+  }
+
+  # Reverse lookup of the servers using a configuration file
+  global_secondary_index {
+    name            = "config_file_path-index"
+    hash_key        = "config_file_path"
+    projection_type = "ALL"
+  }
+
+  # Servers referencing a config file entity, checked before the config file is deleted
+  global_secondary_index {
+    name            = "config_id-index"
+    hash_key        = "config_id"
+    projection_type = "ALL"
+  }
+
+  # No TTL: only the scheduled purge-trash function may remove servers from the
+  # trash, as expiring an item would leave its name, path and module claims behind
+
   point_in_time_recovery {
     enabled = true
   }
@@ -35,6 +95,204 @@ resource "aws_dynamodb_table" "homelab_servers" {
   }
 }
 
+# DynamoDB table holding one item per server name, used to keep names unique
+resource "aws_dynamodb_table" "homelab_server_names" {
+  name         = "homelab-server-names"
+  billing_mode = "PAY_PER_REQUEST"
+  hash_key     = "server_name"
+
+  attribute {
+    name = "server_name"
+    type = "S"
+  }
+
+  point_in_time_recovery {
+    enabled = true
+  }
+
+  tags = {
+    Name        = "Homelab Server Names Table"
+    Project     = "homelab-manager"
+    Environment = var.environment
+  }
+}
+
+# DynamoDB table holding an immutable snapshot of every server change
+resource "aws_dynamodb_table" "homelab_server_revisions" {
+  name         = "homelab-server-revisions"
+  billing_mode = "PAY_PER_REQUEST"
+  hash_key     = "server_id"
+  range_key    = "revision"
+
+  attribute {
+    name = "server_id"
+    type = "S"
+  }
+
+  attribute {
+    name = "revision"
+    type = "N"
+  }
+
+  point_in_time_recovery {
+    enabled = true
+  }
+
+  tags = {
+    Name        = "Homelab Server Revisions Table"
+    Project     = "homelab-manager"
+    Environment = var.environment
+  }
+}
+
+# Registered NixOS configuration files that servers reference by config_id
+resource "aws_dynamodb_table" "homelab_config_files" {
+  name         = "homelab-config-files"
+  billing_mode = "PAY_PER_REQUEST"
+  hash_key     = "config_id"
+
+  attribute {
+    name = "config_id"
+    type = "S"
+  }
+
+  attribute {
+    name = "path"
+    type = "S"
+  }
+
+  # Lookup of config files by path
+  global_secondary_index {
+    name            = "path-index"
+    hash_key        = "path"
+    projection_type = "ALL"
+  }
+
+  point_in_time_recovery {
+    enabled = true
+  }
+
+  tags = {
+    Name        = "Homelab Config Files Table"
+    Project     = "homelab-manager"
+    Environment = var.environment
+  }
+}
+
+# One item per config file path, written in the same transaction as the config
+# file, so two config files can never share a path
+resource "aws_dynamodb_table" "homelab_config_paths" {
+  name         = "homelab-config-paths"
+  billing_mode = "PAY_PER_REQUEST"
+  hash_key     = "path"
+
+  attribute {
+    name = "path"
+    type = "S"
+  }
+
+  point_in_time_recovery {
+    enabled = true
+  }
+
+  tags = {
+    Name        = "Homelab Config Paths Table"
+    Project     = "homelab-manager"
+    Environment = var.environment
+  }
+}
+
+# One item per module a server imports, so servers can be found by module path
+resource "aws_dynamodb_table" "homelab_server_modules" {
+  name         = "homelab-server-modules"
+  billing_mode = "PAY_PER_REQUEST"
+  hash_key     = "module"
+  range_key    = "server_id"
+
+  attribute {
+    name = "module"
+    type = "S"
+  }
+
+  attribute {
+    name = "server_id"
+    type = "S"
+  }
+
+  point_in_time_recovery {
+    enabled = true
+  }
+
+  tags = {
+    Name        = "Homelab Server Modules Table"
+    Project     = "homelab-manager"
+    Environment = var.environment
+  }
+}
+
+# API keys; only a hash of each key's secret is stored
+resource "aws_dynamodb_table" "homelab_api_keys" {
+  name         = "homelab-api-keys"
+  billing_mode = "PAY_PER_REQUEST"
+  hash_key     = "key_id"
+
+  attribute {
+    name = "key_id"
+    type = "S"
+  }
+
+  point_in_time_recovery {
+    enabled = true
+  }
+
+  tags = {
+    Name        = "Homelab API Keys Table"
+    Project     = "homelab-manager"
+    Environment = var.environment
+  }
+}
+
+# Audit log of every change made through the API, keyed by server and time
+resource "aws_dynamodb_table" "homelab_audit_events" {
+  name         = "homelab-audit-events"
+  billing_mode = "PAY_PER_REQUEST"
+  hash_key     = "server_id"
+  range_key    = "event_key"
+
+  attribute {
+    name = "server_id"
+    type = "S"
+  }
+
+  attribute {
+    name = "event_key"
+    type = "S"
+  }
+
+  attribute {
+    name = "log"
+    type = "S"
+  }
+
+  # The whole log in time order; every event has the same `log`
+  global_secondary_index {
+    name            = "log-index"
+    hash_key        = "log"
+    range_key       = "event_key"
+    projection_type = "ALL"
+  }
+
+  point_in_time_recovery {
+    enabled = true
+  }
+
+  tags = {
+    Name        = "Homelab Audit Events Table"
+    Project     = "homelab-manager"
+    Environment = var.environment
+  }
+}
+
 # IAM role for Lambda function
 resource "aws_iam_role" "lambda_role" {
   name = "${var.project_name}-lambda-role"
@@ -88,7 +346,16 @@ resource "aws_iam_policy" "lambda_policy" {
         ]
         Resource = [
           aws_dynamodb_table.homelab_servers.arn,
-          "${aws_dynamodb_table.homelab_servers.arn}/*"
+          "${aws_dynamodb_table.homelab_servers.arn}/*",
+          aws_dynamodb_table.homelab_server_names.arn,
+          aws_dynamodb_table.homelab_server_revisions.arn,
+          aws_dynamodb_table.homelab_audit_events.arn,
+          "${aws_dynamodb_table.homelab_audit_events.arn}/*",
+          aws_dynamodb_table.homelab_config_files.arn,
+          "${aws_dynamodb_table.homelab_config_files.arn}/*",
+          aws_dynamodb_table.homelab_config_paths.arn,
+          aws_dynamodb_table.homelab_server_modules.arn,
+          aws_dynamodb_table.homelab_api_keys.arn
         ]
       }
     ]
@@ -149,6 +416,90 @@ resource "aws_api_gateway_resource" "servers_id" {
   path_part   = "{id}"
 }
 
+# API Gateway resource for /servers/by-name
+resource "aws_api_gateway_resource" "servers_by_name" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_resource.servers.id
+  path_part   = "by-name"
+}
+
+# API Gateway resource for /servers/by-name/{name}
+resource "aws_api_gateway_resource" "servers_by_name_name" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_resource.servers_by_name.id
+  path_part   = "{name}"
+}
+
+# API Gateway resource for /servers/{id}/history
+resource "aws_api_gateway_resource" "servers_id_history" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_resource.servers_id.id
+  path_part   = "history"
+}
+
+# API Gateway resource for /servers/{id}/rollback
+resource "aws_api_gateway_resource" "servers_id_rollback" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_resource.servers_id.id
+  path_part   = "rollback"
+}
+
+# API Gateway resource for /servers/{id}/restore
+resource "aws_api_gateway_resource" "servers_id_restore" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_resource.servers_id.id
+  path_part   = "restore"
+}
+
+# API Gateway resource for /audit
+resource "aws_api_gateway_resource" "audit" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_rest_api.homelab_api.root_resource_id
+  path_part   = "audit"
+}
+
+# API Gateway resource for /keys
+resource "aws_api_gateway_resource" "keys" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_rest_api.homelab_api.root_resource_id
+  path_part   = "keys"
+}
+
+# API Gateway resource for /keys/{id}
+resource "aws_api_gateway_resource" "keys_id" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_resource.keys.id
+  path_part   = "{id}"
+}
+
+# API Gateway resource for /whoami
+resource "aws_api_gateway_resource" "whoami" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_rest_api.homelab_api.root_resource_id
+  path_part   = "whoami"
+}
+
+# API Gateway resource for /configs
+resource "aws_api_gateway_resource" "configs" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_rest_api.homelab_api.root_resource_id
+  path_part   = "configs"
+}
+
+# API Gateway resource for /configs/{config} (a config file ID or percent-encoded path)
+resource "aws_api_gateway_resource" "configs_config" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_resource.configs.id
+  path_part   = "{config}"
+}
+
+# API Gateway resource for /configs/{config}/servers
+resource "aws_api_gateway_resource" "configs_config_servers" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  parent_id   = aws_api_gateway_resource.configs_config.id
+  path_part   = "servers"
+}
+
 # API Gateway method for POST /servers (create)
 resource "aws_api_gateway_method" "servers_post" {
   rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
@@ -165,6 +516,22 @@ resource "aws_api_gateway_method" "servers_get" {
   authorization = "NONE"
 }
 
+# API Gateway method for GET /servers/{id} (get)
+resource "aws_api_gateway_method" "servers_id_get" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.servers_id.id
+  http_method   = "GET"
+  authorization = "NONE"
+}
+
+# API Gateway method for GET /servers/by-name/{name} (lookup by name)
+resource "aws_api_gateway_method" "servers_by_name_get" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.servers_by_name_name.id
+  http_method   = "GET"
+  authorization = "NONE"
+}
+
 # API Gateway method for PUT /servers/{id} (update)
 resource "aws_api_gateway_method" "servers_id_put" {
   rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
@@ -181,6 +548,110 @@ resource "aws_api_gateway_method" "servers_id_delete" {
   authorization = "NONE"
 }
 
+# API Gateway method for GET /servers/{id}/history (revision history)
+resource "aws_api_gateway_method" "servers_id_history_get" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.servers_id_history.id
+  http_method   = "GET"
+  authorization = "NONE"
+}
+
+# API Gateway method for POST /servers/{id}/rollback (restore a revision)
+resource "aws_api_gateway_method" "servers_id_rollback_post" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.servers_id_rollback.id
+  http_method   = "POST"
+  authorization = "NONE"
+}
+
+# API Gateway method for POST /servers/{id}/restore (take out of the trash)
+resource "aws_api_gateway_method" "servers_id_restore_post" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.servers_id_restore.id
+  http_method   = "POST"
+  authorization = "NONE"
+}
+
+# API Gateway method for GET /audit (audit log)
+resource "aws_api_gateway_method" "audit_get" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.audit.id
+  http_method   = "GET"
+  authorization = "NONE"
+}
+
+# API Gateway method for GET /configs/{config}/servers (servers using a config file)
+resource "aws_api_gateway_method" "configs_config_servers_get" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.configs_config_servers.id
+  http_method   = "GET"
+  authorization = "NONE"
+}
+
+# API Gateway method for POST /configs (register config file)
+resource "aws_api_gateway_method" "configs_post" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.configs.id
+  http_method   = "POST"
+  authorization = "NONE"
+}
+
+# API Gateway method for GET /configs (list config files)
+resource "aws_api_gateway_method" "configs_get" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.configs.id
+  http_method   = "GET"
+  authorization = "NONE"
+}
+
+# API Gateway method for GET /configs/{config} (get config file)
+resource "aws_api_gateway_method" "configs_config_get" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.configs_config.id
+  http_method   = "GET"
+  authorization = "NONE"
+}
+
+# API Gateway method for DELETE /configs/{config} (delete config file)
+resource "aws_api_gateway_method" "configs_config_delete" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.configs_config.id
+  http_method   = "DELETE"
+  authorization = "NONE"
+}
+
+# API Gateway method for POST /keys (create API key; the Lambda checks the caller's bearer token)
+resource "aws_api_gateway_method" "keys_post" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.keys.id
+  http_method   = "POST"
+  authorization = "NONE"
+}
+
+# API Gateway method for GET /keys (list API keys)
+resource "aws_api_gateway_method" "keys_get" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.keys.id
+  http_method   = "GET"
+  authorization = "NONE"
+}
+
+# API Gateway method for DELETE /keys/{id} (revoke API key)
+resource "aws_api_gateway_method" "keys_id_delete" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.keys_id.id
+  http_method   = "DELETE"
+  authorization = "NONE"
+}
+
+# API Gateway method for GET /whoami (show the caller and its role)
+resource "aws_api_gateway_method" "whoami_get" {
+  rest_api_id   = aws_api_gateway_rest_api.homelab_api.id
+  resource_id   = aws_api_gateway_resource.whoami.id
+  http_method   = "GET"
+  authorization = "NONE"
+}
+
 # API Gateway integration for Lambda
 resource "aws_api_gateway_integration" "lambda_integration" {
   rest_api_id = aws_api_gateway_rest_api.homelab_api.id
@@ -202,6 +673,26 @@ resource "aws_api_gateway_integration" "lambda_integration_get" {
   uri                     = aws_lambda_function.homelab_lambda.invoke_arn
 }
 
+resource "aws_api_gateway_integration" "lambda_integration_get_id" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.servers_id.id
+  http_method = aws_api_gateway_method.servers_id_get.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_get_by_name" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.servers_by_name_name.id
+  http_method = aws_api_gateway_method.servers_by_name_get.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
 resource "aws_api_gateway_integration" "lambda_integration_put" {
   rest_api_id = aws_api_gateway_rest_api.homelab_api.id
   resource_id = aws_api_gateway_resource.servers_id.id
@@ -222,6 +713,136 @@ resource "aws_api_gateway_integration" "lambda_integration_delete" {
   uri                     = aws_lambda_function.homelab_lambda.invoke_arn
 }
 
+resource "aws_api_gateway_integration" "lambda_integration_get_history" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.servers_id_history.id
+  http_method = aws_api_gateway_method.servers_id_history_get.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_rollback" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.servers_id_rollback.id
+  http_method = aws_api_gateway_method.servers_id_rollback_post.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_restore" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.servers_id_restore.id
+  http_method = aws_api_gateway_method.servers_id_restore_post.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_audit" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.audit.id
+  http_method = aws_api_gateway_method.audit_get.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_get_config_servers" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.configs_config_servers.id
+  http_method = aws_api_gateway_method.configs_config_servers_get.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_add_config_file" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.configs.id
+  http_method = aws_api_gateway_method.configs_post.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_list_config_files" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.configs.id
+  http_method = aws_api_gateway_method.configs_get.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_get_config_file" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.configs_config.id
+  http_method = aws_api_gateway_method.configs_config_get.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_delete_config_file" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.configs_config.id
+  http_method = aws_api_gateway_method.configs_config_delete.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_add_api_key" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.keys.id
+  http_method = aws_api_gateway_method.keys_post.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_list_api_keys" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.keys.id
+  http_method = aws_api_gateway_method.keys_get.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_delete_api_key" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.keys_id.id
+  http_method = aws_api_gateway_method.keys_id_delete.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
+resource "aws_api_gateway_integration" "lambda_integration_whoami" {
+  rest_api_id = aws_api_gateway_rest_api.homelab_api.id
+  resource_id = aws_api_gateway_resource.whoami.id
+  http_method = aws_api_gateway_method.whoami_get.http_method
+
+  integration_http_method = "POST"
+  type                    = "AWS_PROXY"
+  uri                     = aws_lambda_function.homelab_lambda.invoke_arn
+}
+
 # API Gateway deployment
 resource "aws_api_gateway_deployment" "api_deployment" {
   rest_api_id = aws_api_gateway_rest_api.homelab_api.id
@@ -230,14 +851,56 @@ resource "aws_api_gateway_deployment" "api_deployment" {
     redeployment = sha1(jsonencode([
       aws_api_gateway_resource.servers.id,
       aws_api_gateway_resource.servers_id.id,
+      aws_api_gateway_resource.servers_by_name.id,
+      aws_api_gateway_resource.servers_by_name_name.id,
       aws_api_gateway_method.servers_post.id,
       aws_api_gateway_method.servers_get.id,
+      aws_api_gateway_method.servers_id_get.id,
+      aws_api_gateway_method.servers_by_name_get.id,
       aws_api_gateway_method.servers_id_put.id,
       aws_api_gateway_method.servers_id_delete.id,
       aws_api_gateway_integration.lambda_integration.id,
       aws_api_gateway_integration.lambda_integration_get.id,
+      aws_api_gateway_integration.lambda_integration_get_id.id,
+      aws_api_gateway_integration.lambda_integration_get_by_name.id,
       aws_api_gateway_integration.lambda_integration_put.id,
       aws_api_gateway_integration.lambda_integration_delete.id,
+      aws_api_gateway_resource.servers_id_history.id,
+      aws_api_gateway_method.servers_id_history_get.id,
+      aws_api_gateway_integration.lambda_integration_get_history.id,
+      aws_api_gateway_resource.servers_id_rollback.id,
+      aws_api_gateway_method.servers_id_rollback_post.id,
+      aws_api_gateway_integration.lambda_integration_rollback.id,
+      aws_api_gateway_resource.servers_id_restore.id,
+      aws_api_gateway_method.servers_id_restore_post.id,
+      aws_api_gateway_integration.lambda_integration_restore.id,
+      aws_api_gateway_resource.audit.id,
+      aws_api_gateway_method.audit_get.id,
+      aws_api_gateway_integration.lambda_integration_audit.id,
+      aws_api_gateway_resource.configs.id,
+      aws_api_gateway_resource.configs_config.id,
+      aws_api_gateway_resource.configs_config_servers.id,
+      aws_api_gateway_method.configs_config_servers_get.id,
+      aws_api_gateway_integration.lambda_integration_get_config_servers.id,
+      aws_api_gateway_method.configs_post.id,
+      aws_api_gateway_integration.lambda_integration_add_config_file.id,
+      aws_api_gateway_method.configs_get.id,
+      aws_api_gateway_integration.lambda_integration_list_config_files.id,
+      aws_api_gateway_method.configs_config_get.id,
+      aws_api_gateway_integration.lambda_integration_get_config_file.id,
+      aws_api_gateway_method.configs_config_delete.id,
+      aws_api_gateway_integration.lambda_integration_delete_config_file.id,
+      aws_api_gateway_resource.keys.id,
+      aws_api_gateway_resource.keys_id.id,
+      aws_api_gateway_method.keys_post.id,
+      aws_api_gateway_integration.lambda_integration_add_api_key.id,
+      aws_api_gateway_method.keys_get.id,
+      aws_api_gateway_integration.lambda_integration_list_api_keys.id,
+      aws_api_gateway_method.keys_id_delete.id,
+      aws_api_gateway_integration.lambda_integration_delete_api_key.id,
+      aws_api_gateway_resource.whoami.id,
+      aws_api_gateway_method.whoami_get.id,
+      aws_api_gateway_integration.lambda_integration_whoami.id,
     ]))
   }
 
@@ -248,12 +911,42 @@ resource "aws_api_gateway_deployment" "api_deployment" {
   depends_on = [
     aws_api_gateway_method.servers_post,
     aws_api_gateway_method.servers_get,
+    aws_api_gateway_method.servers_id_get,
+    aws_api_gateway_method.servers_by_name_get,
     aws_api_gateway_method.servers_id_put,
     aws_api_gateway_method.servers_id_delete,
     aws_api_gateway_integration.lambda_integration,
     aws_api_gateway_integration.lambda_integration_get,
+    aws_api_gateway_integration.lambda_integration_get_id,
+    aws_api_gateway_integration.lambda_integration_get_by_name,
     aws_api_gateway_integration.lambda_integration_put,
     aws_api_gateway_integration.lambda_integration_delete,
+    aws_api_gateway_method.servers_id_history_get,
+    aws_api_gateway_integration.lambda_integration_get_history,
+    aws_api_gateway_method.servers_id_rollback_post,
+    aws_api_gateway_integration.lambda_integration_rollback,
+    aws_api_gateway_method.servers_id_restore_post,
+    aws_api_gateway_integration.lambda_integration_restore,
+    aws_api_gateway_method.audit_get,
+    aws_api_gateway_integration.lambda_integration_audit,
+    aws_api_gateway_method.configs_config_servers_get,
+    aws_api_gateway_integration.lambda_integration_get_config_servers,
+    aws_api_gateway_method.configs_post,
+    aws_api_gateway_integration.lambda_integration_add_config_file,
+    aws_api_gateway_method.configs_get,
+    aws_api_gateway_integration.lambda_integration_list_config_files,
+    aws_api_gateway_method.configs_config_get,
+    aws_api_gateway_integration.lambda_integration_get_config_file,
+    aws_api_gateway_method.configs_config_delete,
+    aws_api_gateway_integration.lambda_integration_delete_config_file,
+    aws_api_gateway_method.keys_post,
+    aws_api_gateway_integration.lambda_integration_add_api_key,
+    aws_api_gateway_method.keys_get,
+    aws_api_gateway_integration.lambda_integration_list_api_keys,
+    aws_api_gateway_method.keys_id_delete,
+    aws_api_gateway_integration.lambda_integration_delete_api_key,
+    aws_api_gateway_method.whoami_get,
+    aws_api_gateway_integration.lambda_integration_whoami,
   ]
 }
 
@@ -283,7 +976,7 @@ resource "aws_lambda_permission" "api_gateway_permission" {
 # Build Lambda function zip file
 data "archive_file" "lambda_zip" {
   type        = "zip"
-  source_dir  = "${path.root}/../lambda/target/lambda/bootstrap/release/"
+  source_dir  = "${path.root}/../target/lambda/bootstrap/release/"
   output_path = "${path.root}/lambda_function.zip"
 
   depends_on = [null_resource.build_lambda]
@@ -311,8 +1004,16 @@ resource "aws_lambda_function" "homelab_lambda" {
 
   environment {
     variables = {
-      TABLE_NAME = aws_dynamodb_table.homelab_servers.name
-      RUST_LOG   = "info"
+      TABLE_NAME              = aws_dynamodb_table.homelab_servers.name
+      NAMES_TABLE_NAME        = aws_dynamodb_table.homelab_server_names.name
+      REVISIONS_TABLE_NAME    = aws_dynamodb_table.homelab_server_revisions.name
+      AUDIT_TABLE_NAME        = aws_dynamodb_table.homelab_audit_events.name
+      CONFIG_FILES_TABLE_NAME = aws_dynamodb_table.homelab_config_files.name
+      CONFIG_PATHS_TABLE_NAME = aws_dynamodb_table.homelab_config_paths.name
+      MODULES_TABLE_NAME      = aws_dynamodb_table.homelab_server_modules.name
+      API_KEYS_TABLE_NAME     = aws_dynamodb_table.homelab_api_keys.name
+      TRASH_RETENTION_DAYS    = tostring(var.trash_retention_days)
+      RUST_LOG                = "info"
     }
   }
 
@@ -326,4 +1027,62 @@ resource "aws_lambda_function" "homelab_lambda" {
     aws_cloudwatch_log_group.lambda_log_group,
     null_resource.build_lambda
   ]
+}
+
+# CloudWatch Log Group for the trash purge
+resource "aws_cloudwatch_log_group" "purge_trash_log_group" {
+  name              = "/aws/lambda/${var.project_name}-purge-trash"
+  retention_in_days = 14
+
+  tags = {
+    Name        = "${var.project_name}-purge-trash-logs"
+    Project     = var.project_name
+    Environment = var.environment
+  }
+}
+
+# The same binary, run with handler "purge-trash", purges expired servers from
+# the trash: releasing their name, path and module claims and recording the
+# purge in one transaction
+resource "aws_lambda_function" "purge_trash" {
+  filename      = data.archive_file.lambda_zip.output_path
+  function_name = "${var.project_name}-purge-trash"
+  role          = aws_iam_role.lambda_role.arn
+  handler       = "purge-trash"
+  runtime       = "provided.al2023"
+  timeout       = 300
+
+  environment {
+    variables = aws_lambda_function.homelab_lambda.environment[0].variables
+  }
+
+  tags = {
+    Name        = "${var.project_name}-purge-trash"
+    Project     = var.project_name
+    Environment = var.environment
+  }
+
+  depends_on = [
+    aws_cloudwatch_log_group.purge_trash_log_group,
+    null_resource.build_lambda
+  ]
+}
+
+resource "aws_cloudwatch_event_rule" "purge_trash_schedule" {
+  name                = "${var.project_name}-purge-trash"
+  description         = "Purge expired servers from the trash"
+  schedule_expression = "rate(1 hour)"
+}
+
+resource "aws_cloudwatch_event_target" "purge_trash" {
+  rule = aws_cloudwatch_event_rule.purge_trash_schedule.name
+  arn  = aws_lambda_function.purge_trash.arn
+}
+
+resource "aws_lambda_permission" "purge_trash_schedule_permission" {
+  statement_id  = "AllowExecutionFromEventBridge"
+  action        = "lambda:InvokeFunction"
+  function_name = aws_lambda_function.purge_trash.function_name
+  principal     = "events.amazonaws.com"
+  source_arn    = aws_cloudwatch_event_rule.purge_trash_schedule.arn
 }
\ No newline at end of file
@@ -8,11 +8,46 @@ output "dynamodb_table_name" {
   value       = aws_dynamodb_table.homelab_servers.name
 }
 
+output "dynamodb_names_table_name" {
+  description = "Name of the DynamoDB table enforcing unique server names"
+  value       = aws_dynamodb_table.homelab_server_names.name
+}
+
+output "dynamodb_revisions_table_name" {
+  description = "Name of the DynamoDB table holding server revision history"
+  value       = aws_dynamodb_table.homelab_server_revisions.name
+}
+
+output "dynamodb_audit_table_name" {
+  description = "Name of the DynamoDB table holding the audit log"
+  value       = aws_dynamodb_table.homelab_audit_events.name
+}
+
+output "dynamodb_config_files_table_name" {
+  description = "Name of the DynamoDB table holding registered config files"
+  value       = aws_dynamodb_table.homelab_config_files.name
+}
+
+output "dynamodb_config_paths_table_name" {
+  description = "Name of the DynamoDB table enforcing unique config file paths"
+  value       = aws_dynamodb_table.homelab_config_paths.name
+}
+
+output "dynamodb_modules_table_name" {
+  description = "Name of the DynamoDB table indexing servers by imported module"
+  value       = aws_dynamodb_table.homelab_server_modules.name
+}
+
 output "lambda_function_name" {
   description = "Name of the Lambda function"
   value       = aws_lambda_function.homelab_lambda.function_name
 }
 
+output "purge_trash_function_name" {
+  description = "Name of the Lambda function that purges the trash on a schedule"
+  value       = aws_lambda_function.purge_trash.function_name
+}
+
 output "lambda_function_arn" {
   description = "ARN of the Lambda function"
   value       = aws_lambda_function.homelab_lambda.arn
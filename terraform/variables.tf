@@ -16,6 +16,12 @@ variable "environment" {
   default     = "dev"
 }
 
+variable "trash_retention_days" {
+  description = "Days a deleted server stays in the trash before it is purged"
+  type        = number
+  default     = 30
+}
+
 variable "tags" {
   description = "Common tags to apply to all resources"
   type        = map(string)